@@ -1,10 +1,13 @@
 use actix_easy_multipart::{tempfile::Tempfile, text::Text, MultipartForm};
+use chrono::{DateTime, Utc};
 use domain_storage::model::{
-    entity::{FileType, MoveRegistration, RecordNetDiskKind},
+    entity::{FileType, MoveRegistration, NetDisk, NetDiskMeta, RecordNetDiskKind},
     vo::{HashAlgorithm, MoveDestination, RecordNetDisk},
 };
 use domain_workflow::model::entity::queue::{QueueCacheInfo, QueueResourceUsed, QueueTaskCount};
 use serde::{Deserialize, Serialize};
+
+use crate::infrastructure::ArchiveFormat;
 use uuid::Uuid;
 
 #[derive(Deserialize)]
@@ -66,10 +69,27 @@ pub struct PreparePartialUpload {
     pub file_metadata_id: Option<Uuid>,
     pub size: u64,
     pub count: u64,
+    /// Upload parts to the object storage directly with presigned urls.
+    #[serde(default)]
+    pub direct: bool,
     #[serde(flatten)]
     pub r#type: PreparePartialUploadFrom,
 }
 
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum PreparePartialUploadResponse {
+    /// Meta id of the file, parts are uploaded with `PartialUpload`.
+    Cached(Uuid),
+    /// Parts are uploaded with `PUT` requests to the urls, then `CompleteDirectUpload` is called.
+    #[serde(rename_all = "camelCase")]
+    Direct {
+        file_metadata_id: Uuid,
+        part_urls: Vec<String>,
+        expires_at: DateTime<Utc>,
+    },
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(untagged)]
 pub enum PreparePartialUploadFrom {
@@ -91,12 +111,16 @@ pub enum PreparePartialUploadFrom {
 #[serde(rename_all = "camelCase")]
 pub enum FileHashAlgorithm {
     Blake3,
+    Sha256,
+    Xxh3,
 }
 
 impl From<HashAlgorithm> for FileHashAlgorithm {
     fn from(value: HashAlgorithm) -> Self {
         match value {
             HashAlgorithm::Blake3 => Self::Blake3,
+            HashAlgorithm::Sha256 => Self::Sha256,
+            HashAlgorithm::Xxh3 => Self::Xxh3,
         }
     }
 }
@@ -104,6 +128,8 @@ impl From<FileHashAlgorithm> for HashAlgorithm {
     fn from(value: FileHashAlgorithm) -> Self {
         match value {
             FileHashAlgorithm::Blake3 => Self::Blake3,
+            FileHashAlgorithm::Sha256 => Self::Sha256,
+            FileHashAlgorithm::Xxh3 => Self::Xxh3,
         }
     }
 }
@@ -218,3 +244,96 @@ pub struct GetTextByIdResponse {
     pub key: String,
     pub value: String,
 }
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListNetDiskRequest {
+    /// The user's root directory if not provided.
+    pub parent_id: Option<Uuid>,
+    #[serde(default = "ListNetDiskRequest::default_page")]
+    pub page: u64,
+    #[serde(default = "ListNetDiskRequest::default_page_size")]
+    pub page_size: u64,
+}
+
+impl ListNetDiskRequest {
+    fn default_page() -> u64 {
+        1
+    }
+    fn default_page_size() -> u64 {
+        50
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetDiskFileResponse {
+    pub id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+    pub is_dict: bool,
+    pub kind: FileType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_metadata_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<NetDiskMeta>,
+}
+
+impl From<NetDisk> for NetDiskFileResponse {
+    fn from(value: NetDisk) -> Self {
+        Self {
+            id: value.id,
+            parent_id: value.parent_id,
+            name: value.name,
+            is_dict: value.is_dict,
+            kind: value.kind,
+            file_metadata_id: value.file_metadata_id,
+            meta: value.meta,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListNetDiskResponse {
+    pub items: Vec<NetDiskFileResponse>,
+    pub total: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameNetDiskFileRequest {
+    pub id: Uuid,
+    pub name: String,
+}
+
+/// Move or copy a net disk file.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferNetDiskFileRequest {
+    pub id: Uuid,
+    /// The user's root directory if not provided.
+    pub parent_id: Option<Uuid>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetDiskUsageResponse {
+    pub used: u64,
+    pub file_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractArchiveRequest {
+    pub id: Uuid,
+    /// Directory of the archive if not provided.
+    pub parent_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveRequest {
+    #[serde(default)]
+    pub format: ArchiveFormat,
+}
@@ -1,5 +1,8 @@
 use super::dtos::PreparePartialUpload;
-use crate::api::dtos::{FileHashAlgorithm, GetPartialUploadInfoResponse, PartialUploadRequest};
+use crate::api::dtos::{
+    FileHashAlgorithm, GetPartialUploadInfoResponse, PartialUploadRequest,
+    PreparePartialUploadResponse,
+};
 use crate::api::extract_uuid;
 use crate::infrastructure::ServiceProvider;
 use actix_easy_multipart::MultipartForm;
@@ -12,7 +15,8 @@ use alice_infrastructure::error::{
 };
 use domain_storage::model::vo::Part;
 use domain_storage::service::{
-    FileMoveService, MultipartService, RealtimeService, StorageServerDownloadDispatcherService,
+    DirectUploadService, FileMoveService, MultipartService, RealtimeService,
+    StorageServerDownloadDispatcherService,
 };
 use std::io::Read;
 use std::ops::Range;
@@ -39,11 +43,24 @@ pub async fn upload_realtime_file(
 pub async fn prepare_partial_upload(
     #[inject] move_service: Arc<dyn FileMoveService>,
     #[inject] multipart_service: Arc<dyn MultipartService>,
+    #[inject] direct_upload_service: Arc<dyn DirectUploadService>,
     data: web::Json<PreparePartialUpload>,
-) -> AliceResponderResult<Uuid> {
+) -> AliceResponderResult<PreparePartialUploadResponse> {
     let count = data.count;
+    let direct = data.direct;
     let registration = data.0.into_registration();
     let meta_id = registration.meta_id;
+    if direct {
+        let presigned = direct_upload_service
+            .prepare(registration, count)
+            .await
+            .map_err(AliceError::new)?;
+        return Ok(AliceResponder(PreparePartialUploadResponse::Direct {
+            file_metadata_id: meta_id,
+            part_urls: presigned.part_urls,
+            expires_at: presigned.expires_at,
+        }));
+    }
     move_service
         .if_possible_do_flash_upload(&registration)
         .await
@@ -58,7 +75,9 @@ pub async fn prepare_partial_upload(
         .await
         .map_err(AliceError::new)?;
     move_service.register_move(registration).await.map_err(AliceError::new)?;
-    Ok(AliceResponder(meta_id))
+    Ok(AliceResponder(PreparePartialUploadResponse::Cached(
+        meta_id,
+    )))
 }
 
 #[actix_auto_inject(ServiceProvider, scoped)]
@@ -99,6 +118,25 @@ pub async fn partial_upload(
     })
 }
 
+#[actix_auto_inject(ServiceProvider, scoped)]
+#[post("file-storage/CompleteDirectUpload/{id}")]
+pub async fn complete_direct_upload(
+    #[inject] multipart_service: Arc<dyn MultipartService>,
+    #[inject] move_service: Arc<dyn FileMoveService>,
+    id: Path<String>,
+) -> AliceResponderResult<()> {
+    let id = extract_uuid(&id)?;
+    let multipart = multipart_service.info(id).await.map_err(AliceError::new)?;
+    if multipart.direct_upload.is_none() {
+        return Err(AliceError::new(AliceCommonError::InvalidRequest {
+            error_description: format!("File {id} isn't uploaded directly."),
+        }));
+    }
+    // The upload is assembled when the file is moved, as cached parts are uploaded then.
+    move_service.do_registered_moves(id).await.map_err(AliceError::new)?;
+    Ok(AliceResponder(()))
+}
+
 #[actix_auto_inject(ServiceProvider, scoped)]
 #[get("/file-storage/PartialUploadInfo/{id}")]
 pub async fn get_partial_upload_info(
@@ -284,9 +322,11 @@ pub async fn get_rangely_download_file(
 pub async fn cancel_partial_upload(
     #[inject] multipart_service: Arc<dyn MultipartService>,
     #[inject] move_service: Arc<dyn FileMoveService>,
+    #[inject] direct_upload_service: Arc<dyn DirectUploadService>,
     id: Path<String>,
 ) -> AliceResponderResult<()> {
     let id = extract_uuid(&id)?;
+    direct_upload_service.abort(id).await.map_err(AliceError::new)?;
     multipart_service.remove(id).await.map_err(AliceError::new)?;
     move_service.remove_all_with_meta_id(id).await.map_err(AliceError::new)?;
     Ok(AliceResponder(()))
@@ -6,6 +6,7 @@ use uuid::Uuid;
 pub mod agent;
 pub mod dtos;
 pub mod file_storage;
+pub mod net_disk;
 pub mod snapshot;
 pub mod text_storage;
 pub mod usecase_editor;
@@ -0,0 +1,192 @@
+use actix_http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{
+    get, post,
+    web::{self, Path, Query},
+    HttpResponse,
+};
+use alice_di::{actix_auto_inject, IServiceProvider};
+use alice_infrastructure::error::{AliceError, AliceResponder, AliceResponderResult};
+use domain_storage::{
+    model::vo::ArchiveTarget,
+    service::{ArchiveExtractService, NetDiskService},
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        dtos::{
+            ArchiveRequest, ExtractArchiveRequest, ListNetDiskRequest, ListNetDiskResponse,
+            NetDiskUsageResponse, RenameNetDiskFileRequest, TransferNetDiskFileRequest,
+        },
+        extract_uuid,
+    },
+    infrastructure::{ArchiveWriter, ServiceProvider},
+};
+
+/// list files in a net disk directory
+#[actix_auto_inject(ServiceProvider, scoped)]
+#[get("file-storage/ListNetDisk")]
+pub async fn list_net_disk(
+    #[inject] net_disk_service: Arc<dyn NetDiskService>,
+    request: Query<ListNetDiskRequest>,
+) -> AliceResponderResult<ListNetDiskResponse> {
+    let page = net_disk_service
+        .list(request.parent_id, request.page, request.page_size)
+        .await
+        .map_err(AliceError::new)?;
+    Ok(AliceResponder(ListNetDiskResponse {
+        items: page.items.into_iter().map(From::from).collect(),
+        total: page.total,
+    }))
+}
+
+/// rename net disk file
+#[actix_auto_inject(ServiceProvider, scoped)]
+#[post("file-storage/RenameNetDiskFile")]
+pub async fn rename_net_disk_file(
+    #[inject] net_disk_service: Arc<dyn NetDiskService>,
+    data: web::Json<RenameNetDiskFileRequest>,
+) -> AliceResponderResult<()> {
+    net_disk_service.rename(data.id, &data.name).await.map_err(AliceError::new)?;
+    Ok(AliceResponder(()))
+}
+
+/// move net disk file
+#[actix_auto_inject(ServiceProvider, scoped)]
+#[post("file-storage/MoveNetDiskFile")]
+pub async fn move_net_disk_file(
+    #[inject] net_disk_service: Arc<dyn NetDiskService>,
+    data: web::Json<TransferNetDiskFileRequest>,
+) -> AliceResponderResult<()> {
+    net_disk_service
+        .move_to(data.id, data.parent_id)
+        .await
+        .map_err(AliceError::new)?;
+    Ok(AliceResponder(()))
+}
+
+/// copy net disk file
+#[actix_auto_inject(ServiceProvider, scoped)]
+#[post("file-storage/CopyNetDiskFile")]
+pub async fn copy_net_disk_file(
+    #[inject] net_disk_service: Arc<dyn NetDiskService>,
+    data: web::Json<TransferNetDiskFileRequest>,
+) -> AliceResponderResult<Uuid> {
+    let id = net_disk_service.copy(data.id, data.parent_id).await.map_err(AliceError::new)?;
+    Ok(AliceResponder(id))
+}
+
+/// delete net disk file
+#[actix_auto_inject(ServiceProvider, scoped)]
+#[get("file-storage/DeleteNetDiskFile/{id}")]
+pub async fn delete_net_disk_file(
+    #[inject] net_disk_service: Arc<dyn NetDiskService>,
+    id: Path<String>,
+) -> AliceResponderResult<()> {
+    let id = extract_uuid(&id)?;
+    net_disk_service.delete(id).await.map_err(AliceError::new)?;
+    Ok(AliceResponder(()))
+}
+
+/// get storage used by net disk
+#[actix_auto_inject(ServiceProvider, scoped)]
+#[get("file-storage/NetDiskUsage")]
+pub async fn get_net_disk_usage(
+    #[inject] net_disk_service: Arc<dyn NetDiskService>,
+) -> AliceResponderResult<NetDiskUsageResponse> {
+    let usage = net_disk_service.get_usage().await.map_err(AliceError::new)?;
+    Ok(AliceResponder(NetDiskUsageResponse {
+        used: usage.used,
+        file_count: usage.file_count,
+    }))
+}
+
+/// extract a net disk archive into a new directory in background, returns id of the directory
+#[actix_auto_inject(ServiceProvider, scoped)]
+#[post("file-storage/ExtractNetDiskArchive")]
+pub async fn extract_net_disk_archive(
+    #[inject] archive_extract_service: Arc<dyn ArchiveExtractService>,
+    data: web::Json<ExtractArchiveRequest>,
+) -> AliceResponderResult<Uuid> {
+    let id = archive_extract_service
+        .extract(data.id, data.parent_id)
+        .await
+        .map_err(AliceError::new)?;
+    Ok(AliceResponder(id))
+}
+
+/// download a net disk directory as an archive
+#[actix_auto_inject(ServiceProvider, scoped)]
+#[get("file-storage/NetDiskArchive/{id}")]
+pub async fn download_net_disk_archive(
+    #[inject] net_disk_service: Arc<dyn NetDiskService>,
+    #[inject] archive_writer: Arc<ArchiveWriter>,
+    id: Path<String>,
+    request: Query<ArchiveRequest>,
+) -> actix_web::error::Result<HttpResponse> {
+    let id = extract_uuid(&id)?;
+    archive_response(
+        net_disk_service,
+        archive_writer,
+        ArchiveTarget::NetDisk { id },
+        request.0,
+    )
+    .await
+}
+
+/// download outputs of a workflow instance as an archive
+#[actix_auto_inject(ServiceProvider, scoped)]
+#[get("file-storage/FlowInstanceArchive/{id}")]
+pub async fn download_flow_instance_archive(
+    #[inject] net_disk_service: Arc<dyn NetDiskService>,
+    #[inject] archive_writer: Arc<ArchiveWriter>,
+    id: Path<String>,
+    request: Query<ArchiveRequest>,
+) -> actix_web::error::Result<HttpResponse> {
+    let id = extract_uuid(&id)?;
+    archive_response(
+        net_disk_service,
+        archive_writer,
+        ArchiveTarget::FlowInstance { id },
+        request.0,
+    )
+    .await
+}
+
+/// download outputs of a node instance as an archive
+#[actix_auto_inject(ServiceProvider, scoped)]
+#[get("file-storage/NodeInstanceArchive/{id}")]
+pub async fn download_node_instance_archive(
+    #[inject] net_disk_service: Arc<dyn NetDiskService>,
+    #[inject] archive_writer: Arc<ArchiveWriter>,
+    id: Path<String>,
+    request: Query<ArchiveRequest>,
+) -> actix_web::error::Result<HttpResponse> {
+    let id = extract_uuid(&id)?;
+    archive_response(
+        net_disk_service,
+        archive_writer,
+        ArchiveTarget::NodeInstance { id },
+        request.0,
+    )
+    .await
+}
+
+async fn archive_response(
+    net_disk_service: Arc<dyn NetDiskService>,
+    archive_writer: Arc<ArchiveWriter>,
+    target: ArchiveTarget,
+    request: ArchiveRequest,
+) -> actix_web::error::Result<HttpResponse> {
+    let format = request.format;
+    let listing = net_disk_service.get_archive_listing(target).await.map_err(AliceError::new)?;
+    let file_name = format!("{}.{}", listing.name, format.extension());
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        })
+        .streaming(archive_writer.stream(listing, format)))
+}
@@ -30,7 +30,7 @@ pub async fn validate_workflow_draft(
     Ok(AliceResponder(()))
 }
 
-#[actix_auto_inject(ServiceProvider)]
+#[actix_auto_inject(ServiceProvider, scoped)]
 #[tracing::instrument(skip(sp))]
 #[get("workflow-editor/GetNodeDraft")]
 pub async fn get_node_draft(
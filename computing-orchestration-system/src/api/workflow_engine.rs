@@ -89,3 +89,14 @@ pub async fn terminate_workflow(
     service.terminate(id).await.map_err(AliceError::new)?;
     Ok(AliceResponder(()))
 }
+
+#[actix_auto_inject(ServiceProvider, scoped)]
+#[get("workflow-engine/RetryWorkflow/{id}")]
+pub async fn retry_workflow(
+    #[inject] service: Arc<dyn ControlService>,
+    id: Path<String>,
+) -> AliceResponderResult<()> {
+    let id = extract_uuid(&id)?;
+    service.retry_from_failure(id).await.map_err(AliceError::new)?;
+    Ok(AliceResponder(()))
+}
@@ -1,4 +1,6 @@
 use alice_infrastructure::config::CommonConfig;
+use domain_storage::model::vo::PlacementConfig;
+use domain_workflow::model::vo::{quota::QuotaConfig, QueueScoring};
 use serde::Deserialize;
 use uuid::Uuid;
 
@@ -12,10 +14,43 @@ pub struct CoConfig {
     pub bill_topic: String,
     #[serde(default = "default_co_repo_domain")]
     pub co_repo_domain: String,
+    /// Where software and usecase packages are served from, the co-repo by default.
+    #[serde(default)]
+    pub package_registry: PackageRegistryConfig,
+    /// Cache of packages fetched from the package registry.
+    #[serde(default)]
+    pub package_cache: PackageCacheConfig,
     #[serde(default)]
     pub internal_topics: InternalTopics,
+    /// Polling of failed tasks whose retry backoff has ended.
+    #[serde(default)]
+    pub task_retry: TaskRetryConfig,
+    /// Periodic drain of the scheduler backlog, besides the drains when capacity is freed.
+    #[serde(default)]
+    pub backlog_drain: BacklogDrainConfig,
     #[serde(default)]
     pub web_socket: WebSocketConfig,
+    /// Queue scoring used when a node's scheduling strategy doesn't specify one.
+    #[serde(default)]
+    pub queue_scoring: QueueScoring,
+    /// Per user and per project resource quotas, not limited by default.
+    #[serde(default)]
+    pub quotas: QuotaConfig,
+    /// Placement and replication of uploaded files, only the default storage server by default.
+    #[serde(default)]
+    pub storage_placement: PlacementConfig,
+    /// Collection of files that nothing references, disabled by default.
+    #[serde(default)]
+    pub file_gc: FileGcConfig,
+    /// Cleanup of abandoned multipart uploads.
+    #[serde(default)]
+    pub upload_sweep: UploadSweepConfig,
+    /// Uploads to object storage servers with presigned urls, disabled by default.
+    #[serde(default)]
+    pub direct_upload: DirectUploadConfig,
+    /// Limits of archives extracted in net disk.
+    #[serde(default)]
+    pub archive_extract: ArchiveExtractConfig,
 }
 
 #[derive(Clone, Deserialize, Debug)]
@@ -26,6 +61,16 @@ pub struct InternalTopics {
     pub file_upload: String,
     #[serde(default = "InternalTopics::default_status")]
     pub status: String,
+    #[serde(default = "InternalTopics::default_backlog")]
+    pub backlog: String,
+    #[serde(default = "InternalTopics::default_file_gc")]
+    pub file_gc: String,
+    #[serde(default = "InternalTopics::default_upload_sweep")]
+    pub upload_sweep: String,
+    #[serde(default = "InternalTopics::default_task_retry")]
+    pub task_retry: String,
+    #[serde(default = "InternalTopics::default_archive_extract")]
+    pub archive_extract: String,
     #[serde(default)]
     pub ws_messages: WebSocketMessageTopics,
 }
@@ -40,6 +85,21 @@ impl InternalTopics {
     fn default_status() -> String {
         "status".to_string()
     }
+    fn default_backlog() -> String {
+        "scheduler-backlog".to_string()
+    }
+    fn default_file_gc() -> String {
+        "file-gc".to_string()
+    }
+    fn default_upload_sweep() -> String {
+        "upload-sweep".to_string()
+    }
+    fn default_task_retry() -> String {
+        "task-retry".to_string()
+    }
+    fn default_archive_extract() -> String {
+        "archive-extract".to_string()
+    }
 }
 
 impl Default for InternalTopics {
@@ -48,6 +108,11 @@ impl Default for InternalTopics {
             web_socket: Default::default(),
             file_upload: Self::default_file_upload(),
             status: Self::default_status(),
+            backlog: Self::default_backlog(),
+            file_gc: Self::default_file_gc(),
+            upload_sweep: Self::default_upload_sweep(),
+            task_retry: Self::default_task_retry(),
+            archive_extract: Self::default_archive_extract(),
             ws_messages: Default::default(),
         }
     }
@@ -73,6 +138,246 @@ impl Default for WebSocketMessageTopics {
     }
 }
 
+#[derive(Clone, Deserialize, Debug)]
+pub struct FileGcConfig {
+    /// Files may be referenced outside of this system, e.g. by content repo packages, so gc
+    /// should be enabled only when that is not the case.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Seconds between two collections.
+    #[serde(default = "FileGcConfig::default_interval_secs")]
+    pub interval_secs: u64,
+    /// Files are never collected within these seconds after their last reference is removed,
+    /// or after they are created if never referenced.
+    #[serde(default = "FileGcConfig::default_grace_secs")]
+    pub grace_secs: i64,
+    /// Max count of files collected in one run.
+    #[serde(default = "FileGcConfig::default_batch_size")]
+    pub batch_size: u64,
+}
+
+impl FileGcConfig {
+    fn default_interval_secs() -> u64 {
+        60 * 60
+    }
+    fn default_grace_secs() -> i64 {
+        7 * 24 * 60 * 60
+    }
+    fn default_batch_size() -> u64 {
+        100
+    }
+}
+
+impl Default for FileGcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: Self::default_interval_secs(),
+            grace_secs: Self::default_grace_secs(),
+            batch_size: Self::default_batch_size(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct TaskRetryConfig {
+    /// Seconds between two polls, the precision of retry backoff.
+    #[serde(default = "TaskRetryConfig::default_interval_secs")]
+    pub interval_secs: u64,
+}
+
+impl TaskRetryConfig {
+    fn default_interval_secs() -> u64 {
+        10
+    }
+}
+
+impl Default for TaskRetryConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: Self::default_interval_secs(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct BacklogDrainConfig {
+    /// Seconds between two drains.
+    #[serde(default = "BacklogDrainConfig::default_interval_secs")]
+    pub interval_secs: u64,
+}
+
+impl BacklogDrainConfig {
+    fn default_interval_secs() -> u64 {
+        30
+    }
+}
+
+impl Default for BacklogDrainConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: Self::default_interval_secs(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct UploadSweepConfig {
+    #[serde(default = "UploadSweepConfig::default_enabled")]
+    pub enabled: bool,
+    /// Seconds between two sweeps.
+    #[serde(default = "UploadSweepConfig::default_interval_secs")]
+    pub interval_secs: u64,
+    /// Uploads without a completed part within these seconds are swept, and their tasks are
+    /// failed. It should be shorter than `redis.exp_msecs`, the lease of multipart records.
+    #[serde(default = "UploadSweepConfig::default_session_ttl_secs")]
+    pub session_ttl_secs: i64,
+}
+
+impl UploadSweepConfig {
+    fn default_enabled() -> bool {
+        true
+    }
+    fn default_interval_secs() -> u64 {
+        30 * 60
+    }
+    fn default_session_ttl_secs() -> i64 {
+        12 * 60 * 60
+    }
+}
+
+impl Default for UploadSweepConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            interval_secs: Self::default_interval_secs(),
+            session_ttl_secs: Self::default_session_ttl_secs(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct DirectUploadConfig {
+    /// Clients must be able to reach `uploadEndpoint` of object storage servers. Direct uploads
+    /// are rejected when `storage_placement.replicas` is more than one.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Seconds before presigned urls of parts expire. It should be shorter than
+    /// `upload_sweep.session_ttl_secs`, as parts uploaded directly don't keep the upload alive.
+    #[serde(default = "DirectUploadConfig::default_url_expire_secs")]
+    pub url_expire_secs: u64,
+    /// Read uploaded files back to verify their hashes, it costs bandwidth between this system
+    /// and storage servers, but files with wrong hashes would break flash uploads otherwise.
+    #[serde(default = "DirectUploadConfig::default_verify_hash")]
+    pub verify_hash: bool,
+}
+
+impl DirectUploadConfig {
+    fn default_url_expire_secs() -> u64 {
+        6 * 60 * 60
+    }
+    fn default_verify_hash() -> bool {
+        true
+    }
+}
+
+impl Default for DirectUploadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url_expire_secs: Self::default_url_expire_secs(),
+            verify_hash: Self::default_verify_hash(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct ArchiveExtractConfig {
+    /// Max total size in bytes of members of an archive, archives are unpacked on local disk.
+    #[serde(default = "ArchiveExtractConfig::default_max_total_size")]
+    pub max_total_size: u64,
+    /// Max count of members of an archive, including directories.
+    #[serde(default = "ArchiveExtractConfig::default_max_entry_count")]
+    pub max_entry_count: u64,
+}
+
+impl ArchiveExtractConfig {
+    fn default_max_total_size() -> u64 {
+        10 * 1024 * 1024 * 1024
+    }
+    fn default_max_entry_count() -> u64 {
+        10000
+    }
+}
+
+impl Default for ArchiveExtractConfig {
+    fn default() -> Self {
+        Self {
+            max_total_size: Self::default_max_total_size(),
+            max_entry_count: Self::default_max_entry_count(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Debug, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PackageRegistryConfig {
+    /// The co-repo at `co_repo_domain`.
+    #[default]
+    CoRepo,
+    /// A package store listed by `index.json` in the directory, for clusters that can't reach
+    /// the co-repo.
+    Store { dir: String },
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct PackageCacheConfig {
+    #[serde(default = "PackageCacheConfig::default_enabled")]
+    pub enabled: bool,
+    /// Directory of cached tarballs.
+    #[serde(default = "PackageCacheConfig::default_dir")]
+    pub dir: String,
+    /// Max count of extracted packages kept in memory.
+    #[serde(default = "PackageCacheConfig::default_memory_capacity")]
+    pub memory_capacity: usize,
+    /// Max total bytes of cached tarballs, the least recently used are evicted first.
+    #[serde(default = "PackageCacheConfig::default_disk_capacity")]
+    pub disk_capacity: u64,
+    /// Seconds a package is used without asking the registry again, unless its hash is pinned.
+    #[serde(default = "PackageCacheConfig::default_unpinned_ttl_secs")]
+    pub unpinned_ttl_secs: u64,
+}
+
+impl PackageCacheConfig {
+    fn default_enabled() -> bool {
+        true
+    }
+    fn default_dir() -> String {
+        "package_cache".to_string()
+    }
+    fn default_memory_capacity() -> usize {
+        64
+    }
+    fn default_disk_capacity() -> u64 {
+        4 * 1024 * 1024 * 1024
+    }
+    fn default_unpinned_ttl_secs() -> u64 {
+        5 * 60
+    }
+}
+
+impl Default for PackageCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            dir: Self::default_dir(),
+            memory_capacity: Self::default_memory_capacity(),
+            disk_capacity: Self::default_disk_capacity(),
+            unpinned_ttl_secs: Self::default_unpinned_ttl_secs(),
+        }
+    }
+}
+
 #[derive(Clone, Deserialize, Debug)]
 pub struct WebSocketConfig {
     pub keep_alive: u64,
@@ -0,0 +1,19 @@
+//! Collection columns of the `file_metadata` table.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "file_metadata")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    /// Time the last reference is removed.
+    pub dereferenced_time: Option<DateTimeUtc>,
+    /// Time the meta is tombstoned, its objects are being deleted.
+    pub deleted_time: Option<DateTimeUtc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,24 @@
+//! References to file metas from rows that keep their ids in json, e.g. slots in a workflow
+//! spec, and pins of owners outside the database. Net disk entries reference metas by their
+//! own column.
+
+use sea_orm::entity::prelude::*;
+
+/// Owner kind of references from workflow instance specs.
+pub const WORKFLOW_INSTANCE: &str = "flow_instance";
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "file_reference")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub file_metadata_id: Uuid,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub owner_kind: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub owner_id: Uuid,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
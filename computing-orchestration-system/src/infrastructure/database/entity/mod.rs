@@ -0,0 +1,6 @@
+//! Entities of the columns and tables owned by this system, on top of `database_model`.
+
+pub mod file_metadata_gc;
+pub mod file_reference;
+pub mod storage_usage;
+pub mod task_attempts;
@@ -0,0 +1,16 @@
+//! Bytes stored on each storage server, kept with `file_storage` rows.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "storage_usage")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub storage_server_id: Uuid,
+    pub used: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,21 @@
+//! Retry columns of the `task` table.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "task")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub node_instance_id: Uuid,
+    /// Failed attempts of the task, serialized `TaskAttempt`s.
+    #[sea_orm(column_type = "JsonBinary")]
+    pub attempts: Json,
+    /// Time of the pending retry.
+    pub next_attempt_at: Option<DateTimeUtc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
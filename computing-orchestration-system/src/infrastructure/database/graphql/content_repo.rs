@@ -11,6 +11,14 @@ use reqwest::Client;
 )]
 pub struct GetTarById;
 
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.gql",
+    query_path = "graphql/query.gql",
+    response_derives = "Debug, Serialize, Deserialize, Clone"
+)]
+pub struct GetVersionsBySystemName;
+
 pub struct ContentRepository {
     pub client: Arc<Client>,
     pub url: String,
@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+use crate::infrastructure::database::entity::task_attempts;
+
+const NEXT_ATTEMPT_AT_INDEX: &str = "task_next_attempt_at_idx";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(task_attempts::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(task_attempts::Column::Attempts)
+                            .json_binary()
+                            .not_null()
+                            .default(Expr::cust("'[]'::JSONB")),
+                    )
+                    .add_column_if_not_exists(
+                        ColumnDef::new(task_attempts::Column::NextAttemptAt)
+                            .timestamp_with_time_zone(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name(NEXT_ATTEMPT_AT_INDEX)
+                    .table(task_attempts::Entity)
+                    .col(task_attempts::Column::NextAttemptAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(NEXT_ATTEMPT_AT_INDEX)
+                    .table(task_attempts::Entity)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(task_attempts::Entity)
+                    .drop_column(task_attempts::Column::Attempts)
+                    .drop_column(task_attempts::Column::NextAttemptAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,79 @@
+use database_model::{file_metadata, file_storage};
+use sea_orm_migration::prelude::*;
+
+use crate::infrastructure::database::entity::storage_usage;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(storage_usage::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(storage_usage::Column::StorageServerId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(storage_usage::Column::Used)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // Count the files stored before usage was kept.
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(storage_usage::Entity)
+                    .columns([
+                        storage_usage::Column::StorageServerId,
+                        storage_usage::Column::Used,
+                    ])
+                    .select_from(
+                        Query::select()
+                            .column((file_storage::Entity, file_storage::Column::StorageServerId))
+                            .expr(Func::sum(Expr::col((
+                                file_metadata::Entity,
+                                file_metadata::Column::Size,
+                            ))))
+                            .from(file_storage::Entity)
+                            .inner_join(
+                                file_metadata::Entity,
+                                Expr::col((
+                                    file_storage::Entity,
+                                    file_storage::Column::FileMetadataId,
+                                ))
+                                .equals((file_metadata::Entity, file_metadata::Column::Id)),
+                            )
+                            .group_by_col((
+                                file_storage::Entity,
+                                file_storage::Column::StorageServerId,
+                            ))
+                            .to_owned(),
+                    )
+                    .map_err(|e| DbErr::Migration(e.to_string()))?
+                    .on_conflict(
+                        OnConflict::column(storage_usage::Column::StorageServerId)
+                            .do_nothing()
+                            .to_owned(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(storage_usage::Entity).if_exists().to_owned())
+            .await
+    }
+}
@@ -0,0 +1,95 @@
+use sea_orm_migration::prelude::*;
+
+use crate::infrastructure::database::entity::{file_metadata_gc, file_reference};
+
+const OWNER_INDEX: &str = "file_reference_owner_idx";
+
+/// Reference the files used by the slots of existing workflow instances.
+const REFERENCE_WORKFLOW_INSTANCES: &str = "INSERT INTO file_reference
+    (file_metadata_id, owner_kind, owner_id)
+    SELECT DISTINCT (ids.id #>> '{}')::UUID, 'flow_instance', f.id FROM flow_instance f
+    CROSS JOIN LATERAL (
+        SELECT jsonb_path_query(
+            f.spec::JSONB,
+            'lax $.nodeSpecs[*].inputSlots[*].contents[*].fileMetadataId'
+        ) AS id
+        UNION ALL
+        SELECT jsonb_path_query(
+            f.spec::JSONB,
+            'lax $.nodeSpecs[*].outputSlots[*].allTasksPreparedContentIds[*]'
+        )
+    ) ids
+    WHERE jsonb_typeof(ids.id) = 'string'
+    ON CONFLICT DO NOTHING";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(file_metadata_gc::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(file_metadata_gc::Column::DereferencedTime)
+                            .timestamp_with_time_zone(),
+                    )
+                    .add_column_if_not_exists(
+                        ColumnDef::new(file_metadata_gc::Column::DeletedTime)
+                            .timestamp_with_time_zone(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(file_reference::Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(file_reference::Column::FileMetadataId).uuid().not_null())
+                    .col(ColumnDef::new(file_reference::Column::OwnerKind).text().not_null())
+                    .col(ColumnDef::new(file_reference::Column::OwnerId).uuid().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(file_reference::Column::FileMetadataId)
+                            .col(file_reference::Column::OwnerKind)
+                            .col(file_reference::Column::OwnerId),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name(OWNER_INDEX)
+                    .table(file_reference::Entity)
+                    .col(file_reference::Column::OwnerKind)
+                    .col(file_reference::Column::OwnerId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .get_connection()
+            .execute_unprepared(REFERENCE_WORKFLOW_INSTANCES)
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(file_reference::Entity).if_exists().to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(file_metadata_gc::Entity)
+                    .drop_column(file_metadata_gc::Column::DereferencedTime)
+                    .drop_column(file_metadata_gc::Column::DeletedTime)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,25 @@
+//! Schema changes owned by this system, on top of the tables created from `database_model`.
+
+use sea_orm_migration::prelude::*;
+
+mod m20261016_000001_task_attempts;
+mod m20261016_000002_storage_usage;
+mod m20261016_000003_file_reference;
+
+pub struct Migrator;
+
+#[async_trait::async_trait]
+impl MigratorTrait for Migrator {
+    fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+        vec![
+            Box::new(m20261016_000001_task_attempts::Migration),
+            Box::new(m20261016_000002_storage_usage::Migration),
+            Box::new(m20261016_000003_file_reference::Migration),
+        ]
+    }
+
+    /// Applied migrations are tracked apart from the ones of `database_model`.
+    fn migration_table_name() -> DynIden {
+        Alias::new("cos_migrations").into_iden()
+    }
+}
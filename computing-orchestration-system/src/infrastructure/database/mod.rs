@@ -1,3 +1,5 @@
+pub mod entity;
+pub mod migration;
 pub mod orm;
 pub use orm::OrmRepo;
 
@@ -3,13 +3,17 @@ use std::sync::Arc;
 use crate::infrastructure::service::file_upload_runner::FileUploadRunner;
 use alice_di::IServiceProvider;
 use alice_infrastructure::middleware::authorization::{AliceScopedConfig, UserInfo};
-use domain_storage::command::FileUploadCommand;
+use domain_storage::{
+    command::{ExtractArchiveCommand, FileUploadCommand},
+    service::{ArchiveExtractService, FileGcService, UploadSweepService},
+};
 use domain_workflow::{
     model::vo::msg::{ChangeMsg, Info},
-    service::ScheduleService,
+    service::{ScheduleService, SchedulerBacklogService},
 };
 use infrastructure_command::WsServerOperateCommand;
 use service_workflow::{FlowScheduleServiceImpl, NodeScheduleServiceImpl, TaskScheduleServiceImpl};
+use uuid::Uuid;
 
 use super::ServiceProvider;
 
@@ -19,7 +23,16 @@ pub async fn file_upload_runner_consumer(
     #[inject] service: Arc<FileUploadRunner>,
     #[serialize] command: FileUploadCommand,
 ) -> anyhow::Result<()> {
-    service.upload_file(command.move_id, command.task_id).await
+    service.upload_file(command.move_id, command.user_id, command.task_id).await
+}
+
+#[alice_di::auto_inject(ServiceProvider, scoped(AliceScopedConfig{user_info:Some(UserInfo{id:command.user_id}),..Default::default()}))]
+#[alice_web::message_consumer]
+pub async fn archive_extract_consumer(
+    #[inject] service: Arc<dyn ArchiveExtractService>,
+    #[serialize] command: ExtractArchiveCommand,
+) -> anyhow::Result<()> {
+    service.run_extraction(command.archive_id, command.dir_id).await
 }
 
 #[alice_di::auto_inject(ServiceProvider)]
@@ -49,3 +62,48 @@ pub async fn status_consumer(
     }
     Ok(())
 }
+
+#[alice_di::auto_inject(ServiceProvider, scoped)]
+#[alice_web::message_consumer]
+pub async fn backlog_consumer(
+    #[inject] backlog_service: Arc<dyn SchedulerBacklogService>,
+    #[serialize] id: Uuid,
+) -> anyhow::Result<()> {
+    tracing::debug!("Drain scheduler backlog, triggered by {id}");
+    backlog_service.drain().await
+}
+
+#[alice_di::auto_inject(ServiceProvider, scoped)]
+#[alice_web::message_consumer]
+pub async fn file_gc_consumer(
+    #[inject] file_gc_service: Arc<dyn FileGcService>,
+    #[serialize] run_id: Uuid,
+) -> anyhow::Result<()> {
+    let collected = file_gc_service.collect().await?;
+    tracing::info!("File gc {run_id} collected {collected} files");
+    Ok(())
+}
+
+#[alice_di::auto_inject(ServiceProvider, scoped)]
+#[alice_web::message_consumer]
+pub async fn upload_sweep_consumer(
+    #[inject] upload_sweep_service: Arc<dyn UploadSweepService>,
+    #[serialize] run_id: Uuid,
+) -> anyhow::Result<()> {
+    let swept = upload_sweep_service.sweep().await?;
+    tracing::info!("Upload sweep {run_id} swept {swept} uploads");
+    Ok(())
+}
+
+#[alice_di::auto_inject(ServiceProvider, scoped)]
+#[alice_web::message_consumer]
+pub async fn task_retry_consumer(
+    #[inject] task_service: Arc<TaskScheduleServiceImpl>,
+    #[serialize] run_id: Uuid,
+) -> anyhow::Result<()> {
+    let retried = task_service.run_due_retries().await?;
+    if retried > 0 {
+        tracing::info!("Task retry {run_id} retried {retried} tasks");
+    }
+    Ok(())
+}
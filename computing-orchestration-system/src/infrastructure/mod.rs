@@ -11,5 +11,6 @@ mod database;
 mod internal_message_consumer;
 mod repository;
 mod service;
+pub use service::archive_writer::{ArchiveFormat, ArchiveWriter};
 mod ws_session_opener;
 pub use ws_session_opener::WsSessionOpener;
@@ -1 +1,8 @@
 mod package;
+mod package_cache;
+mod package_store;
+#[cfg(test)]
+mod test_util;
+
+pub use package_cache::{CachedPackageRepository, PackageCache};
+pub use package_store::PackageStoreRepository;
@@ -1,6 +1,9 @@
 use alice_architecture::repository::ReadOnlyRepository;
 use async_trait::async_trait;
-use domain_content_repo::{model::entity::Package, repository::PackageRepo};
+use domain_content_repo::{
+    model::{entity::Package, vo::PackageVersion},
+    repository::PackageRepo,
+};
 use graphql_client::{GraphQLQuery, Response};
 use uuid::Uuid;
 
@@ -8,7 +11,7 @@ use crate::infrastructure::database::graphql::content_repo::*;
 
 #[async_trait]
 impl PackageRepo for ContentRepository {
-    async fn get_package(&self, content_entity_ver_id: Uuid) -> anyhow::Result<Package> {
+    async fn get_package_tar(&self, content_entity_ver_id: Uuid) -> anyhow::Result<Vec<u8>> {
         let v = get_tar_by_id::Variables {
             content_entity_version_id: content_entity_ver_id.to_string(),
         };
@@ -29,9 +32,35 @@ impl PackageRepo for ContentRepository {
             content_entity_version.data.unwrap().id.unwrap()
         );
         let response = reqwest::get(true_url).await?;
-        let package = Package::extract_package(content_entity_ver_id, &response.bytes().await?)?;
+        Ok(response.bytes().await?.to_vec())
+    }
 
-        Ok(package)
+    async fn get_versions(&self, system_name: &str) -> anyhow::Result<Vec<PackageVersion>> {
+        let v = get_versions_by_system_name::Variables {
+            system_name: system_name.to_owned(),
+        };
+        let request_body = GetVersionsBySystemName::build_query(v);
+        let res = self
+            .client
+            .post(format!("{}/graphql", self.url))
+            .json(&request_body)
+            .send()
+            .await?;
+        let response_body: Response<get_versions_by_system_name::ResponseData> = res.json().await?;
+        let data = response_body.data.ok_or(anyhow::anyhow!("Data not exist."))?;
+        data.content_entity_versions
+            .unwrap_or_default()
+            .into_iter()
+            .flatten()
+            .map(|el| {
+                Ok(PackageVersion {
+                    version_id: Uuid::parse_str(
+                        &el.uuid.ok_or(anyhow::anyhow!("Data not exist."))?,
+                    )?,
+                    version: el.tag,
+                })
+            })
+            .collect()
     }
 }
 
@@ -0,0 +1,456 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use alice_architecture::repository::ReadOnlyRepository;
+use anyhow::bail;
+use async_trait::async_trait;
+use domain_content_repo::{
+    model::{entity::Package, vo::PackageVersion},
+    repository::PackageRepo,
+};
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+/// Extension of package tarballs in the cache directory.
+const TAR_EXTENSION: &str = "tar";
+
+struct MemoryEntry {
+    package: Package,
+    last_used: Instant,
+    /// Time the tarball is fetched from the registry.
+    fetched: SystemTime,
+}
+
+struct DiskEntry {
+    size: u64,
+    last_used: SystemTime,
+}
+
+/// Tarballs on disk, the directory is scanned once on first use instead of on every write.
+#[derive(Default)]
+struct DiskIndex {
+    scanned: bool,
+    entries: HashMap<PathBuf, DiskEntry>,
+}
+
+/// Cache of packages shared by all requests. Tarballs are kept on disk as
+/// `{dir}/{version_id}/{hash}.tar`, extracted packages are kept in memory. Entries never change
+/// once written, a package changed in the registry is cached beside the old one.
+///
+/// Lookups of a pinned hash use the entry of the hash whenever it is fetched. Unpinned lookups
+/// use the latest entry of the version only if it is fetched within the TTL, the registry may
+/// have changed the package since.
+///
+/// Both are evicted least recently used first. Use times of tarballs are kept in memory, after a
+/// restart they start from the modified times. Tarballs of pinned hashes are evicted as well,
+/// they are fetched from the registry again when used, which fails if the package is changed
+/// in the registry since it was pinned.
+#[derive(TypedBuilder)]
+pub struct PackageCache {
+    #[builder(setter(into))]
+    dir: PathBuf,
+    /// Max count of packages in memory.
+    #[builder(default = 64)]
+    memory_capacity: usize,
+    /// Max total size of tarballs on disk.
+    #[builder(default = 4 * 1024 * 1024 * 1024)]
+    disk_capacity: u64,
+    /// How long a package is used by unpinned lookups after it is fetched.
+    #[builder(default = Duration::from_secs(5 * 60))]
+    unpinned_ttl: Duration,
+    #[builder(default, setter(skip))]
+    memory: Mutex<HashMap<Uuid, MemoryEntry>>,
+    #[builder(default, setter(skip))]
+    disk: tokio::sync::Mutex<DiskIndex>,
+}
+
+impl PackageCache {
+    fn get_memory(&self, version_id: Uuid, hash: Option<&str>) -> Option<Package> {
+        let mut memory = self.memory.lock().unwrap();
+        let entry = memory.get_mut(&version_id)?;
+        match hash {
+            Some(hash) if hash != entry.package.hash => return None,
+            None if !self.is_fresh(entry.fetched) => return None,
+            _ => {}
+        }
+        entry.last_used = Instant::now();
+        Some(entry.package.clone())
+    }
+
+    /// Put the package in memory, the least recently used one is evicted when it is full.
+    fn put_memory(&self, package: Package, fetched: SystemTime) {
+        let mut memory = self.memory.lock().unwrap();
+        if !memory.contains_key(&package.version_id) && memory.len() >= self.memory_capacity {
+            let least_used = memory.iter().min_by_key(|(_, el)| el.last_used).map(|(id, _)| *id);
+            if let Some(id) = least_used {
+                memory.remove(&id);
+            }
+        }
+        memory.insert(
+            package.version_id,
+            MemoryEntry {
+                package,
+                last_used: Instant::now(),
+                fetched,
+            },
+        );
+    }
+
+    /// Read the tarball of the hash, or the latest cached one of the version if no hash is given
+    /// and it is fetched within the TTL. The tarball is returned with the time it is fetched.
+    async fn get_disk(
+        &self,
+        version_id: Uuid,
+        hash: Option<&str>,
+    ) -> anyhow::Result<Option<(Vec<u8>, SystemTime)>> {
+        let path = match hash {
+            Some(hash) => self.tar_path(version_id, hash),
+            None => {
+                let tars = list_tars(&self.dir.join(version_id.to_string())).await?;
+                match tars.into_iter().max_by_key(|(_, modified, _)| *modified) {
+                    Some((path, modified, _)) if self.is_fresh(modified) => path,
+                    _ => return Ok(None),
+                }
+            }
+        };
+        if !tokio::fs::try_exists(&path).await? {
+            return Ok(None);
+        }
+        // Tarballs are written once they are fetched, and rewritten when fetched again.
+        let fetched = tokio::fs::metadata(&path).await?.modified()?;
+        let tar = tokio::fs::read(&path).await?;
+        self.lock_disk().await?.entries.insert(
+            path,
+            DiskEntry {
+                size: tar.len() as u64,
+                last_used: SystemTime::now(),
+            },
+        );
+        Ok(Some((tar, fetched)))
+    }
+
+    /// Write the tarball, then evict the least recently used tarballs when the disk capacity is
+    /// exceeded.
+    async fn put_disk(&self, version_id: Uuid, hash: &str, tar: &[u8]) -> anyhow::Result<()> {
+        let path = self.tar_path(version_id, hash);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Write to a temporary file first, so that a partly written tarball is never read.
+        let temp_path = path.with_extension(format!("{}.{}", Uuid::new_v4(), TAR_EXTENSION));
+        tokio::fs::write(&temp_path, tar).await?;
+        tokio::fs::rename(&temp_path, &path).await?;
+
+        let mut disk = self.lock_disk().await?;
+        disk.entries.insert(
+            path,
+            DiskEntry {
+                size: tar.len() as u64,
+                last_used: SystemTime::now(),
+            },
+        );
+        let mut total = disk.entries.values().map(|el| el.size).sum::<u64>();
+        if total <= self.disk_capacity {
+            return Ok(());
+        }
+        let mut tars = disk
+            .entries
+            .iter()
+            .map(|(path, el)| (el.last_used, path.to_owned()))
+            .collect::<Vec<_>>();
+        tars.sort();
+        for (_, path) in tars {
+            if total <= self.disk_capacity {
+                break;
+            }
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            total -= disk.entries.remove(&path).map(|el| el.size).unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    /// Lock the index of tarballs on disk, the directory is scanned if it isn't yet.
+    async fn lock_disk(&self) -> anyhow::Result<tokio::sync::MutexGuard<'_, DiskIndex>> {
+        let mut disk = self.disk.lock().await;
+        if disk.scanned {
+            return Ok(disk);
+        }
+        if tokio::fs::try_exists(&self.dir).await? {
+            let mut entries = tokio::fs::read_dir(&self.dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                if !entry.file_type().await?.is_dir() {
+                    continue;
+                }
+                for (path, modified, size) in list_tars(&entry.path()).await? {
+                    let entry = DiskEntry {
+                        size,
+                        last_used: modified,
+                    };
+                    disk.entries.insert(path, entry);
+                }
+            }
+        }
+        disk.scanned = true;
+        Ok(disk)
+    }
+
+    /// Whether a package fetched at the time can be used by unpinned lookups.
+    fn is_fresh(&self, fetched: SystemTime) -> bool {
+        // A time in the future is fresh as well.
+        !matches!(fetched.elapsed(), Ok(elapsed) if elapsed >= self.unpinned_ttl)
+    }
+
+    fn tar_path(&self, version_id: Uuid, hash: &str) -> PathBuf {
+        self.dir.join(version_id.to_string()).join(format!("{hash}.{TAR_EXTENSION}"))
+    }
+}
+
+/// List tarballs in the directory with their modified time and size.
+async fn list_tars(dir: &Path) -> anyhow::Result<Vec<(PathBuf, SystemTime, u64)>> {
+    let mut tars = vec![];
+    if !tokio::fs::try_exists(dir).await? {
+        return Ok(tars);
+    }
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let metadata = entry.metadata().await?;
+        let is_tar = path.extension().is_some_and(|el| el == TAR_EXTENSION)
+            && path.file_stem().is_some_and(|el| !el.to_string_lossy().contains('.'));
+        if metadata.is_file() && is_tar {
+            tars.push((path, metadata.modified()?, metadata.len()));
+        }
+    }
+    Ok(tars)
+}
+
+/// Package repository that looks up the cache before the registry.
+#[derive(TypedBuilder)]
+pub struct CachedPackageRepository {
+    inner: Arc<dyn PackageRepo>,
+    cache: Arc<PackageCache>,
+}
+
+impl CachedPackageRepository {
+    async fn load(&self, version_id: Uuid, hash: Option<&str>) -> anyhow::Result<Package> {
+        if let Some(package) = self.cache.get_memory(version_id, hash) {
+            return Ok(package);
+        }
+        let cached_tar = self.cache.get_disk(version_id, hash).await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to read cached package: {version_id}, {e}");
+            None
+        });
+        let (package, fetched) = match cached_tar {
+            Some((tar, fetched)) => (Package::extract_package(version_id, &tar)?, fetched),
+            None => {
+                let tar = self.inner.get_package_tar(version_id).await?;
+                let package = Package::extract_package(version_id, &tar)?;
+                if let Some(hash) = hash {
+                    if package.hash != hash {
+                        bail!(
+                            "Package: {version_id} is changed, pinned hash: {hash}, current hash: {}",
+                            package.hash
+                        );
+                    }
+                }
+                if let Err(e) = self.cache.put_disk(version_id, &package.hash, &tar).await {
+                    tracing::warn!("Failed to cache package: {version_id}, {e}");
+                }
+                (package, SystemTime::now())
+            }
+        };
+        self.cache.put_memory(package.clone(), fetched);
+        Ok(package)
+    }
+}
+
+#[async_trait]
+impl PackageRepo for CachedPackageRepository {
+    async fn get_package_tar(&self, content_entity_ver_id: Uuid) -> anyhow::Result<Vec<u8>> {
+        match self.cache.get_disk(content_entity_ver_id, None).await {
+            Ok(Some((tar, _))) => Ok(tar),
+            _ => self.inner.get_package_tar(content_entity_ver_id).await,
+        }
+    }
+
+    async fn get_package(&self, content_entity_ver_id: Uuid) -> anyhow::Result<Package> {
+        self.load(content_entity_ver_id, None).await
+    }
+
+    async fn get_pinned_package(
+        &self,
+        content_entity_ver_id: Uuid,
+        hash: &str,
+    ) -> anyhow::Result<Package> {
+        self.load(content_entity_ver_id, Some(hash)).await
+    }
+
+    async fn get_versions(&self, system_name: &str) -> anyhow::Result<Vec<PackageVersion>> {
+        self.inner.get_versions(system_name).await
+    }
+}
+
+#[async_trait]
+impl ReadOnlyRepository<Package> for CachedPackageRepository {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use anyhow::anyhow;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::infrastructure::repository::content_repo::test_util::package_tar;
+
+    /// Registry serving the tarballs, counts the tarballs fetched.
+    #[derive(Default)]
+    struct StubRegistry {
+        tars: Mutex<HashMap<Uuid, Vec<u8>>>,
+        fetched: AtomicUsize,
+    }
+
+    impl StubRegistry {
+        fn set(&self, version_id: Uuid, tar: Vec<u8>) {
+            self.tars.lock().unwrap().insert(version_id, tar);
+        }
+    }
+
+    #[async_trait]
+    impl PackageRepo for StubRegistry {
+        async fn get_package_tar(&self, content_entity_ver_id: Uuid) -> anyhow::Result<Vec<u8>> {
+            self.fetched.fetch_add(1, Ordering::SeqCst);
+            self.tars
+                .lock()
+                .unwrap()
+                .get(&content_entity_ver_id)
+                .cloned()
+                .ok_or(anyhow!("No package: {content_entity_ver_id}"))
+        }
+
+        async fn get_versions(&self, _system_name: &str) -> anyhow::Result<Vec<PackageVersion>> {
+            unreachable!()
+        }
+    }
+
+    #[async_trait]
+    impl ReadOnlyRepository<Package> for StubRegistry {}
+
+    fn repository(registry: &Arc<StubRegistry>, dir: &TempDir) -> CachedPackageRepository {
+        CachedPackageRepository::builder()
+            .inner(registry.clone())
+            .cache(Arc::new(PackageCache::builder().dir(&dir.path()).build()))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_unpinned_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = Arc::new(StubRegistry::default());
+        let version_id = Uuid::new_v4();
+        registry.set(version_id, package_tar("computing", "1.0.0").await);
+        let repository = CachedPackageRepository::builder()
+            .inner(registry.clone())
+            .cache(Arc::new(
+                PackageCache::builder().dir(&dir.path()).unpinned_ttl(Duration::ZERO).build(),
+            ))
+            .build();
+        let old_hash = repository.get_package(version_id).await.unwrap().hash;
+
+        // The package is changed in the registry, unpinned lookups see it once the TTL is over.
+        registry.set(version_id, package_tar("computing", "1.0.1").await);
+        let package = repository.get_package(version_id).await.unwrap();
+        assert_eq!(package.manifest.version, "1.0.1");
+        assert_eq!(registry.fetched.load(Ordering::SeqCst), 2);
+
+        // Pinned lookups use the cached one of the hash regardless of the TTL.
+        let package = repository.get_pinned_package(version_id, &old_hash).await.unwrap();
+        assert_eq!(package.manifest.version, "1.0.0");
+        assert_eq!(registry.fetched.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_memory_hit() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = Arc::new(StubRegistry::default());
+        let version_id = Uuid::new_v4();
+        registry.set(version_id, package_tar("computing", "1.0.0").await);
+        let repository = repository(&registry, &dir);
+
+        let package = repository.get_package(version_id).await.unwrap();
+        // Removed from disk, so only the memory can serve it.
+        std::fs::remove_dir_all(&dir.path()).unwrap();
+        let cached = repository.get_pinned_package(version_id, &package.hash).await.unwrap();
+        assert_eq!(cached.manifest.version, "1.0.0");
+        assert_eq!(registry.fetched.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_disk_hit() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = Arc::new(StubRegistry::default());
+        let version_id = Uuid::new_v4();
+        registry.set(version_id, package_tar("computing", "1.0.0").await);
+        let hash = repository(&registry, &dir).get_package(version_id).await.unwrap().hash;
+
+        // A new cache on the same directory has nothing in memory.
+        let repository = repository(&registry, &dir);
+        let package = repository.get_pinned_package(version_id, &hash).await.unwrap();
+        assert_eq!(package.manifest.version, "1.0.0");
+        assert_eq!(registry.fetched.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pinned_hash_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = Arc::new(StubRegistry::default());
+        let version_id = Uuid::new_v4();
+        registry.set(version_id, package_tar("computing", "1.0.0").await);
+        let repository = repository(&registry, &dir);
+        let old_hash = repository.get_package(version_id).await.unwrap().hash;
+
+        // The package is changed in the registry, the cached one of the old hash is still used.
+        registry.set(version_id, package_tar("computing", "1.0.1").await);
+        let package = repository.get_pinned_package(version_id, &old_hash).await.unwrap();
+        assert_eq!(package.manifest.version, "1.0.0");
+        assert_eq!(registry.fetched.load(Ordering::SeqCst), 1);
+
+        // Neither the cache nor the registry has the hash.
+        let e = repository.get_pinned_package(version_id, "UNKNOWN").await.unwrap_err();
+        assert!(e.to_string().contains("is changed"));
+        assert_eq!(registry.fetched.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_disk_eviction() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PackageCache::builder().dir(&dir.path()).disk_capacity(200).build();
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        cache.put_disk(a, "A", &[0; 100]).await.unwrap();
+        cache.put_disk(b, "B", &[0; 100]).await.unwrap();
+        // Reading `a` makes `b` the least recently used.
+        assert!(cache.get_disk(a, Some("A")).await.unwrap().is_some());
+        cache.put_disk(c, "C", &[0; 100]).await.unwrap();
+
+        assert!(cache.get_disk(a, Some("A")).await.unwrap().is_some());
+        assert!(cache.get_disk(b, Some("B")).await.unwrap().is_none());
+        assert!(cache.get_disk(c, None).await.unwrap().is_some());
+
+        // A new cache on the same directory starts from the tarballs on disk.
+        let cache = PackageCache::builder().dir(&dir.path()).disk_capacity(200).build();
+        cache.put_disk(b, "B", &[0; 100]).await.unwrap();
+        let remaining = [(a, "A"), (b, "B"), (c, "C")]
+            .into_iter()
+            .filter(|(version_id, hash)| cache.tar_path(*version_id, hash).exists())
+            .count();
+        assert_eq!(remaining, 2);
+    }
+}
@@ -0,0 +1,248 @@
+use std::{path::PathBuf, sync::Arc};
+
+use alice_architecture::repository::ReadOnlyRepository;
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use domain_content_repo::{
+    model::{entity::Package, vo::PackageVersion},
+    repository::PackageRepo,
+};
+use domain_storage::{repository::FileMetaRepo, service::StorageServerDownloadDispatcherService};
+use serde::Deserialize;
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+/// Name of the index file in the root of the store directory.
+const INDEX_FILE_NAME: &str = "index.json";
+
+/// Owner of the pins of the files on the storage server used by the index.
+const PIN_OWNER: &str = "package_store";
+
+/// Index of the package store, for example:
+///
+/// ```json
+/// {
+///   "packages": [
+///     { "versionId": "...", "systemName": "openmpi", "version": "4.1.5", "file": "openmpi-4.1.5.tar" },
+///     { "versionId": "...", "systemName": "gromacs", "version": "2023.1", "fileMetadataId": "..." }
+///   ]
+/// }
+/// ```
+#[derive(Deserialize, Debug)]
+struct PackageIndex {
+    #[serde(default)]
+    packages: Vec<PackageIndexEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PackageIndexEntry {
+    version_id: Uuid,
+    system_name: String,
+    version: String,
+    #[serde(flatten)]
+    source: PackageSource,
+}
+
+/// Where the package tarball is.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum PackageSource {
+    /// A file relative to the store directory.
+    File { file: PathBuf },
+    /// A file on the storage server.
+    #[serde(rename_all = "camelCase")]
+    Storage { file_metadata_id: Uuid },
+}
+
+/// Packages served without the co-repo, so that clusters without access to it can still run
+/// workflows. The index is read on every query, packages are added without a restart.
+#[derive(TypedBuilder)]
+pub struct PackageStoreRepository {
+    #[builder(setter(into))]
+    dir: PathBuf,
+    download_service: Arc<dyn StorageServerDownloadDispatcherService>,
+    meta_repo: Arc<dyn FileMetaRepo>,
+}
+
+impl PackageStoreRepository {
+    async fn read_index(&self) -> anyhow::Result<PackageIndex> {
+        let path = self.dir.join(INDEX_FILE_NAME);
+        let content = tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("Failed to read package index: {}", path.display()))?;
+        let index: PackageIndex = serde_json::from_slice(&content)
+            .with_context(|| format!("Invalid package index: {}", path.display()))?;
+        // Nothing else references the files on the storage server, pin them so that they
+        // aren't collected. Pins of the last read are kept if it fails.
+        let meta_ids = index
+            .packages
+            .iter()
+            .filter_map(|el| match el.source {
+                PackageSource::Storage { file_metadata_id } => Some(file_metadata_id),
+                PackageSource::File { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        if let Err(e) = self.meta_repo.set_pinned(PIN_OWNER, &meta_ids).await {
+            tracing::warn!("Failed to pin files of the package store: {e}");
+        }
+        Ok(index)
+    }
+}
+
+#[async_trait]
+impl PackageRepo for PackageStoreRepository {
+    async fn get_package_tar(&self, content_entity_ver_id: Uuid) -> anyhow::Result<Vec<u8>> {
+        let entry = self
+            .read_index()
+            .await?
+            .packages
+            .into_iter()
+            .find(|el| el.version_id == content_entity_ver_id)
+            .ok_or(anyhow!(
+                "Package: {content_entity_ver_id} isn't in the package store."
+            ))?;
+        Ok(match entry.source {
+            PackageSource::File { file } => {
+                let path = self.dir.join(file);
+                tokio::fs::read(&path)
+                    .await
+                    .with_context(|| format!("Failed to read package: {}", path.display()))?
+            }
+            PackageSource::Storage { file_metadata_id } => {
+                self.download_service.get_bytes(file_metadata_id).await?
+            }
+        })
+    }
+
+    async fn get_versions(&self, system_name: &str) -> anyhow::Result<Vec<PackageVersion>> {
+        Ok(self
+            .read_index()
+            .await?
+            .packages
+            .into_iter()
+            .filter(|el| el.system_name == system_name)
+            .map(|el| PackageVersion {
+                version_id: el.version_id,
+                version: el.version,
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ReadOnlyRepository<Package> for PackageStoreRepository {}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, ops::Range};
+
+    use domain_storage::mock::MockFileMetaRepo;
+
+    use super::*;
+    use crate::infrastructure::repository::content_repo::test_util::package_tar;
+
+    /// Storage server holding the files in memory.
+    struct StubDownloadService {
+        files: HashMap<Uuid, Vec<u8>>,
+    }
+
+    #[async_trait]
+    impl StorageServerDownloadDispatcherService for StubDownloadService {
+        async fn download(&self, _meta_id: Uuid) -> anyhow::Result<()> {
+            unreachable!()
+        }
+
+        async fn get_bytes(&self, meta_id: Uuid) -> anyhow::Result<Vec<u8>> {
+            self.files.get(&meta_id).cloned().ok_or(anyhow!("No file: {meta_id}"))
+        }
+
+        async fn get_text(&self, _meta_id: Uuid) -> anyhow::Result<String> {
+            unreachable!()
+        }
+
+        async fn rangely_get_file(
+            &self,
+            _meta_id: Uuid,
+            _ranges: &[Range<u64>],
+        ) -> anyhow::Result<Vec<Vec<u8>>> {
+            unreachable!()
+        }
+
+        async fn get_file_size(&self, _meta_id: Uuid) -> anyhow::Result<u64> {
+            unreachable!()
+        }
+
+        async fn get_download_url(&self, _meta_id: Uuid) -> anyhow::Result<String> {
+            unreachable!()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_package_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let (file_version_id, storage_version_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let file_metadata_id = Uuid::new_v4();
+        std::fs::write(
+            dir.path().join("computing-1.0.0.tar"),
+            package_tar("computing", "1.0.0").await,
+        )
+        .unwrap();
+        let index = serde_json::json!({
+            "packages": [
+                {
+                    "versionId": file_version_id,
+                    "systemName": "computing",
+                    "version": "1.0.0",
+                    "file": "computing-1.0.0.tar"
+                },
+                {
+                    "versionId": storage_version_id,
+                    "systemName": "computing",
+                    "version": "2.0.0",
+                    "fileMetadataId": file_metadata_id
+                }
+            ]
+        });
+        std::fs::write(dir.path().join(INDEX_FILE_NAME), index.to_string()).unwrap();
+        let mut meta_repo = MockFileMetaRepo::new();
+        meta_repo
+            .expect_set_pinned()
+            .withf(move |owner, meta_ids| *owner == PIN_OWNER && *meta_ids == [file_metadata_id])
+            .returning(|_, _| Ok(()));
+        let repository = PackageStoreRepository::builder()
+            .dir(&dir.path())
+            .download_service(Arc::new(StubDownloadService {
+                files: HashMap::from([(file_metadata_id, package_tar("computing", "2.0.0").await)]),
+            }))
+            .meta_repo(Arc::new(meta_repo))
+            .build();
+
+        let mut versions = repository
+            .get_versions("computing")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|el| (el.version_id, el.version))
+            .collect::<Vec<_>>();
+        versions.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            versions,
+            [
+                (file_version_id, "1.0.0".to_string()),
+                (storage_version_id, "2.0.0".to_string())
+            ]
+        );
+        assert!(repository.get_versions("other").await.unwrap().is_empty());
+
+        let package = repository.get_package(file_version_id).await.unwrap();
+        assert_eq!(package.manifest.version, "1.0.0");
+        let package = repository.get_package(storage_version_id).await.unwrap();
+        assert_eq!(package.manifest.version, "2.0.0");
+        assert!(repository.get_pinned_package(storage_version_id, &package.hash).await.is_ok());
+
+        let e = repository.get_pinned_package(file_version_id, &package.hash).await.unwrap_err();
+        assert!(e.to_string().contains("is changed"));
+        assert!(repository.get_package(Uuid::new_v4()).await.is_err());
+    }
+}
@@ -0,0 +1,28 @@
+/// Tarball of a software package in the layout parsed by `Package::extract_package`.
+pub async fn package_tar(system_name: &str, version: &str) -> Vec<u8> {
+    let files = [
+        (
+            "package/manifest.yaml",
+            format!(
+                "ability:\n  SoftwareComputing: Software\nname: {system_name}\n\
+                systemName: {system_name}\nversion: {version}\nmaintainers: []\n"
+            ),
+        ),
+        (
+            "package/software/spec.yaml",
+            format!(
+                "kind: SoftwareSpec\nspec:\n  spack:\n    argument_list: []\n    \
+                name: {system_name}\n"
+            ),
+        ),
+    ];
+    let mut builder = tokio_tar::Builder::new(vec![]);
+    for (path, content) in files {
+        let mut header = tokio_tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, content.as_bytes()).await.unwrap();
+    }
+    builder.into_inner().await.unwrap()
+}
@@ -1,3 +1,5 @@
 mod content_repo;
 mod storage;
 mod workflow;
+
+pub use content_repo::{CachedPackageRepository, PackageCache, PackageStoreRepository};
@@ -1,16 +1,18 @@
 use alice_architecture::repository::{DBRepository, MutableRepository, ReadOnlyRepository};
 use anyhow::anyhow;
-use database_model::file_storage;
+use database_model::{file_metadata, file_storage};
 use std::sync::atomic::Ordering;
 
 use domain_storage::model::entity::FileStorage;
 use domain_storage::repository::FileStorageRepo;
 use sea_orm::{
-    ActiveValue::*, ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter, QueryTrait,
+    sea_query::{Alias, Expr, OnConflict, Query},
+    ActiveValue::*,
+    ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter, QueryTrait,
 };
 use uuid::Uuid;
 
-use crate::infrastructure::database::OrmRepo;
+use crate::infrastructure::database::{entity::storage_usage, OrmRepo};
 
 #[async_trait::async_trait]
 impl ReadOnlyRepository<FileStorage> for OrmRepo {}
@@ -26,9 +28,36 @@ impl MutableRepository<FileStorage> for OrmRepo {
             created_user_id: Set(self.user_id()?),
             ..Default::default()
         };
-        let stmt = file_storage::Entity::insert(active_model)
-            .build(self.db.get_connection().get_database_backend());
+        let backend = self.db.get_connection().get_database_backend();
+        let stmt = file_storage::Entity::insert(active_model).build(backend);
         stmts.push(stmt);
+        // The meta is inserted before its storages, in the same transaction.
+        let stmt = Query::insert()
+            .into_table(storage_usage::Entity)
+            .columns([
+                storage_usage::Column::StorageServerId,
+                storage_usage::Column::Used,
+            ])
+            .select_from(
+                Query::select()
+                    .expr(Expr::val(entity.storage_server_id))
+                    .column(file_metadata::Column::Size)
+                    .from(file_metadata::Entity)
+                    .and_where(file_metadata::Column::Id.eq(entity.meta_id))
+                    .to_owned(),
+            )?
+            .on_conflict(
+                OnConflict::column(storage_usage::Column::StorageServerId)
+                    .value(
+                        storage_usage::Column::Used,
+                        Expr::col((storage_usage::Entity, storage_usage::Column::Used)).add(
+                            Expr::col((Alias::new("excluded"), storage_usage::Column::Used)),
+                        ),
+                    )
+                    .to_owned(),
+            )
+            .to_owned();
+        stmts.push(backend.build(&stmt));
         self.can_drop.store(false, Ordering::Relaxed);
         Ok(entity.meta_id)
     }
@@ -61,4 +90,56 @@ impl FileStorageRepo for OrmRepo {
             )))?;
         Ok(x.server_url)
     }
+
+    async fn get_all_by_meta_id(&self, meta_id: Uuid) -> anyhow::Result<Vec<FileStorage>> {
+        Ok(file_storage::Entity::find()
+            .filter(file_storage::Column::FileMetadataId.eq(meta_id))
+            .all(self.db.get_connection())
+            .await?
+            .into_iter()
+            .map(FileStorage::from)
+            .collect())
+    }
+
+    async fn get_used_size(&self, storage_server_id: Uuid) -> anyhow::Result<u64> {
+        let used = storage_usage::Entity::find_by_id(storage_server_id)
+            .one(self.db.get_connection())
+            .await?
+            .map(|m| m.used)
+            .unwrap_or_default();
+        Ok(used.max(0) as u64)
+    }
+
+    async fn delete_by_meta_id(&self, meta_id: Uuid) -> anyhow::Result<()> {
+        let size = file_metadata::Entity::find_by_id(meta_id)
+            .one(self.db.get_connection())
+            .await?
+            .ok_or(anyhow!("No file meta with id: {meta_id}."))?
+            .size;
+        let backend = self.db.get_connection().get_database_backend();
+        let mut stmts = self.statements.lock().await;
+        // Usage is decreased before the storages are deleted.
+        let stmt = storage_usage::Entity::update_many()
+            .col_expr(
+                storage_usage::Column::Used,
+                Expr::col(storage_usage::Column::Used).sub(size),
+            )
+            .filter(
+                storage_usage::Column::StorageServerId.in_subquery(
+                    Query::select()
+                        .column(file_storage::Column::StorageServerId)
+                        .from(file_storage::Entity)
+                        .and_where(file_storage::Column::FileMetadataId.eq(meta_id))
+                        .to_owned(),
+                ),
+            )
+            .build(backend);
+        stmts.push(stmt);
+        let stmt = file_storage::Entity::delete_many()
+            .filter(file_storage::Column::FileMetadataId.eq(meta_id))
+            .build(backend);
+        stmts.push(stmt);
+        self.can_drop.store(false, Ordering::Relaxed);
+        Ok(())
+    }
 }
@@ -2,17 +2,20 @@ use alice_architecture::repository::{DBRepository, MutableRepository, ReadOnlyRe
 use anyhow::anyhow;
 
 use async_trait::async_trait;
-use database_model::file_metadata;
+use chrono::{DateTime, Utc};
+use database_model::{file_metadata, file_system};
 use domain_storage::model::entity::FileMeta;
-use domain_storage::model::vo::HashAlgorithm;
+use domain_storage::model::vo::{FileReferences, HashAlgorithm};
 use domain_storage::repository::FileMetaRepo;
 use sea_orm::prelude::*;
-use sea_orm::Condition;
-use sea_orm::QueryTrait;
-use sea_orm::Set;
+use sea_orm::sea_query::{Expr, Func, OnConflict, Query, SelectStatement, SimpleExpr};
+use sea_orm::{Condition, QueryOrder, QuerySelect, QueryTrait, Set, Statement, TransactionTrait};
 use std::sync::atomic::Ordering;
 
-use crate::infrastructure::database::OrmRepo;
+use crate::infrastructure::database::{
+    entity::{file_metadata_gc, file_reference},
+    OrmRepo,
+};
 
 #[async_trait]
 impl FileMetaRepo for OrmRepo {
@@ -23,11 +26,9 @@ impl FileMetaRepo for OrmRepo {
     ) -> anyhow::Result<Option<FileMeta>> {
         Ok(
             match file_metadata::Entity::find()
-                .filter(
-                    Condition::all()
-                        .add(file_metadata::Column::Hash.eq(hash))
-                        .add(file_metadata::Column::HashAlgorithm.eq(hash_algorithm.to_string())),
-                )
+                .filter(file_metadata::Column::Hash.eq(hash))
+                .filter(file_metadata::Column::HashAlgorithm.eq(hash_algorithm.to_string()))
+                .filter(file_metadata_gc::Column::DeletedTime.is_null())
                 .one(self.db.get_connection())
                 .await?
             {
@@ -36,6 +37,221 @@ impl FileMetaRepo for OrmRepo {
             },
         )
     }
+
+    async fn get_references(&self, meta_id: Uuid) -> anyhow::Result<FileReferences> {
+        let net_disk = file_system::Entity::find()
+            .filter(file_system::Column::FileMetadataId.eq(meta_id))
+            .count(self.db.get_connection())
+            .await?;
+        let workflow = file_reference::Entity::find()
+            .filter(file_reference::Column::FileMetadataId.eq(meta_id))
+            .filter(file_reference::Column::OwnerKind.eq(file_reference::WORKFLOW_INSTANCE))
+            .count(self.db.get_connection())
+            .await?;
+        let pinned = file_reference::Entity::find()
+            .filter(file_reference::Column::FileMetadataId.eq(meta_id))
+            .filter(file_reference::Column::OwnerKind.ne(file_reference::WORKFLOW_INSTANCE))
+            .count(self.db.get_connection())
+            .await?;
+        Ok(FileReferences {
+            net_disk,
+            workflow,
+            pinned,
+            snapshot: 0,
+        })
+    }
+
+    async fn get_unreferenced(
+        &self,
+        dereferenced_before: DateTime<Utc>,
+        limit: u64,
+    ) -> anyhow::Result<Vec<FileMeta>> {
+        let dereferenced_time = || -> SimpleExpr {
+            Func::coalesce([
+                Expr::col((
+                    file_metadata::Entity,
+                    file_metadata_gc::Column::DereferencedTime,
+                ))
+                .into(),
+                Expr::col((file_metadata::Entity, file_metadata::Column::CreatedTime)).into(),
+            ])
+            .into()
+        };
+        file_metadata::Entity::find()
+            // Tombstoned metas are returned regardless of the time, their deletion has failed.
+            .filter(
+                Condition::any()
+                    .add(file_metadata_gc::Column::DeletedTime.is_not_null())
+                    .add(Expr::expr(dereferenced_time()).lt(dereferenced_before)),
+            )
+            .filter(file_metadata::Column::Id.not_in_subquery(referenced_by_net_disk()))
+            .filter(file_metadata::Column::Id.not_in_subquery(referenced_by_owners()))
+            .order_by_asc(dereferenced_time())
+            .limit(limit)
+            .all(self.db.get_connection())
+            .await?
+            .into_iter()
+            .map(FileMeta::try_from)
+            .collect()
+    }
+
+    async fn tombstone(&self, meta_id: Uuid) -> anyhow::Result<bool> {
+        let trans = self.db.get_connection().begin().await?;
+        // Wait for transactions that are adding references to the meta, they hold share locks
+        // of it, so that the check below sees their references.
+        let locked = file_metadata_gc::Entity::find_by_id(meta_id)
+            .lock_exclusive()
+            .one(&trans)
+            .await?;
+        if locked.is_none() {
+            trans.rollback().await?;
+            return Ok(false);
+        }
+        let result = file_metadata_gc::Entity::update_many()
+            .col_expr(
+                file_metadata_gc::Column::DeletedTime,
+                Func::coalesce([
+                    Expr::col(file_metadata_gc::Column::DeletedTime).into(),
+                    Expr::current_timestamp().into(),
+                ])
+                .into(),
+            )
+            .filter(file_metadata_gc::Column::Id.eq(meta_id))
+            .filter(
+                file_metadata_gc::Column::Id.not_in_subquery(
+                    referenced_by_net_disk()
+                        .and_where(file_system::Column::FileMetadataId.eq(meta_id))
+                        .to_owned(),
+                ),
+            )
+            .filter(
+                file_metadata_gc::Column::Id.not_in_subquery(
+                    referenced_by_owners()
+                        .and_where(file_reference::Column::FileMetadataId.eq(meta_id))
+                        .to_owned(),
+                ),
+            )
+            .exec(&trans)
+            .await?;
+        trans.commit().await?;
+        Ok(result.rows_affected == 1)
+    }
+
+    async fn set_pinned(&self, owner: &str, meta_ids: &[Uuid]) -> anyhow::Result<()> {
+        // Pins of an owner share the nil owner id, the owner is the kind.
+        let stmts = self.reference_stmts(owner, Uuid::nil(), meta_ids).await?;
+        let trans = self.db.get_connection().begin().await?;
+        for stmt in stmts {
+            trans.execute(stmt).await?;
+        }
+        trans.commit().await?;
+        Ok(())
+    }
+
+    async fn restore(&self, meta_id: Uuid) -> anyhow::Result<()> {
+        file_metadata_gc::Entity::update_many()
+            .col_expr(
+                file_metadata_gc::Column::DeletedTime,
+                Expr::value(Option::<DateTime<Utc>>::None),
+            )
+            .filter(file_metadata_gc::Column::Id.eq(meta_id))
+            .exec(self.db.get_connection())
+            .await?;
+        Ok(())
+    }
+}
+
+/// Metas referenced by net disk entries.
+fn referenced_by_net_disk() -> SelectStatement {
+    Query::select()
+        .column(file_system::Column::FileMetadataId)
+        .from(file_system::Entity)
+        .and_where(file_system::Column::FileMetadataId.is_not_null())
+        .to_owned()
+}
+
+/// Metas referenced by the owners in the reference table.
+fn referenced_by_owners() -> SelectStatement {
+    Query::select()
+        .column(file_reference::Column::FileMetadataId)
+        .from(file_reference::Entity)
+        .to_owned()
+}
+
+impl OrmRepo {
+    /// Build the statement locking the metas till the transaction it runs in commits, so that
+    /// they aren't tombstoned before the references added in the transaction are visible. Fail
+    /// if any of them is tombstoned already.
+    pub(crate) async fn lock_metas_stmt(&self, meta_ids: &[Uuid]) -> anyhow::Result<Statement> {
+        let tombstoned = file_metadata_gc::Entity::find()
+            .filter(file_metadata_gc::Column::Id.is_in(meta_ids.to_vec()))
+            .filter(file_metadata_gc::Column::DeletedTime.is_not_null())
+            .count(self.db.get_connection())
+            .await?;
+        if tombstoned > 0 {
+            anyhow::bail!("Referenced file is being deleted.");
+        }
+        Ok(file_metadata_gc::Entity::find()
+            .select_only()
+            .column(file_metadata_gc::Column::Id)
+            .filter(file_metadata_gc::Column::Id.is_in(meta_ids.to_vec()))
+            .lock_shared()
+            .build(self.db.get_connection().get_database_backend()))
+    }
+
+    /// Build the statement setting the dereferenced time of the metas selected by the query.
+    pub(crate) fn dereference_stmt(&self, meta_ids: SelectStatement) -> Statement {
+        file_metadata_gc::Entity::update_many()
+            .col_expr(
+                file_metadata_gc::Column::DereferencedTime,
+                Expr::current_timestamp().into(),
+            )
+            .filter(file_metadata_gc::Column::Id.in_subquery(meta_ids))
+            .build(self.db.get_connection().get_database_backend())
+    }
+
+    /// Build statements replacing references of the owner with the metas, to run in the
+    /// transaction writing the owner. Ids of files to be uploaded are referenced as well, e.g.
+    /// prepared outputs.
+    pub(crate) async fn reference_stmts(
+        &self,
+        owner_kind: &str,
+        owner_id: Uuid,
+        meta_ids: &[Uuid],
+    ) -> anyhow::Result<Vec<Statement>> {
+        let backend = self.db.get_connection().get_database_backend();
+        let removed = || {
+            Condition::all()
+                .add(file_reference::Column::OwnerKind.eq(owner_kind))
+                .add(file_reference::Column::OwnerId.eq(owner_id))
+                .add(file_reference::Column::FileMetadataId.is_not_in(meta_ids.to_vec()))
+        };
+        let mut stmts = vec![
+            self.dereference_stmt(referenced_by_owners().cond_where(removed()).to_owned()),
+            file_reference::Entity::delete_many().filter(removed()).build(backend),
+        ];
+        if !meta_ids.is_empty() {
+            stmts.push(self.lock_metas_stmt(meta_ids).await?);
+            let active_models = meta_ids.iter().map(|meta_id| file_reference::ActiveModel {
+                file_metadata_id: Set(*meta_id),
+                owner_kind: Set(owner_kind.to_owned()),
+                owner_id: Set(owner_id),
+            });
+            let stmt = file_reference::Entity::insert_many(active_models)
+                .on_conflict(
+                    OnConflict::columns([
+                        file_reference::Column::FileMetadataId,
+                        file_reference::Column::OwnerKind,
+                        file_reference::Column::OwnerId,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .build(backend);
+            stmts.push(stmt);
+        }
+        Ok(stmts)
+    }
 }
 
 #[async_trait::async_trait]
@@ -68,6 +284,15 @@ impl MutableRepository<FileMeta> for OrmRepo {
         Ok(entity.id)
     }
 
+    async fn delete_by_id(&self, uuid: Uuid) -> anyhow::Result<()> {
+        let mut stmts = self.statements.lock().await;
+        let stmt = file_metadata::Entity::delete_by_id(uuid)
+            .build(self.db.get_connection().get_database_backend());
+        stmts.push(stmt);
+        self.can_drop.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
     async fn save_changed(&self) -> anyhow::Result<bool> {
         self.save_changed().await
     }
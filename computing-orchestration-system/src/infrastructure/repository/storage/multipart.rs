@@ -2,6 +2,7 @@ use alice_architecture::repository::{
     DBRepository, LeaseDBRepository, LeaseRepository, MutableRepository, ReadOnlyRepository,
 };
 use anyhow::anyhow;
+use chrono::Utc;
 use domain_storage::{
     exception::{FileException, FileResult},
     model::entity::Multipart,
@@ -27,6 +28,21 @@ impl MultipartRepo for RedisRepo {
         })
     }
 
+    async fn get_all_by_key_regex(&self, regex: &str) -> anyhow::Result<Vec<Multipart>> {
+        let keys = self.query_keys(regex).await?;
+        let values = match keys.len() {
+            0 => vec![],
+            1 => vec![self.query::<Option<String>>(&Cmd::get(keys.first().unwrap())).await?],
+            _ => self.query::<Vec<Option<String>>>(&Cmd::get(keys)).await?,
+        };
+        // Values are none if their keys expire after the keys are queried.
+        Ok(values
+            .iter()
+            .flatten()
+            .map(|el| serde_json::from_str::<Multipart>(el))
+            .collect::<Result<_, _>>()?)
+    }
+
     async fn delete_by_key_regex(&self, regex: &str) -> anyhow::Result<()> {
         let keys = self.query_keys(regex).await?;
         let key = keys.first().ok_or(anyhow!("No such multipart with regex: {regex}"))?;
@@ -44,6 +60,7 @@ impl MultipartRepo for RedisRepo {
                 .await?
                 .ok_or(FileException::MultipartNotFound { meta_id: id })?;
             multipart.shards.retain(|c| !c.eq(&nth));
+            multipart.updated_at = Utc::now();
             (self as &dyn LeaseRepository<Multipart>)
                 .update_with_lease(
                     &format!("multipart_{id}_{}", multipart.hash),
@@ -2,26 +2,60 @@ use async_trait::async_trait;
 use database_model::file_system;
 use std::sync::atomic::Ordering;
 
-use alice_architecture::repository::{DBRepository, MutableRepository, ReadOnlyRepository};
+use alice_architecture::repository::{
+    DBRepository, DbField, MutableRepository, ReadOnlyRepository,
+};
 
 use domain_storage::{
-    model::entity::{DirKind, FileType, NetDisk},
+    exception::FileException,
+    model::{
+        entity::{DbNetDisk, DirKind, FileType, NetDisk},
+        vo::{NetDiskPage, NetDiskUsage},
+    },
     repository::NetDiskRepo,
 };
 use sea_orm::{
-    prelude::*, sea_query::OnConflict, ActiveValue::Set, Condition, DatabaseBackend, QueryTrait,
-    Statement,
+    prelude::*,
+    sea_query::{OnConflict, Query, SelectStatement},
+    ActiveValue::{NotSet, Set},
+    Condition, DatabaseBackend, PaginatorTrait, QueryOrder, QueryTrait, Statement,
 };
 
 use crate::infrastructure::database::OrmRepo;
 
+impl OrmRepo {
+    /// Query the meta of the user's net disk entry.
+    fn net_disk_meta_query(&self, id: Uuid) -> anyhow::Result<SelectStatement> {
+        Ok(Query::select()
+            .column(file_system::Column::FileMetadataId)
+            .from(file_system::Entity)
+            .and_where(file_system::Column::Id.eq(id))
+            .and_where(file_system::Column::OwnerId.eq(self.user_id()?))
+            .to_owned())
+    }
+}
+
 #[async_trait::async_trait]
-impl ReadOnlyRepository<NetDisk> for OrmRepo {}
+impl ReadOnlyRepository<NetDisk> for OrmRepo {
+    async fn get_by_id(&self, uuid: Uuid) -> anyhow::Result<NetDisk> {
+        file_system::Entity::find_by_id(uuid)
+            .filter(file_system::Column::OwnerId.eq(self.user_id()?))
+            .one(self.db.get_connection())
+            .await?
+            .ok_or(FileException::NetDiskFileNotFound { id: uuid })?
+            .try_into()
+    }
+}
 
 #[async_trait::async_trait]
 impl MutableRepository<NetDisk> for OrmRepo {
     async fn insert(&self, entity: &NetDisk) -> anyhow::Result<Uuid> {
+        let lock_stmt = match entity.file_metadata_id {
+            Some(meta_id) => Some(self.lock_metas_stmt(&[meta_id]).await?),
+            None => None,
+        };
         let mut stmts = self.statements.lock().await;
+        stmts.extend(lock_stmt);
         // Use user_id and user_id + 1 as flowdraft、 flowinstance folder's root id, to avoid concurrency logic error.
         // For example, if all dirs use random uuid,
         //
@@ -73,6 +107,62 @@ impl MutableRepository<NetDisk> for OrmRepo {
         Ok(entity.id)
     }
 
+    async fn update(&self, entity: DbNetDisk) -> anyhow::Result<()> {
+        let mut reference_stmts = vec![];
+        if let DbField::Set(meta_id) = &entity.file_metadata_id {
+            let id = match &entity.id {
+                DbField::Set(id) | DbField::Unchanged(id) => *id,
+                DbField::NotSet => anyhow::bail!("Net disk id is required to update file!"),
+            };
+            // The replaced meta is dereferenced.
+            reference_stmts.push(self.dereference_stmt(self.net_disk_meta_query(id)?));
+            if let Some(meta_id) = meta_id {
+                reference_stmts.push(self.lock_metas_stmt(&[*meta_id]).await?);
+            }
+        }
+        let mut stmts = self.statements.lock().await;
+        stmts.extend(reference_stmts);
+        let active_model = file_system::ActiveModel {
+            id: entity.id.into_active_value(),
+            parent_id: entity.parent_id.into_active_value(),
+            name: entity.name.into_active_value(),
+            is_dict: entity.is_dict.into_active_value(),
+            kind: match entity.kind {
+                DbField::Set(el) | DbField::Unchanged(el) => Set(el as i32),
+                DbField::NotSet => NotSet,
+            },
+            file_metadata_id: entity.file_metadata_id.into_active_value(),
+            meta: match entity.meta {
+                DbField::Set(el) | DbField::Unchanged(el) => {
+                    Set(el.map(serde_json::to_value).transpose()?)
+                }
+                DbField::NotSet => NotSet,
+            },
+            ..Default::default()
+        };
+        let stmt = file_system::Entity::update(active_model)
+            .filter(file_system::Column::OwnerId.eq(self.user_id()?))
+            .build(self.db.get_connection().get_database_backend());
+        stmts.push(stmt);
+        self.can_drop.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn delete_by_id(&self, uuid: Uuid) -> anyhow::Result<()> {
+        let mut stmts = self.statements.lock().await;
+        stmts.push(self.dereference_stmt(self.net_disk_meta_query(uuid)?));
+        let stmt = file_system::Entity::delete_many()
+            .filter(
+                Condition::all()
+                    .add(file_system::Column::Id.eq(uuid))
+                    .add(file_system::Column::OwnerId.eq(self.user_id()?)),
+            )
+            .build(self.db.get_connection().get_database_backend());
+        stmts.push(stmt);
+        self.can_drop.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
     async fn save_changed(&self) -> anyhow::Result<bool> {
         self.save_changed().await
     }
@@ -207,4 +297,69 @@ impl NetDiskRepo for OrmRepo {
             .await?
             .map(|el| el.id))
     }
+
+    async fn get_children(&self, parent_id: Uuid) -> anyhow::Result<Vec<NetDisk>> {
+        file_system::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(file_system::Column::ParentId.eq(parent_id))
+                    .add(file_system::Column::OwnerId.eq(self.user_id()?)),
+            )
+            .all(self.db.get_connection())
+            .await?
+            .into_iter()
+            .map(NetDisk::try_from)
+            .collect()
+    }
+
+    async fn get_children_paged(
+        &self,
+        parent_id: Uuid,
+        page: u64,
+        page_size: u64,
+    ) -> anyhow::Result<NetDiskPage> {
+        let paginator = file_system::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(file_system::Column::ParentId.eq(parent_id))
+                    .add(file_system::Column::OwnerId.eq(self.user_id()?)),
+            )
+            .order_by_desc(file_system::Column::IsDict)
+            .order_by_asc(file_system::Column::Name)
+            .paginate(self.db.get_connection(), page_size);
+        let total = paginator.num_items().await?;
+        let items = paginator
+            .fetch_page(page - 1)
+            .await?
+            .into_iter()
+            .map(NetDisk::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(NetDiskPage {
+            items,
+            total: total as u64,
+        })
+    }
+
+    async fn get_usage(&self) -> anyhow::Result<NetDiskUsage> {
+        let mut sql = String::from("SELECT COALESCE(SUM(m.size), 0)::BIGINT AS used,");
+        sql.push_str(" COUNT(*) AS file_count");
+        sql.push_str(" FROM file_system f JOIN file_metadata m ON f.file_metadata_id = m.id");
+        sql.push_str(" WHERE f.owner_id = $1 AND NOT f.is_dict");
+        let result = self
+            .db
+            .get_connection()
+            .query_one(Statement::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                &sql,
+                vec![self.user_id()?.into()],
+            ))
+            .await?
+            .ok_or(anyhow::anyhow!("No result when sum used size of net disk."))?;
+        let used: i64 = result.try_get("", "used")?;
+        let file_count: i64 = result.try_get("", "file_count")?;
+        Ok(NetDiskUsage {
+            used: used as u64,
+            file_count: file_count as u64,
+        })
+    }
 }
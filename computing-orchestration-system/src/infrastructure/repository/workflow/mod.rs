@@ -1,6 +1,9 @@
 mod installed_software;
 mod node_instance;
 mod queue;
+mod queue_cache;
+mod quota;
+mod scheduler_backlog;
 mod software_block_list;
 mod task;
 mod workflow_draft;
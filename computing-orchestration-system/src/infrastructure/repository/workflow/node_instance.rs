@@ -153,6 +153,7 @@ impl NodeInstanceRepo for OrmRepo {
         Ok(res.iter().all(|el| {
             el.status.eq(&(NodeInstanceStatus::Completed as i32))
                 || el.status.eq(&(NodeInstanceStatus::Standby as i32))
+                || el.status.eq(&(NodeInstanceStatus::Skipped as i32))
         }))
     }
 
@@ -221,6 +222,22 @@ impl NodeInstanceRepo for OrmRepo {
         Ok(node_spec)
     }
 
+    async fn get_running_nodes_by_queue_ids(
+        &self,
+        queue_ids: &[Uuid],
+    ) -> anyhow::Result<Vec<NodeInstance>> {
+        let res = node_instance::Entity::find()
+            .filter(node_instance::Column::QueueId.is_in(queue_ids.to_owned()))
+            .filter(node_instance::Column::Status.eq(NodeInstanceStatus::Running as i32))
+            .all(self.db.get_connection())
+            .await?;
+        let mut r = vec![];
+        for el in res.into_iter() {
+            r.push(el.try_into()?);
+        }
+        Ok(r)
+    }
+
     async fn update_immediately_with_lock(&self, entity: DbNodeInstance) -> anyhow::Result<()> {
         let last_modified_time = entity.last_modified_time.value()?.to_owned();
 
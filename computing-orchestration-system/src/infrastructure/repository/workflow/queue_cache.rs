@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use domain_workflow::{
+    model::entity::{
+        queue::{QueueCacheInfo, QueueResourceUsed, QueueTaskCount},
+        Queue,
+    },
+    repository::QueueCacheRepo,
+};
+use redis::Cmd;
+use uuid::Uuid;
+
+use crate::infrastructure::database::RedisRepo;
+
+const QUEUE_CACHE_KEY_PREFIX: &str = "queue_cache_";
+const QUEUE_RESERVATIONS_KEY_PREFIX: &str = "queue_reservations_";
+const QUEUE_ROUND_KEY_PREFIX: &str = "queue_round_";
+
+const MEMORY_USED: &str = "memory_used";
+const CORE_NUMBER_USED: &str = "core_number_used";
+const STORAGE_CAPACITY_USED: &str = "storage_capacity_used";
+const NODE_NUMBER_USED: &str = "node_number_used";
+const QUEUING_TASK_COUNT: &str = "queuing_task_count";
+const RUNNING_TASK_COUNT: &str = "running_task_count";
+/// Prefix of fields reported by the agent, they are kept apart from the fields counted by
+/// reservations so that reports never overwrite reservations.
+const REPORTED_PREFIX: &str = "reported_";
+
+/// Resource fields in the order they are recorded in a reservation.
+const RESOURCE_FIELDS: [&str; 4] = [
+    MEMORY_USED,
+    CORE_NUMBER_USED,
+    STORAGE_CAPACITY_USED,
+    NODE_NUMBER_USED,
+];
+
+/// Reserve resources for a node atomically.
+/// KEYS are the cache hash and the reservation hash, ARGV[1] is the node id, ARGV[2] is the
+/// reservation record, the rest is a list of (field, increment, limit) triples, limit is empty
+/// when there is no limit. A field is compared with the larger one of itself and its reported
+/// value. If any field reaches its limit, nothing is changed and the field is returned, otherwise
+/// an empty string is returned. A node already holding a reservation is not reserved again.
+const RESERVE_SCRIPT: &str = r#"
+if redis.call('HEXISTS', KEYS[2], ARGV[1]) == 1 then
+    return ''
+end
+for i = 3, #ARGV, 3 do
+    local limit = ARGV[i + 2]
+    if limit ~= '' then
+        local used = math.max(
+            tonumber(redis.call('HGET', KEYS[1], ARGV[i]) or '0'),
+            tonumber(redis.call('HGET', KEYS[1], 'reported_' .. ARGV[i]) or '0'))
+        if used + tonumber(ARGV[i + 1]) >= tonumber(limit) then
+            return ARGV[i]
+        end
+    end
+end
+for i = 3, #ARGV, 3 do
+    redis.call('HINCRBY', KEYS[1], ARGV[i], ARGV[i + 1])
+end
+redis.call('HSET', KEYS[2], ARGV[1], ARGV[2])
+return ''
+"#;
+
+/// Move the reservation of a node from queuing to running, at most once.
+/// KEYS are the cache hash and the reservation hash, ARGV is the node id, the queuing field and
+/// the running field. The last element of a reservation record tells if it is started.
+const START_SCRIPT: &str = r#"
+local record = redis.call('HGET', KEYS[2], ARGV[1])
+if not record then
+    return 0
+end
+local values = {}
+for el in string.gmatch(record, '%S+') do
+    table.insert(values, el)
+end
+if values[#values] == '1' then
+    return 0
+end
+values[#values] = '1'
+redis.call('HINCRBY', KEYS[1], ARGV[2], -1)
+redis.call('HINCRBY', KEYS[1], ARGV[3], 1)
+redis.call('HSET', KEYS[2], ARGV[1], table.concat(values, ' '))
+return 1
+"#;
+
+/// Release the reservation of a node, at most once.
+/// KEYS are the cache hash and the reservation hash, ARGV is the node id, the queuing field, the
+/// running field, then the resource fields in the order of the reservation record.
+const RELEASE_SCRIPT: &str = r#"
+local record = redis.call('HGET', KEYS[2], ARGV[1])
+if not record then
+    return 0
+end
+local values = {}
+for el in string.gmatch(record, '%S+') do
+    table.insert(values, el)
+end
+for i = 4, #ARGV do
+    redis.call('HINCRBY', KEYS[1], ARGV[i], -tonumber(values[i - 3]))
+end
+if values[#values] == '1' then
+    redis.call('HINCRBY', KEYS[1], ARGV[3], -1)
+else
+    redis.call('HINCRBY', KEYS[1], ARGV[2], -1)
+end
+redis.call('HDEL', KEYS[2], ARGV[1])
+return 1
+"#;
+
+impl RedisRepo {
+    /// Keys of the cache hash and the reservation hash of the queue, they share the hash tag so
+    /// that scripts can use both in a cluster.
+    fn queue_cache_keys(queue_id: Uuid) -> (String, String) {
+        (
+            format!("{QUEUE_CACHE_KEY_PREFIX}{{{queue_id}}}"),
+            format!("{QUEUE_RESERVATIONS_KEY_PREFIX}{{{queue_id}}}"),
+        )
+    }
+
+    /// Build EVAL of the script on keys of the queue, arguments are added by the caller.
+    fn queue_cache_script(script: &str, queue_id: Uuid) -> redis::Cmd {
+        let (cache_key, reservations_key) = Self::queue_cache_keys(queue_id);
+        let mut cmd = redis::cmd("EVAL");
+        cmd.arg(script).arg(2).arg(cache_key).arg(reservations_key);
+        cmd
+    }
+}
+
+#[async_trait]
+impl QueueCacheRepo for RedisRepo {
+    async fn get_cache_info(&self, queue_id: Uuid) -> anyhow::Result<QueueCacheInfo> {
+        let (cache_key, _) = Self::queue_cache_keys(queue_id);
+        let fields = self.query::<HashMap<String, i64>>(&Cmd::hgetall(cache_key)).await?;
+        let field = |name: &str| {
+            let reserved = fields.get(name).copied().unwrap_or_default();
+            let reported =
+                fields.get(&format!("{REPORTED_PREFIX}{name}")).copied().unwrap_or_default();
+            reserved.max(reported)
+        };
+        Ok(QueueCacheInfo {
+            used: QueueResourceUsed {
+                memory_used: field(MEMORY_USED),
+                core_number_used: field(CORE_NUMBER_USED),
+                storage_capacity_used: field(STORAGE_CAPACITY_USED),
+                node_number_used: field(NODE_NUMBER_USED),
+            },
+            task_count: QueueTaskCount {
+                queuing_task_count: field(QUEUING_TASK_COUNT),
+                running_task_count: field(RUNNING_TASK_COUNT),
+            },
+        })
+    }
+
+    async fn set_reported_usage(
+        &self,
+        queue_id: Uuid,
+        info: &QueueCacheInfo,
+    ) -> anyhow::Result<()> {
+        let (cache_key, _) = Self::queue_cache_keys(queue_id);
+        let mut cmd = redis::cmd("HSET");
+        cmd.arg(cache_key);
+        for (field, value) in [
+            (MEMORY_USED, info.used.memory_used),
+            (CORE_NUMBER_USED, info.used.core_number_used),
+            (STORAGE_CAPACITY_USED, info.used.storage_capacity_used),
+            (NODE_NUMBER_USED, info.used.node_number_used),
+            (QUEUING_TASK_COUNT, info.task_count.queuing_task_count),
+            (RUNNING_TASK_COUNT, info.task_count.running_task_count),
+        ] {
+            cmd.arg(format!("{REPORTED_PREFIX}{field}")).arg(value);
+        }
+        self.query::<()>(&cmd).await?;
+        Ok(())
+    }
+
+    async fn reserve(
+        &self,
+        queue: &Queue,
+        node_id: Uuid,
+        used: &QueueResourceUsed,
+    ) -> anyhow::Result<()> {
+        let fields = [
+            (MEMORY_USED, used.memory_used, queue.memory_alert),
+            (
+                CORE_NUMBER_USED,
+                used.core_number_used,
+                queue.core_number_alert,
+            ),
+            (
+                STORAGE_CAPACITY_USED,
+                used.storage_capacity_used,
+                queue.storage_capacity_alert,
+            ),
+            (
+                NODE_NUMBER_USED,
+                used.node_number_used,
+                queue.max_node_count,
+            ),
+        ];
+        // The record is the resources in the order of RESOURCE_FIELDS and whether it is started.
+        let record = fields
+            .iter()
+            .map(|(_, increment, _)| increment.to_string())
+            .chain(["0".to_string()])
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut cmd = Self::queue_cache_script(RESERVE_SCRIPT, queue.id);
+        cmd.arg(node_id.to_string()).arg(record);
+        for (field, increment, limit) in
+            fields
+                .into_iter()
+                .chain([(QUEUING_TASK_COUNT, 1, queue.max_queuing_task_count)])
+        {
+            cmd.arg(field)
+                .arg(increment)
+                .arg(limit.map(|el| el.to_string()).unwrap_or_default());
+        }
+        let full_field = self.query::<String>(&cmd).await?;
+        if !full_field.is_empty() {
+            anyhow::bail!("queue {} {full_field} reaches the limit", queue.id);
+        }
+        Ok(())
+    }
+
+    async fn start(&self, queue_id: Uuid, node_id: Uuid) -> anyhow::Result<()> {
+        let mut cmd = Self::queue_cache_script(START_SCRIPT, queue_id);
+        cmd.arg(node_id.to_string()).arg(QUEUING_TASK_COUNT).arg(RUNNING_TASK_COUNT);
+        self.query::<i64>(&cmd).await?;
+        Ok(())
+    }
+
+    async fn release(&self, queue_id: Uuid, node_id: Uuid) -> anyhow::Result<bool> {
+        let mut cmd = Self::queue_cache_script(RELEASE_SCRIPT, queue_id);
+        cmd.arg(node_id.to_string())
+            .arg(QUEUING_TASK_COUNT)
+            .arg(RUNNING_TASK_COUNT)
+            .arg(&RESOURCE_FIELDS[..]);
+        Ok(self.query::<i64>(&cmd).await? == 1)
+    }
+
+    async fn next_round(&self, queue_set: &str) -> anyhow::Result<usize> {
+        let round = self
+            .query::<u64>(&Cmd::incr(
+                format!("{QUEUE_ROUND_KEY_PREFIX}{queue_set}"),
+                1,
+            ))
+            .await?;
+        // INCR starts from 1.
+        Ok(round.saturating_sub(1) as usize)
+    }
+}
@@ -0,0 +1,128 @@
+use async_trait::async_trait;
+use domain_workflow::{
+    model::vo::quota::{QuotaLimit, QuotaSubject, QuotaUsage},
+    repository::QuotaRepo,
+};
+use redis::Cmd;
+use uuid::Uuid;
+
+use crate::infrastructure::database::RedisRepo;
+
+/// Hash of running node id to its cores.
+const QUOTA_RUNNING_KEY_PREFIX: &str = "quota_running_";
+/// Accumulated core hours.
+const QUOTA_SPEND_KEY_PREFIX: &str = "quota_spend_";
+
+/// Add a running node if the running nodes and cores are within limits, the same as
+/// `QuotaLimit::check`.
+/// KEYS[1] is the running hash, ARGV is the node id, its cores, max running nodes and max cores,
+/// limits are empty when there is no limit. Returns whether it is added, with the running nodes
+/// and cores before adding. A node already added is not added again.
+const ADD_RUNNING_SCRIPT: &str = r#"
+if redis.call('HEXISTS', KEYS[1], ARGV[1]) == 1 then
+    return {1, 0, 0}
+end
+local values = redis.call('HVALS', KEYS[1])
+local cores = 0
+for _, el in ipairs(values) do
+    cores = cores + tonumber(el)
+end
+local added = 1
+if ARGV[3] ~= '' and #values >= tonumber(ARGV[3]) then
+    added = 0
+end
+if ARGV[4] ~= '' and cores + tonumber(ARGV[2]) > tonumber(ARGV[4]) then
+    added = 0
+end
+if added == 1 then
+    redis.call('HSET', KEYS[1], ARGV[1], ARGV[2])
+end
+return {added, #values, cores}
+"#;
+
+fn subject_key(prefix: &str, subject: &QuotaSubject) -> String {
+    match subject {
+        QuotaSubject::User(id) => format!("{prefix}user_{id}"),
+        QuotaSubject::Project(id) => format!("{prefix}project_{id}"),
+    }
+}
+
+#[async_trait]
+impl QuotaRepo for RedisRepo {
+    async fn get_usage(&self, subject: &QuotaSubject) -> anyhow::Result<QuotaUsage> {
+        let cores = self
+            .query::<Vec<u64>>(&Cmd::hvals(subject_key(QUOTA_RUNNING_KEY_PREFIX, subject)))
+            .await?;
+        let core_hours = self
+            .query::<Option<f64>>(&Cmd::get(subject_key(QUOTA_SPEND_KEY_PREFIX, subject)))
+            .await?;
+        Ok(QuotaUsage {
+            running_nodes: cores.len() as u64,
+            cores: cores.iter().sum(),
+            core_hours: core_hours.unwrap_or_default(),
+        })
+    }
+
+    async fn add_running_node(
+        &self,
+        subject: &QuotaSubject,
+        node_id: Uuid,
+        cores: u64,
+    ) -> anyhow::Result<()> {
+        self.query::<()>(&Cmd::hset(
+            subject_key(QUOTA_RUNNING_KEY_PREFIX, subject),
+            node_id.to_string(),
+            cores,
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn try_add_running_node(
+        &self,
+        subject: &QuotaSubject,
+        node_id: Uuid,
+        cores: u64,
+        limit: &QuotaLimit,
+    ) -> anyhow::Result<Option<QuotaUsage>> {
+        let mut cmd = redis::cmd("EVAL");
+        cmd.arg(ADD_RUNNING_SCRIPT)
+            .arg(1)
+            .arg(subject_key(QUOTA_RUNNING_KEY_PREFIX, subject))
+            .arg(node_id.to_string())
+            .arg(cores)
+            .arg(limit.max_running_nodes.map(|el| el.to_string()).unwrap_or_default())
+            .arg(limit.max_cores.map(|el| el.to_string()).unwrap_or_default());
+        let (added, running_nodes, cores) = self.query::<(u64, u64, u64)>(&cmd).await?;
+        if added == 1 {
+            return Ok(None);
+        }
+        Ok(Some(QuotaUsage {
+            running_nodes,
+            cores,
+            ..Default::default()
+        }))
+    }
+
+    async fn remove_running_node(
+        &self,
+        subject: &QuotaSubject,
+        node_id: Uuid,
+    ) -> anyhow::Result<()> {
+        self.query::<()>(&Cmd::hdel(
+            subject_key(QUOTA_RUNNING_KEY_PREFIX, subject),
+            node_id.to_string(),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn add_core_hours(&self, subject: &QuotaSubject, core_hours: f64) -> anyhow::Result<()> {
+        self.query::<()>(&Cmd::incr(
+            subject_key(QUOTA_SPEND_KEY_PREFIX, subject),
+            core_hours,
+        ))
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use async_trait::async_trait;
+use domain_workflow::{
+    model::vo::backlog::{BacklogEntry, Preemption},
+    repository::SchedulerBacklogRepo,
+};
+use redis::Cmd;
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use crate::infrastructure::database::RedisRepo;
+
+const SCHEDULER_BACKLOG_KEY: &str = "scheduler_backlog";
+const SCHEDULER_PREEMPTION_KEY: &str = "scheduler_preemption";
+
+/// Get and delete a field of a hash atomically, returns nil when the field doesn't exist.
+const REMOVE_SCRIPT: &str = r#"
+local value = redis.call('HGET', KEYS[1], ARGV[1])
+if value then
+    redis.call('HDEL', KEYS[1], ARGV[1])
+end
+return value
+"#;
+
+impl RedisRepo {
+    /// Get and delete a json value in a hash atomically.
+    async fn remove_hash_json<T: DeserializeOwned>(
+        &self,
+        key: &str,
+        field: Uuid,
+    ) -> anyhow::Result<Option<T>> {
+        let mut cmd = redis::cmd("EVAL");
+        cmd.arg(REMOVE_SCRIPT).arg(1).arg(key).arg(field.to_string());
+        Ok(match self.query::<Option<String>>(&cmd).await? {
+            Some(value) => Some(serde_json::from_str(&value)?),
+            None => None,
+        })
+    }
+
+    /// Get all json values in a hash.
+    async fn get_hash_jsons<T: DeserializeOwned>(&self, key: &str) -> anyhow::Result<Vec<T>> {
+        let values = self.query::<Vec<String>>(&Cmd::hvals(key)).await?;
+        Ok(values.iter().map(|el| serde_json::from_str(el)).collect::<Result<_, _>>()?)
+    }
+}
+
+#[async_trait]
+impl SchedulerBacklogRepo for RedisRepo {
+    async fn push(&self, entry: &BacklogEntry) -> anyhow::Result<()> {
+        self.query::<()>(&Cmd::hset_nx(
+            SCHEDULER_BACKLOG_KEY,
+            entry.node_id.to_string(),
+            serde_json::to_string(entry)?,
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn remove(&self, node_id: Uuid) -> anyhow::Result<Option<BacklogEntry>> {
+        self.remove_hash_json(SCHEDULER_BACKLOG_KEY, node_id).await
+    }
+
+    async fn get_all(&self) -> anyhow::Result<Vec<BacklogEntry>> {
+        self.get_hash_jsons(SCHEDULER_BACKLOG_KEY).await
+    }
+
+    async fn push_preemption(&self, preemption: &Preemption) -> anyhow::Result<()> {
+        self.query::<()>(&Cmd::hset(
+            SCHEDULER_PREEMPTION_KEY,
+            preemption.node_id.to_string(),
+            serde_json::to_string(preemption)?,
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn remove_preemption(&self, node_id: Uuid) -> anyhow::Result<Option<Preemption>> {
+        self.remove_hash_json(SCHEDULER_PREEMPTION_KEY, node_id).await
+    }
+
+    async fn get_preemptions(&self) -> anyhow::Result<Vec<Preemption>> {
+        self.get_hash_jsons(SCHEDULER_PREEMPTION_KEY).await
+    }
+}
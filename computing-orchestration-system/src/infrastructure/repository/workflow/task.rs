@@ -1,15 +1,21 @@
+use std::collections::HashMap;
 use std::sync::atomic::Ordering;
 
-use crate::infrastructure::database::OrmRepo;
-use alice_architecture::repository::{DBRepository, MutableRepository, ReadOnlyRepository};
+use crate::infrastructure::database::{entity::task_attempts, OrmRepo};
+use alice_architecture::repository::{
+    DBRepository, DbField, MutableRepository, ReadOnlyRepository,
+};
 use anyhow::Context;
+use chrono::{DateTime, Utc};
 use database_model::{node_instance, queue, task};
-use domain_workflow::model::entity::task::{DbTask, Task};
+use domain_workflow::model::entity::task::{DbTask, Task, TaskAttempt};
 use domain_workflow::model::entity::NodeInstance;
 use domain_workflow::repository::TaskRepo;
 use num_traits::FromPrimitive;
 use sea_orm::{
-    ColumnTrait, ConnectionTrait, EntityTrait, ModelTrait, QueryFilter, QueryOrder, QueryTrait, Set,
+    sea_query::{Expr, IntoCondition},
+    ActiveValue::Unchanged,
+    ColumnTrait, EntityTrait, ModelTrait, QueryFilter, QueryOrder, QueryTrait, Set,
 };
 use uuid::Uuid;
 
@@ -20,6 +26,33 @@ impl DBRepository<Task> for OrmRepo {}
 impl MutableRepository<Task> for OrmRepo {
     async fn update(&self, entity: DbTask) -> anyhow::Result<()> {
         let mut stmts = self.statements.lock().await;
+        let backend = self.db.get_connection().get_database_backend();
+        // Attempts columns are not in the task model, they are updated by their own entity.
+        if matches!(entity.attempts, DbField::Set(_))
+            || matches!(entity.next_attempt_at, DbField::Set(_))
+        {
+            let id = match &entity.id {
+                DbField::Set(id) | DbField::Unchanged(id) => *id,
+                DbField::NotSet => anyhow::bail!("Task id is required to update attempts!"),
+            };
+            let active_model = task_attempts::ActiveModel {
+                id: Unchanged(id),
+                attempts: entity.attempts.try_into()?,
+                next_attempt_at: entity.next_attempt_at.into_active_value(),
+                ..Default::default()
+            };
+            stmts.push(task_attempts::Entity::update(active_model).build(backend));
+        }
+        let has_model_columns = matches!(entity.status, DbField::Set(_))
+            || matches!(entity.node_instance_id, DbField::Set(_))
+            || matches!(entity.body, DbField::Set(_))
+            || matches!(entity.r#type, DbField::Set(_))
+            || matches!(entity.message, DbField::Set(_))
+            || matches!(entity.used_resources, DbField::Set(_));
+        if !has_model_columns {
+            self.can_drop.store(false, std::sync::atomic::Ordering::Relaxed);
+            return Ok(());
+        }
         let active_model = task::ActiveModel {
             id: entity.id.into_active_value(),
             status: entity.status.into(),
@@ -30,8 +63,7 @@ impl MutableRepository<Task> for OrmRepo {
             used_resources: entity.used_resources.try_into()?,
             ..Default::default()
         };
-        let stmt = task::Entity::update(active_model)
-            .build(self.db.get_connection().get_database_backend());
+        let stmt = task::Entity::update(active_model).build(backend);
         stmts.push(stmt);
         self.can_drop.store(false, std::sync::atomic::Ordering::Relaxed);
         Ok(())
@@ -39,22 +71,40 @@ impl MutableRepository<Task> for OrmRepo {
 
     async fn insert_list(&self, entities: &[Task]) -> anyhow::Result<Vec<Uuid>> {
         let mut stmts = self.statements.lock().await;
-        let f = |n: usize, t: Task| -> anyhow::Result<task::ActiveModel> {
-            Ok(task::ActiveModel {
+        let f = |n: usize, t: Task| -> task::ActiveModel {
+            task::ActiveModel {
                 id: Set(t.id),
                 node_instance_id: Set(t.node_instance_id),
-                body: Set(serde_json::to_value(t.body)?),
+                body: Set(t.body),
                 r#type: Set(t.r#type.to_owned() as i32),
                 status: Set(t.status.to_owned() as i32),
                 number: Set(n as i32),
                 ..Default::default()
-            })
+            }
         };
-        let active_models: anyhow::Result<Vec<_>> =
-            entities.iter().cloned().enumerate().map(|(n, entity)| f(n, entity)).collect();
-        let stmt = task::Entity::insert_many(active_models?)
+        let active_models = entities
+            .iter()
+            .cloned()
+            .enumerate()
+            .map(|(n, entity)| f(n, entity))
+            .collect::<Vec<_>>();
+        let stmt = task::Entity::insert_many(active_models)
             .build(self.db.get_connection().get_database_backend());
         stmts.push(stmt);
+        for entity in entities.iter() {
+            if entity.attempts.is_empty() && entity.next_attempt_at.is_none() {
+                continue;
+            }
+            let active_model = task_attempts::ActiveModel {
+                id: Unchanged(entity.id),
+                attempts: Set(serde_json::to_value(&entity.attempts)?),
+                next_attempt_at: Set(entity.next_attempt_at),
+                ..Default::default()
+            };
+            let stmt = task_attempts::Entity::update(active_model)
+                .build(self.db.get_connection().get_database_backend());
+            stmts.push(stmt);
+        }
         self.can_drop.store(false, Ordering::Relaxed);
         Ok(entities.iter().map(|e| e.id).collect::<Vec<_>>())
     }
@@ -91,6 +141,11 @@ impl ReadOnlyRepository<Task> for OrmRepo {
                     node_instance.queue_id.unwrap()
                 )
             })?;
+        let (attempts, next_attempt_at) = self
+            .get_attempts(task_attempts::Column::Id.eq(uuid))
+            .await?
+            .remove(&uuid)
+            .unwrap_or_default();
         Ok(Task {
             id: task.id,
             node_instance_id: task.node_instance_id,
@@ -100,6 +155,8 @@ impl ReadOnlyRepository<Task> for OrmRepo {
             message: task.message,
             used_resources: task.used_resources,
             queue_topic: queue.topic_name,
+            attempts,
+            next_attempt_at,
         })
     }
 }
@@ -123,6 +180,9 @@ impl TaskRepo for OrmRepo {
             .get_by_id(queue_id)
             .await?;
         let queue_topic = queue.topic_name;
+        let mut attempts = self
+            .get_attempts(task_attempts::Column::NodeInstanceId.eq(node_instance_id))
+            .await?;
         task::Entity::find()
             .filter(task::Column::NodeInstanceId.eq(node_instance_id))
             .order_by_asc(task::Column::Number)
@@ -131,6 +191,7 @@ impl TaskRepo for OrmRepo {
             .into_iter()
             .map(|m| {
                 let queue_topic = queue_topic.to_owned();
+                let (attempts, next_attempt_at) = attempts.remove(&m.id).unwrap_or_default();
                 Ok(Task {
                     id: m.id,
                     node_instance_id: m.node_instance_id,
@@ -140,8 +201,58 @@ impl TaskRepo for OrmRepo {
                     message: m.message,
                     used_resources: m.used_resources,
                     queue_topic,
+                    attempts,
+                    next_attempt_at,
                 })
             })
             .collect::<anyhow::Result<Vec<Task>>>()
     }
+
+    async fn delete_by_node_id(&self, node_id: Uuid) -> anyhow::Result<()> {
+        let mut stmts = self.statements.lock().await;
+        let stmt = task::Entity::delete_many()
+            .filter(task::Column::NodeInstanceId.eq(node_id))
+            .build(self.db.get_connection().get_database_backend());
+        stmts.push(stmt);
+        self.can_drop.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn take_due_retries(&self, before: DateTime<Utc>) -> anyhow::Result<Vec<Uuid>> {
+        Ok(task_attempts::Entity::update_many()
+            .col_expr(
+                task_attempts::Column::NextAttemptAt,
+                Expr::value(Option::<DateTime<Utc>>::None),
+            )
+            .filter(task_attempts::Column::NextAttemptAt.lte(before))
+            .exec_with_returning(self.db.get_connection())
+            .await?
+            .into_iter()
+            .map(|m| m.id)
+            .collect())
+    }
+}
+
+/// Attempts history and the time of the pending retry of a task.
+type Attempts = (Vec<TaskAttempt>, Option<DateTime<Utc>>);
+
+impl OrmRepo {
+    /// Get attempts columns of tasks matching the condition, they are not in the task model.
+    async fn get_attempts(
+        &self,
+        condition: impl IntoCondition,
+    ) -> anyhow::Result<HashMap<Uuid, Attempts>> {
+        task_attempts::Entity::find()
+            .filter(condition)
+            .all(self.db.get_connection())
+            .await?
+            .into_iter()
+            .map(|m| {
+                Ok((
+                    m.id,
+                    (serde_json::from_value(m.attempts)?, m.next_attempt_at),
+                ))
+            })
+            .collect()
+    }
 }
@@ -1,6 +1,8 @@
 use std::sync::atomic::Ordering;
 
-use alice_architecture::repository::{DBRepository, MutableRepository, ReadOnlyRepository};
+use alice_architecture::repository::{
+    DBRepository, DbField, MutableRepository, ReadOnlyRepository,
+};
 
 use database_model::flow_instance;
 use domain_workflow::{
@@ -8,9 +10,9 @@ use domain_workflow::{
     repository::WorkflowInstanceRepo,
 };
 use sea_orm::{prelude::*, Set};
-use sea_orm::{Condition, QueryTrait};
+use sea_orm::{Condition, QueryTrait, Statement, TransactionTrait};
 
-use crate::infrastructure::database::OrmRepo;
+use crate::infrastructure::database::{entity::file_reference, OrmRepo};
 
 #[async_trait::async_trait]
 impl ReadOnlyRepository<WorkflowInstance> for OrmRepo {
@@ -30,6 +32,7 @@ impl ReadOnlyRepository<WorkflowInstance> for OrmRepo {
 #[async_trait::async_trait]
 impl MutableRepository<WorkflowInstance> for OrmRepo {
     async fn update(&self, entity: DbWorkflowInstance) -> anyhow::Result<()> {
+        let reference_stmts = self.spec_reference_stmts(&entity).await?;
         let mut stmts = self.statements.lock().await;
         let active_model = flow_instance::ActiveModel {
             id: entity.id.into_active_value(),
@@ -40,12 +43,14 @@ impl MutableRepository<WorkflowInstance> for OrmRepo {
             description: entity.description.into_active_value(),
             logo: entity.logo.into_active_value(),
             user_id: entity.user_id.into_active_value(),
+            project_id: entity.project_id.into_active_value(),
             ..Default::default()
         };
         let stmt = flow_instance::Entity::update(active_model)
             // .filter(flow_instance::Column::UserId.eq(self.user_id()?))
             .build(self.db.get_connection().get_database_backend());
         stmts.push(stmt);
+        stmts.extend(reference_stmts);
         self.can_drop.store(false, Ordering::Relaxed);
         Ok(())
     }
@@ -59,11 +64,22 @@ impl MutableRepository<WorkflowInstance> for OrmRepo {
             status: Set(entity.status.to_owned() as i32),
             spec: Set(serde_json::to_value(entity.spec.to_owned())?),
             user_id: Set(self.user_id()?),
+            project_id: Set(entity.project_id),
             ..Default::default()
         };
-        flow_instance::Entity::insert(active_model)
-            .exec(self.db.get_connection())
+        let reference_stmts = self
+            .reference_stmts(
+                file_reference::WORKFLOW_INSTANCE,
+                entity.id,
+                &entity.spec.file_ids(),
+            )
             .await?;
+        let trans = self.db.get_connection().begin().await?;
+        flow_instance::Entity::insert(active_model).exec(&trans).await?;
+        for stmt in reference_stmts {
+            trans.execute(stmt).await?;
+        }
+        trans.commit().await?;
         Ok(entity.id)
     }
 
@@ -86,6 +102,7 @@ impl WorkflowInstanceRepo for OrmRepo {
 
     async fn update_immediately_with_lock(&self, entity: DbWorkflowInstance) -> anyhow::Result<()> {
         let last_modified_time = entity.last_modified_time.value()?.to_owned();
+        let reference_stmts = self.spec_reference_stmts(&entity).await?;
 
         let active_model = flow_instance::ActiveModel {
             id: entity.id.into_active_value(),
@@ -95,6 +112,7 @@ impl WorkflowInstanceRepo for OrmRepo {
             description: entity.description.into_active_value(),
             logo: entity.logo.into_active_value(),
             user_id: entity.user_id.into_active_value(),
+            project_id: entity.project_id.into_active_value(),
             ..Default::default()
         };
         let stmt = flow_instance::Entity::update(active_model)
@@ -103,10 +121,35 @@ impl WorkflowInstanceRepo for OrmRepo {
                     .add(flow_instance::Column::LastModifiedTime.eq(last_modified_time)),
             )
             .build(self.db.get_connection().get_database_backend());
-        let rows_affected = self.db.get_connection().execute(stmt).await?.rows_affected();
+        let trans = self.db.get_connection().begin().await?;
+        let rows_affected = trans.execute(stmt).await?.rows_affected();
         if rows_affected == 0 {
+            trans.rollback().await?;
             anyhow::bail!("No rows affected when update workflow instance.")
         }
+        for stmt in reference_stmts {
+            trans.execute(stmt).await?;
+        }
+        trans.commit().await?;
         Ok(())
     }
 }
+
+impl OrmRepo {
+    /// Build statements referencing the files used by the spec, none if the spec isn't set.
+    async fn spec_reference_stmts(
+        &self,
+        entity: &DbWorkflowInstance,
+    ) -> anyhow::Result<Vec<Statement>> {
+        let spec = match &entity.spec {
+            DbField::Set(spec) => spec,
+            _ => return Ok(vec![]),
+        };
+        let id = match &entity.id {
+            DbField::Set(id) | DbField::Unchanged(id) => *id,
+            DbField::NotSet => anyhow::bail!("Workflow instance id is required to update spec!"),
+        };
+        self.reference_stmts(file_reference::WORKFLOW_INSTANCE, id, &spec.file_ids())
+            .await
+    }
+}
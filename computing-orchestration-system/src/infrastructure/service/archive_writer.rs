@@ -0,0 +1,239 @@
+use std::{pin::pin, sync::Arc};
+
+use actix_web::web::Bytes;
+use anyhow::{anyhow, bail};
+use async_zip::{tokio::write::ZipFileWriter, Compression, ZipEntryBuilder};
+use domain_storage::{model::vo::ArchiveListing, service::StorageServerDownloadDispatcherService};
+use futures::{Stream, TryStreamExt};
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::oneshot,
+};
+use tokio_tar::{Builder, EntryType, Header};
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+/// Size of the pipe between the archive writer and the response.
+const PIPE_BUFFER_SIZE: usize = 1024 * 1024;
+/// Size of each chunk of the response.
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+/// Size of each range of a file read from storage servers.
+const FILE_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveFormat {
+    #[default]
+    Zip,
+    Tar,
+}
+
+impl ArchiveFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "application/zip",
+            ArchiveFormat::Tar => "application/x-tar",
+        }
+    }
+}
+
+/// Write net disk directories as archives, files are fetched by ranges so only one chunk of a
+/// file is held in memory at a time.
+#[derive(TypedBuilder)]
+pub struct ArchiveWriter {
+    download_service: Arc<dyn StorageServerDownloadDispatcherService>,
+}
+
+impl ArchiveWriter {
+    /// Stream the archive, it is written in background while the stream is read.
+    ///
+    /// The stream ends with an error if writing fails, so that the response is aborted rather
+    /// than ending as a truncated archive, as it has already started by then.
+    pub fn stream(
+        self: Arc<Self>,
+        listing: ArchiveListing,
+        format: ArchiveFormat,
+    ) -> impl Stream<Item = std::io::Result<Bytes>> {
+        let (writer, reader) = tokio::io::duplex(PIPE_BUFFER_SIZE);
+        let (result_sender, result_receiver) = oneshot::channel();
+        actix_web::rt::spawn(async move {
+            let result = self.write(&listing, format, writer).await;
+            if let Err(e) = &result {
+                tracing::error!("Failed to write archive of {}: {e}", listing.name);
+            }
+            let _ = result_sender.send(result);
+        });
+        futures::stream::unfold(
+            (reader, Some(result_receiver)),
+            |(mut reader, result_receiver)| async move {
+                let mut chunk = vec![0; READ_CHUNK_SIZE];
+                match reader.read(&mut chunk).await {
+                    // The writer is dropped once writing is done, check whether it succeeded.
+                    Ok(0) => {
+                        let result = match result_receiver?.await {
+                            Ok(el) => el,
+                            Err(_) => Err(anyhow!("Archive writer is cancelled.")),
+                        };
+                        match result {
+                            Ok(()) => None,
+                            Err(e) => {
+                                Some((Err(std::io::Error::other(e.to_string())), (reader, None)))
+                            }
+                        }
+                    }
+                    Ok(n) => {
+                        chunk.truncate(n);
+                        Some((Ok(Bytes::from(chunk)), (reader, result_receiver)))
+                    }
+                    Err(e) => Some((Err(e), (reader, None))),
+                }
+            },
+        )
+    }
+
+    pub async fn write<W>(
+        &self,
+        listing: &ArchiveListing,
+        format: ArchiveFormat,
+        writer: W,
+    ) -> anyhow::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        match format {
+            ArchiveFormat::Zip => self.write_zip(listing, writer).await,
+            ArchiveFormat::Tar => self.write_tar(listing, writer).await,
+        }
+    }
+
+    async fn write_zip<W>(&self, listing: &ArchiveListing, writer: W) -> anyhow::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let mut zip = ZipFileWriter::with_tokio(writer);
+        let name = format!("{}/", listing.name);
+        zip.write_entry_whole(ZipEntryBuilder::new(name.into(), Compression::Stored), &[])
+            .await?;
+        for entry in listing.entries.iter() {
+            match entry.meta_id {
+                Some(meta_id) => {
+                    let size = self.download_service.get_file_size(meta_id).await?;
+                    let builder =
+                        ZipEntryBuilder::new(entry.path.to_owned().into(), Compression::Deflate);
+                    let mut entry_writer = zip.write_entry_stream(builder).await?;
+                    let mut chunks = pin!(self.read_chunks(meta_id, size));
+                    while let Some(chunk) = chunks.try_next().await? {
+                        futures::AsyncWriteExt::write_all(&mut entry_writer, &chunk).await?;
+                    }
+                    entry_writer.close().await?;
+                }
+                None => {
+                    let builder = ZipEntryBuilder::new(
+                        format!("{}/", entry.path).into(),
+                        Compression::Stored,
+                    );
+                    zip.write_entry_whole(builder, &[]).await?;
+                }
+            }
+        }
+        zip.close().await?;
+        Ok(())
+    }
+
+    async fn write_tar<W>(&self, listing: &ArchiveListing, writer: W) -> anyhow::Result<()>
+    where
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let mut tar = Builder::new(writer);
+        append_tar_dir(&mut tar, &listing.name).await?;
+        for entry in listing.entries.iter() {
+            match entry.meta_id {
+                Some(meta_id) => {
+                    let size = self.download_service.get_file_size(meta_id).await?;
+                    // Chunks are piped into the tar entry while it is appended.
+                    let (mut pipe_writer, pipe_reader) = tokio::io::duplex(PIPE_BUFFER_SIZE);
+                    let pipe = async {
+                        let mut chunks = pin!(self.read_chunks(meta_id, size));
+                        while let Some(chunk) = chunks.try_next().await? {
+                            pipe_writer.write_all(&chunk).await?;
+                        }
+                        drop(pipe_writer);
+                        anyhow::Ok(())
+                    };
+                    let append = append_tar_file(&mut tar, &entry.path, size, pipe_reader);
+                    tokio::try_join!(pipe, append)?;
+                }
+                None => append_tar_dir(&mut tar, &entry.path).await?,
+            }
+        }
+        tar.into_inner().await?;
+        Ok(())
+    }
+
+    /// Read the file of the size by ranges.
+    fn read_chunks(
+        &self,
+        meta_id: Uuid,
+        size: u64,
+    ) -> impl Stream<Item = anyhow::Result<Vec<u8>>> + '_ {
+        futures::stream::try_unfold(0, move |start| async move {
+            if start >= size {
+                return Ok(None);
+            }
+            let end = (start + FILE_CHUNK_SIZE).min(size);
+            let chunk = self
+                .download_service
+                .rangely_get_file(meta_id, &[start..end])
+                .await?
+                .pop()
+                .ok_or(anyhow!(
+                    "No content of file: {meta_id} in range {start}..{end}"
+                ))?;
+            if chunk.len() as u64 != end - start {
+                bail!("File: {meta_id} is changed while archiving.");
+            }
+            Ok(Some((chunk, end)))
+        })
+    }
+}
+
+/// Append a file with its size and content.
+async fn append_tar_file<W, R>(
+    tar: &mut Builder<W>,
+    path: &str,
+    size: u64,
+    content: R,
+) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin + Send + 'static,
+    R: AsyncRead + Unpin + Send,
+{
+    let mut header = Header::new_gnu();
+    header.set_mtime(chrono::Utc::now().timestamp() as u64);
+    header.set_entry_type(EntryType::Regular);
+    header.set_mode(0o644);
+    header.set_size(size);
+    tar.append_data(&mut header, path, content).await?;
+    Ok(())
+}
+
+async fn append_tar_dir<W>(tar: &mut Builder<W>, path: &str) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let mut header = Header::new_gnu();
+    header.set_mtime(chrono::Utc::now().timestamp() as u64);
+    header.set_entry_type(EntryType::Directory);
+    header.set_mode(0o755);
+    header.set_size(0);
+    tar.append_data(&mut header, path, tokio::io::empty()).await?;
+    Ok(())
+}
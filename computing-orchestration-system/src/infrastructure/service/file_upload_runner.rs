@@ -1,12 +1,15 @@
 use alice_architecture::message_queue::producer::MessageQueueProducerTemplate;
 use anyhow::{anyhow, bail};
-use domain_workflow::model::vo::msg::{ChangeMsg, Info, TaskChangeInfo, TaskStatusChange};
+use domain_workflow::{
+    model::vo::msg::{ChangeMsg, Info, TaskChangeInfo, TaskStatusChange},
+    repository::{NodeInstanceRepo, TaskRepo, WorkflowInstanceRepo},
+};
 use std::sync::Arc;
 use uuid::Uuid;
 
 use domain_storage::{
-    command::{CacheOperateCommand::*, CacheReadCommand::*, CreateNetDiskFileCommand},
-    model::vo::{MoveDestination, RecordFileMeta, RecordFileStorage},
+    command::CreateNetDiskFileCommand,
+    model::vo::{MoveDestination, PlacementHint, RecordFileMeta, RecordFileStorage},
     service::*,
 };
 use typed_builder::TypedBuilder;
@@ -14,17 +17,51 @@ use typed_builder::TypedBuilder;
 #[derive(TypedBuilder, Clone)]
 pub struct FileUploadRunner {
     upload_service: Arc<dyn StorageServerUploadDispatcherService>,
+    direct_upload_service: Arc<dyn DirectUploadService>,
     cache_service: Arc<dyn CacheService>,
     meta_storage_service: Arc<dyn MetaStorageService>,
     net_disk_service: Arc<dyn NetDiskService>,
     file_move_service: Arc<dyn FileMoveService>,
     multipart_service: Arc<dyn MultipartService>,
+    task_repo: Arc<dyn TaskRepo>,
+    node_repo: Arc<dyn NodeInstanceRepo>,
+    flow_repo: Arc<dyn WorkflowInstanceRepo>,
     status_mq_producer: Arc<dyn MessageQueueProducerTemplate<ChangeMsg>>,
     status_mq_topic: String,
 }
 
 impl FileUploadRunner {
-    pub async fn upload_file(&self, move_id: Uuid, task_id: Option<Uuid>) -> anyhow::Result<()> {
+    /// Placement hint of the file, files uploaded by a task are consumed on its node's queue.
+    async fn placement_hint(
+        &self,
+        user_id: Uuid,
+        task_id: Option<Uuid>,
+    ) -> anyhow::Result<PlacementHint> {
+        let task_id = match task_id {
+            Some(el) => el,
+            None => {
+                return Ok(PlacementHint {
+                    user_id: Some(user_id),
+                    ..Default::default()
+                })
+            }
+        };
+        let node_id = self.task_repo.get_by_id(task_id).await?.node_instance_id;
+        let node = self.node_repo.get_by_id(node_id).await?;
+        let flow = self.flow_repo.get_by_node_id(node_id).await?;
+        Ok(PlacementHint {
+            user_id: Some(flow.user_id),
+            project_id: flow.project_id,
+            queue_id: node.queue_id,
+        })
+    }
+
+    pub async fn upload_file(
+        &self,
+        move_id: Uuid,
+        user_id: Uuid,
+        task_id: Option<Uuid>,
+    ) -> anyhow::Result<()> {
         let mut move_info = self
             .file_move_service
             .get_move_info(move_id)
@@ -44,8 +81,24 @@ impl FileUploadRunner {
                 _ => bail!("Unreachable destination when run file upload."),
             },
         );
-        let content = self.cache_service.read(ReadNormal { meta_id }).await?;
-        let server_url = match self.upload_service.upload(meta_id, &content).await {
+        let multipart = self.multipart_service.info(meta_id).await?;
+        let uploaded = match multipart.direct_upload {
+            // Parts are on the storage server already, only assemble them.
+            Some(_) => {
+                self.direct_upload_service.complete(meta_id).await.map_err(anyhow::Error::from)
+            }
+            None => {
+                // Parts of the file are streamed to storage servers one by one.
+                let parts = CachedParts {
+                    meta_id,
+                    part_count: multipart.part_count,
+                    cache_service: self.cache_service.clone(),
+                };
+                let hint = self.placement_hint(user_id, task_id).await?;
+                self.upload_service.upload(&parts, size, &hint).await
+            }
+        };
+        let server_urls = match uploaded {
             Ok(el) => el,
             Err(e) => {
                 move_info.is_upload_failed = true;
@@ -57,8 +110,13 @@ impl FileUploadRunner {
             }
         };
 
-        let (storage_server_id, server_url) =
-            (server_url.storage_server_id, server_url.to_string());
+        let file_storage_infos = server_urls
+            .iter()
+            .map(|el| RecordFileStorage {
+                storage_server_id: el.storage_server_id,
+                server_url: el.to_string(),
+            })
+            .collect();
         self.meta_storage_service
             .record_meta_and_storage(
                 meta_id,
@@ -68,10 +126,7 @@ impl FileUploadRunner {
                     hash_algorithm,
                     size,
                 },
-                RecordFileStorage {
-                    storage_server_id,
-                    server_url,
-                },
+                file_storage_infos,
             )
             .await?;
         if let Some(el) = record_net_disk {
@@ -87,7 +142,6 @@ impl FileUploadRunner {
                 .await?;
         }
 
-        self.cache_service.operate(RemoveNormal { meta_id }).await?;
         self.multipart_service.remove(meta_id).await?;
         self.file_move_service.remove_all_with_meta_id(meta_id).await?;
         if let Some(id) = task_id {
@@ -0,0 +1,32 @@
+use std::{sync::Arc, time::Duration};
+
+use alice_architecture::{
+    background_service::BackgroundService, message_queue::producer::MessageQueueProducerTemplate,
+};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+/// Periodically ask the consumer of the topic to run a job, e.g. file gc or upload sweep.
+///
+/// Jobs run in the consumer, so that they get a scoped service provider.
+#[derive(TypedBuilder)]
+pub struct IntervalTrigger {
+    mq_producer: Arc<dyn MessageQueueProducerTemplate<Uuid>>,
+    topic: String,
+    interval: Duration,
+}
+
+#[async_trait]
+impl BackgroundService for IntervalTrigger {
+    async fn run(&self) {
+        let mut interval = tokio::time::interval(self.interval);
+        loop {
+            interval.tick().await;
+            let run_id = Uuid::new_v4();
+            if let Err(e) = self.mq_producer.send_object(&run_id, &self.topic).await {
+                tracing::error!("Failed to trigger {}: {e}", self.topic);
+            }
+        }
+    }
+}
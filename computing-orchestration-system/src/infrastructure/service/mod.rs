@@ -1,13 +1,17 @@
 //! External services
 
+pub mod archive_writer;
 pub mod file_upload_runner;
 pub mod inner_usecase_select_service;
-pub mod minio_server_broker;
+pub mod interval_trigger;
+pub mod opendal_server_broker;
 
 pub mod prelude {
     pub use super::{
+        archive_writer::ArchiveWriter,
         file_upload_runner::FileUploadRunner,
         inner_usecase_select_service::InnerUsecaseSelectService,
-        minio_server_broker::MinioServerBrokerService,
+        interval_trigger::IntervalTrigger,
+        opendal_server_broker::OpendalServerBrokerService,
     };
 }
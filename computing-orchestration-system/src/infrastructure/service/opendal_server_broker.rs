@@ -0,0 +1,593 @@
+use std::{ops::Range, str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, bail};
+use async_trait::async_trait;
+use aws_sdk_s3::{
+    config::{BehaviorVersion, Credentials, Region},
+    presigning::PresigningConfig,
+    types::{CompletedMultipartUpload, CompletedPart},
+    Client,
+};
+use chrono::Utc;
+use domain_storage::{
+    model::{
+        entity::{FileSystemOption, ObjectServerOption, OpenDalOption, StorageServer, StorageType},
+        vo::{DirectUpload, PresignedUpload, ServerUrl},
+    },
+    service::{CachedParts, MetaStorageService, StorageServerBrokerService},
+};
+use opendal::{
+    services::{Fs, S3},
+    Operator, Scheme, Writer,
+};
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+/// Size of each write to the server, S3 multipart upload requires parts except the last one to
+/// be at least 5 MiB.
+const WRITE_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Transport files with any storage server backend through opendal, direct uploads are made
+/// with the S3 client as opendal can't presign multipart uploads.
+#[derive(TypedBuilder)]
+pub struct OpendalServerBrokerService {
+    meta_storage_service: Arc<dyn MetaStorageService>,
+}
+
+#[async_trait]
+impl StorageServerBrokerService for OpendalServerBrokerService {
+    async fn upload(
+        &self,
+        storage_server: &StorageServer,
+        parts: &CachedParts,
+    ) -> anyhow::Result<ServerUrl> {
+        let meta_id = parts.meta_id;
+        let operator = create_operator(storage_server)?;
+        let bucket = match &storage_server.storage_type {
+            StorageType::ObjectStorage { options } => options.default_bucket.to_owned(),
+            StorageType::FileSystem { .. } | StorageType::OpenDal { .. } => String::new(),
+        };
+        let server_url = ServerUrl {
+            bucket,
+            storage_server_id: storage_server.id,
+            meta_id,
+        };
+        let mut writer = operator.writer(&meta_id.to_string()).await?;
+        if let Err(e) = write_parts(&mut writer, parts).await {
+            // Clean up the unfinished multipart upload.
+            if let Err(abort_error) = writer.abort().await {
+                tracing::error!("Failed to abort upload of file {meta_id}: {abort_error}");
+            }
+            return Err(e);
+        }
+        writer.close().await?;
+
+        Ok(server_url)
+    }
+
+    async fn rangely_get_file(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+        range: &[Range<u64>],
+    ) -> anyhow::Result<Vec<Vec<u8>>> {
+        let mut vec_rangely_file = vec![];
+        for range in range.iter() {
+            let content = self.get_content(storage_server, meta_id, Some(range)).await?;
+            vec_rangely_file.push(content)
+        }
+        Ok(vec_rangely_file)
+    }
+
+    async fn get_file_size(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+    ) -> anyhow::Result<u64> {
+        let operator = create_operator(storage_server)?;
+        let meta = operator.stat(&meta_id.to_string()).await?;
+
+        Ok(meta.content_length())
+    }
+
+    #[allow(warnings)]
+    async fn download(&self, storage_server: &StorageServer, meta_id: Uuid) -> anyhow::Result<()> {
+        unimplemented!()
+    }
+
+    async fn get_download_url(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+    ) -> anyhow::Result<String> {
+        let download_endpoint = storage_server.storage_type.download_endpoint().ok_or(anyhow!(
+            "Storage server {} has no download endpoint",
+            storage_server.id
+        ))?;
+        let server_url =
+            self.meta_storage_service.get_server_url(storage_server.id, meta_id).await?;
+        Ok(format!("{download_endpoint}/{server_url}"))
+    }
+
+    async fn get_bytes(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+    ) -> anyhow::Result<Vec<u8>> {
+        self.get_content(storage_server, meta_id, None).await
+    }
+
+    async fn get_text(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+    ) -> anyhow::Result<String> {
+        let bytes = self.get_content(storage_server, meta_id, None).await?;
+        Ok(String::from_utf8(bytes)?)
+    }
+
+    async fn delete(&self, storage_server: &StorageServer, meta_id: Uuid) -> anyhow::Result<()> {
+        // Deleting a missing file succeeds in opendal.
+        let operator = create_operator(storage_server)?;
+        operator.delete(&meta_id.to_string()).await?;
+        Ok(())
+    }
+
+    async fn create_direct_upload(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+        part_count: u64,
+        expires_in: Duration,
+    ) -> anyhow::Result<PresignedUpload> {
+        let options = object_server_option(storage_server)?;
+        let key = object_key(storage_server, meta_id);
+        let client = create_s3_client(options, &options.endpoint);
+        let output = client
+            .create_multipart_upload()
+            .bucket(&options.default_bucket)
+            .key(&key)
+            .send()
+            .await?;
+        let upload_id = output
+            .upload_id()
+            .ok_or(anyhow!("No upload id of file {meta_id} is returned"))?
+            .to_owned();
+
+        let part_urls =
+            presign_part_urls(options, &key, &upload_id, part_count, expires_in).await?;
+        Ok(PresignedUpload {
+            upload: DirectUpload {
+                storage_server_id: storage_server.id,
+                upload_id,
+            },
+            part_urls,
+            expires_at: Utc::now() + chrono::Duration::from_std(expires_in)?,
+        })
+    }
+
+    async fn complete_direct_upload(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+        upload: &DirectUpload,
+        part_count: u64,
+    ) -> anyhow::Result<ServerUrl> {
+        let options = object_server_option(storage_server)?;
+        let key = object_key(storage_server, meta_id);
+        let client = create_s3_client(options, &options.endpoint);
+
+        // Etags of uploaded parts are listed by the server, so clients needn't report them.
+        let mut etags = vec![None; part_count as usize];
+        let mut marker = None;
+        loop {
+            let output = client
+                .list_parts()
+                .bucket(&options.default_bucket)
+                .key(&key)
+                .upload_id(&upload.upload_id)
+                .set_part_number_marker(marker.take())
+                .send()
+                .await?;
+            for part in output.parts() {
+                if let (Some(number), Some(etag)) = (part.part_number(), part.e_tag()) {
+                    let nth = usize::try_from(number - 1).ok();
+                    if let Some(el) = nth.and_then(|nth| etags.get_mut(nth)) {
+                        *el = Some(etag.to_owned());
+                    }
+                }
+            }
+            if output.is_truncated() != Some(true) {
+                break;
+            }
+            marker = output.next_part_number_marker().map(str::to_owned);
+        }
+        let missing = etags
+            .iter()
+            .enumerate()
+            .filter(|(_, el)| el.is_none())
+            .map(|(nth, _)| nth)
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            bail!("Parts {missing:?} of file {meta_id} aren't uploaded");
+        }
+
+        let parts = etags
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(nth, etag)| {
+                CompletedPart::builder()
+                    .part_number(part_number(nth as u64))
+                    .e_tag(etag)
+                    .build()
+            })
+            .collect();
+        client
+            .complete_multipart_upload()
+            .bucket(&options.default_bucket)
+            .key(&key)
+            .upload_id(&upload.upload_id)
+            .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+            .send()
+            .await?;
+        Ok(ServerUrl {
+            bucket: options.default_bucket.to_owned(),
+            storage_server_id: storage_server.id,
+            meta_id,
+        })
+    }
+
+    async fn abort_direct_upload(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+        upload: &DirectUpload,
+    ) -> anyhow::Result<()> {
+        let options = object_server_option(storage_server)?;
+        let client = create_s3_client(options, &options.endpoint);
+        client
+            .abort_multipart_upload()
+            .bucket(&options.default_bucket)
+            .key(object_key(storage_server, meta_id))
+            .upload_id(&upload.upload_id)
+            .send()
+            .await?;
+        Ok(())
+    }
+}
+
+impl OpendalServerBrokerService {
+    async fn get_content(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+        range: Option<&Range<u64>>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let file_key = &meta_id.to_string();
+        let operator = create_operator(storage_server)?;
+        Ok(match range.cloned() {
+            Some(r) => operator.read_with(file_key).range(r).await?,
+            None => operator.read(file_key).await?,
+        })
+    }
+}
+
+/// Stream parts to the writer, only one chunk is held in memory at a time.
+async fn write_parts(writer: &mut Writer, parts: &CachedParts) -> anyhow::Result<()> {
+    let mut chunk = Vec::with_capacity(WRITE_CHUNK_SIZE);
+    for nth in 0..parts.part_count {
+        chunk.extend(parts.read(nth).await?);
+        if chunk.len() >= WRITE_CHUNK_SIZE {
+            writer.write(std::mem::take(&mut chunk)).await?;
+        }
+    }
+    if !chunk.is_empty() {
+        writer.write(chunk).await?;
+    }
+    Ok(())
+}
+
+/// Create the operator of the storage server, files are stored under `storage-{id}` of the
+/// server's root.
+fn create_operator(storage_server: &StorageServer) -> anyhow::Result<Operator> {
+    let root = format!("storage-{}", storage_server.id);
+    match &storage_server.storage_type {
+        StorageType::ObjectStorage { options } => create_s3_operator(&root, options),
+        StorageType::FileSystem { options } => create_fs_operator(&root, options),
+        StorageType::OpenDal { options } => create_opendal_operator(&root, options),
+    }
+}
+
+fn create_s3_operator(root: &str, options: &ObjectServerOption) -> anyhow::Result<Operator> {
+    let endpoint = &options.endpoint;
+    let access_key_id = &options.access_key_id;
+    let secret_access_key = &options.secret_access_key;
+    let region = &options.region;
+    let bucket = &options.default_bucket;
+    let mut builder = S3::default();
+    builder
+        .endpoint(endpoint)
+        .root(root)
+        .bucket(bucket)
+        .region(region)
+        .access_key_id(access_key_id)
+        .secret_access_key(secret_access_key)
+        .allow_anonymous();
+    Ok(Operator::new(builder)?.finish())
+}
+
+fn object_server_option(storage_server: &StorageServer) -> anyhow::Result<&ObjectServerOption> {
+    match &storage_server.storage_type {
+        StorageType::ObjectStorage { options } => Ok(options),
+        StorageType::FileSystem { .. } | StorageType::OpenDal { .. } => bail!(
+            "Storage server {} isn't an object storage",
+            storage_server.id
+        ),
+    }
+}
+
+/// Key of the file in the bucket, the same as the one opendal writes to.
+fn object_key(storage_server: &StorageServer, meta_id: Uuid) -> String {
+    format!("storage-{}/{meta_id}", storage_server.id)
+}
+
+/// S3 part numbers start from 1.
+fn part_number(nth: u64) -> i32 {
+    nth as i32 + 1
+}
+
+/// Presign urls to upload each part of the multipart upload, signed with the host clients
+/// upload to.
+async fn presign_part_urls(
+    options: &ObjectServerOption,
+    key: &str,
+    upload_id: &str,
+    part_count: u64,
+    expires_in: Duration,
+) -> anyhow::Result<Vec<String>> {
+    let upload_endpoint = options.upload_endpoint.as_deref().unwrap_or(&options.endpoint);
+    let presign_client = create_s3_client(options, upload_endpoint);
+    let presigning_config = PresigningConfig::expires_in(expires_in)?;
+    let mut part_urls = Vec::with_capacity(part_count as usize);
+    for nth in 0..part_count {
+        let request = presign_client
+            .upload_part()
+            .bucket(&options.default_bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .part_number(part_number(nth))
+            .presigned(presigning_config.clone())
+            .await?;
+        part_urls.push(request.uri().to_string());
+    }
+    Ok(part_urls)
+}
+
+fn create_s3_client(options: &ObjectServerOption, endpoint: &str) -> Client {
+    let credentials = Credentials::new(
+        &options.access_key_id,
+        &options.secret_access_key,
+        None,
+        None,
+        "storage-server",
+    );
+    let config = aws_sdk_s3::Config::builder()
+        .behavior_version(BehaviorVersion::latest())
+        .endpoint_url(endpoint)
+        .region(Region::new(options.region.to_owned()))
+        .credentials_provider(credentials)
+        .force_path_style(true)
+        .build();
+    Client::from_conf(config)
+}
+
+fn create_fs_operator(root: &str, options: &FileSystemOption) -> anyhow::Result<Operator> {
+    let mut builder = Fs::default();
+    builder.root(&join_root(&options.root, root));
+    Ok(Operator::new(builder)?.finish())
+}
+
+fn create_opendal_operator(root: &str, options: &OpenDalOption) -> anyhow::Result<Operator> {
+    let scheme = Scheme::from_str(&options.scheme)?;
+    let mut config = options.config.clone();
+    let root = match config.get("root") {
+        Some(base) => join_root(base, root),
+        None => root.to_owned(),
+    };
+    config.insert("root".to_string(), root);
+    Ok(Operator::via_map(scheme, config)?)
+}
+
+fn join_root(base: &str, root: &str) -> String {
+    format!("{}/{root}", base.trim_end_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use domain_storage::{
+        command::CacheOperateCommand,
+        model::vo::{HashAlgorithm, Part, RecordFileMeta, RecordFileStorage},
+        service::CacheService,
+    };
+    use service_storage::LocalCacheServiceImpl;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// Only the download url looks up metas, which is not tested.
+    struct NoMetaStorageService;
+
+    #[async_trait]
+    impl MetaStorageService for NoMetaStorageService {
+        async fn record_meta_and_storage(
+            &self,
+            _meta_id: Uuid,
+            _file_meta_info: RecordFileMeta,
+            _file_storage_infos: Vec<RecordFileStorage>,
+        ) -> anyhow::Result<()> {
+            unreachable!()
+        }
+
+        async fn satisfy_flash_upload(
+            &self,
+            _hash: &str,
+            _hash_algorithm: &HashAlgorithm,
+        ) -> anyhow::Result<Option<Uuid>> {
+            unreachable!()
+        }
+
+        async fn get_server_url(
+            &self,
+            _storage_server_id: Uuid,
+            _meta_id: Uuid,
+        ) -> anyhow::Result<String> {
+            unreachable!()
+        }
+
+        async fn get_storage_server_ids(&self, _meta_id: Uuid) -> anyhow::Result<Vec<Uuid>> {
+            unreachable!()
+        }
+    }
+
+    struct Fixture {
+        dir: TempDir,
+        broker: OpendalServerBrokerService,
+        server: StorageServer,
+        cache_service: Arc<LocalCacheServiceImpl>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let server = StorageServer {
+                id: Uuid::new_v4(),
+                name: "fs".to_string(),
+                capacity: u64::MAX,
+                storage_type: StorageType::FileSystem {
+                    options: FileSystemOption {
+                        root: dir.path().join("root").to_string_lossy().into_owned(),
+                        download_endpoint: None,
+                    },
+                },
+            };
+            Self {
+                broker: OpendalServerBrokerService::builder()
+                    .meta_storage_service(Arc::new(NoMetaStorageService))
+                    .build(),
+                server,
+                cache_service: Arc::new(
+                    LocalCacheServiceImpl::builder().base(dir.path().join("cache")).build(),
+                ),
+                dir,
+            }
+        }
+
+        /// Upload the parts as a file, returns its meta id.
+        async fn put(&self, parts: &[&[u8]]) -> Uuid {
+            let meta_id = Uuid::new_v4();
+            for (nth, content) in parts.iter().enumerate() {
+                self.cache_service
+                    .operate(CacheOperateCommand::WritePart(Part {
+                        meta_id,
+                        content: content.to_vec(),
+                        nth: nth as u64,
+                    }))
+                    .await
+                    .unwrap();
+            }
+            let parts = CachedParts {
+                meta_id,
+                part_count: parts.len() as u64,
+                cache_service: self.cache_service.clone(),
+            };
+            let server_url = self.broker.upload(&self.server, &parts).await.unwrap();
+            assert_eq!(
+                server_url.to_string(),
+                format!("storage-{}/{meta_id}", self.server.id)
+            );
+            meta_id
+        }
+    }
+
+    #[tokio::test]
+    async fn test_presign_part_urls() {
+        let options = ObjectServerOption {
+            endpoint: "http://minio:9000".to_string(),
+            upload_endpoint: Some("https://upload.example.com".to_string()),
+            default_bucket: "files".to_string(),
+            access_key_id: "access".to_string(),
+            secret_access_key: "secret".to_string(),
+            region: "us-east-1".to_string(),
+            ..Default::default()
+        };
+        let urls = presign_part_urls(
+            &options,
+            "storage-1/2",
+            "upload",
+            3,
+            Duration::from_secs(60),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(urls.len(), 3);
+        for (nth, url) in urls.iter().enumerate() {
+            // Signed for the upload endpoint, not the one used by this system.
+            assert!(url.starts_with("https://upload.example.com/files/storage-1/2?"));
+            assert!(url.contains(&format!("partNumber={}", nth + 1)));
+            assert!(url.contains("uploadId=upload"));
+            assert!(url.contains("X-Amz-Expires=60"));
+            assert!(url.contains("X-Amz-Signature="));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fs_put_get() {
+        let fixture = Fixture::new();
+        let meta_id = fixture.put(&[b"hello ", b"file ", b"system"]).await;
+
+        let path = fixture.dir.path().join(format!("root/storage-{}/{meta_id}", fixture.server.id));
+        assert_eq!(std::fs::read(path).unwrap(), b"hello file system");
+        let broker = &fixture.broker;
+        let server = &fixture.server;
+        assert_eq!(
+            broker.get_bytes(server, meta_id).await.unwrap(),
+            b"hello file system"
+        );
+        assert_eq!(
+            broker.get_text(server, meta_id).await.unwrap(),
+            "hello file system"
+        );
+        assert_eq!(broker.get_file_size(server, meta_id).await.unwrap(), 17);
+    }
+
+    #[tokio::test]
+    async fn test_fs_range() {
+        let fixture = Fixture::new();
+        let meta_id = fixture.put(&[b"0123456789"]).await;
+
+        let ranges = fixture
+            .broker
+            .rangely_get_file(&fixture.server, meta_id, &[0..3, 5..10, 9..10])
+            .await
+            .unwrap();
+        assert_eq!(
+            ranges,
+            vec![b"012".to_vec(), b"56789".to_vec(), b"9".to_vec()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fs_delete() {
+        let fixture = Fixture::new();
+        let meta_id = fixture.put(&[b"to be deleted"]).await;
+        let broker = &fixture.broker;
+        let server = &fixture.server;
+
+        broker.delete(server, meta_id).await.unwrap();
+        assert!(broker.get_bytes(server, meta_id).await.is_err());
+        // Deleting a missing file succeeds.
+        broker.delete(server, meta_id).await.unwrap();
+    }
+}
@@ -15,6 +15,7 @@ use alice_infrastructure::{
     ConsumerFn,
 };
 use infrastructure_command::WsServerOperateCommand;
+use sea_orm_migration::MigratorTrait;
 use uuid::Uuid;
 
 // domains
@@ -30,8 +31,12 @@ use domain_workflow::{
 // domain services
 use super::{
     config::*,
-    database::{graphql::content_repo::ContentRepository, OrmRepo, RedisClient, RedisRepo},
+    database::{
+        graphql::content_repo::ContentRepository, migration::Migrator, OrmRepo, RedisClient,
+        RedisRepo,
+    },
     internal_message_consumer,
+    repository::{CachedPackageRepository, PackageCache, PackageStoreRepository},
     service::prelude::*,
     websocket_message_consumer, WsManager, WsSessionOpener,
 };
@@ -120,7 +125,9 @@ build_container! {
 
     database: Arc<Database> {
         build async {
-            Arc::new(Database::new(&common_config.db.url).await)
+            let database = Database::new(&common_config.db.url).await;
+            Migrator::up(database.get_connection(), None).await?;
+            Arc::new(database)
         }
     }
 
@@ -135,35 +142,25 @@ build_container! {
         }
     }
 
-    package_repo: Arc<dyn PackageRepo> {
+    package_cache: Arc<PackageCache> {
         build {
             Arc::new(
-                ContentRepository::new(
-                    http_client.clone(),
-                    co_config.co_repo_domain.clone(),
-                )
+                PackageCache::builder()
+                    .dir(co_config.package_cache.dir.clone())
+                    .memory_capacity(co_config.package_cache.memory_capacity)
+                    .disk_capacity(co_config.package_cache.disk_capacity)
+                    .unpinned_ttl(std::time::Duration::from_secs(co_config.package_cache.unpinned_ttl_secs))
+                    .build()
             )
         }
     }
 
-    co_software_computing_usecase_service: Arc<dyn SoftwareComputingUsecaseInfoService> {
-        build {
-            Arc::new(SoftwareComputingUsecaseInfoServiceImpl::builder().package_repo(package_repo.clone()).build())
-        }
-    }
-
     validate_package_service: Arc<dyn ValidatePackageService> {
         build {
             Arc::new(ValidatePackageServiceImpl)
         }
     }
 
-    node_draft_service: Arc<dyn NodeDraftService> {
-        build {
-            Arc::new(NodeDraftServiceImpl::builder().package_repo(package_repo.clone()).build())
-        }
-    }
-
     kafka_mq_producer: Arc<KafkaMessageQueueProducer> {
         provide[
             Arc<dyn MessageQueueProducerTemplate<ViewRealtimeCommand>>,
@@ -214,7 +211,7 @@ build_container! {
     scoped storage_server_broker_service: Arc<dyn StorageServerBrokerService> {
         build {
             Arc::new(
-                MinioServerBrokerService::builder()
+                OpendalServerBrokerService::builder()
                     .meta_storage_service(meta_storage_service.clone())
                     .build()
             )
@@ -227,6 +224,38 @@ build_container! {
                 StorageServerResourceServiceImpl::builder()
                     .default_storage_server_id(self.co_config.default_storage_server_id)
                     .storage_server_repo(sea_orm_repository.clone())
+                    .storage_repo(sea_orm_repository.clone())
+                    .placement(self.co_config.storage_placement.clone())
+                    .build()
+            )
+        }
+    }
+
+    scoped file_gc_service: Arc<dyn FileGcService> {
+        build {
+            Arc::new(
+                FileGcServiceImpl::builder()
+                    .meta_repo(sea_orm_repository.clone())
+                    .storage_repo(sea_orm_repository.clone())
+                    .snapshot_repo(redis_repository.clone())
+                    .resources_service(storage_server_resource_service.clone())
+                    .storage_server_broker_service(storage_server_broker_service.clone())
+                    .grace_secs(self.co_config.file_gc.grace_secs)
+                    .batch_size(self.co_config.file_gc.batch_size)
+                    .build()
+            )
+        }
+    }
+
+    scoped quota_service: Arc<dyn QuotaService> {
+        build {
+            Arc::new(
+                QuotaServiceImpl::builder()
+                    .quota_repo(redis_repository.clone())
+                    .flow_repo(sea_orm_repository.clone())
+                    .backlog_mq_producer(self.internal_message_queue_producer.clone())
+                    .backlog_mq_topic(self.co_config.internal_topics.backlog.to_owned())
+                    .config(self.co_config.quotas.to_owned())
                     .build()
             )
         }
@@ -237,6 +266,31 @@ build_container! {
             Arc::new(
                 QueueResourceServiceImpl::builder()
                     .queue_resource_repo(sea_orm_repository.clone())
+                    .queue_cache_repo(redis_repository.clone())
+                    .backlog_repo(redis_repository.clone())
+                    .node_repo(sea_orm_repository.clone())
+                    .flow_repo(sea_orm_repository.clone())
+                    .quota_service(quota_service.clone())
+                    .status_mq_producer(self.internal_message_queue_producer.clone())
+                    .status_mq_topic(self.co_config.internal_topics.status.to_owned())
+                    .backlog_mq_producer(self.internal_message_queue_producer.clone())
+                    .backlog_mq_topic(self.co_config.internal_topics.backlog.to_owned())
+                    .default_scoring(self.co_config.queue_scoring.to_owned())
+                    .build()
+            )
+        }
+    }
+
+    scoped scheduler_backlog_service: Arc<dyn SchedulerBacklogService> {
+        build {
+            Arc::new(
+                SchedulerBacklogServiceImpl::builder()
+                    .backlog_repo(redis_repository.clone())
+                    .node_repo(sea_orm_repository.clone())
+                    .flow_repo(sea_orm_repository.clone())
+                    .task_repo(sea_orm_repository.clone())
+                    .queue_resource_service(queue_resource_service.clone())
+                    .quota_service(quota_service.clone())
                     .status_mq_producer(self.internal_message_queue_producer.clone())
                     .status_mq_topic(self.co_config.internal_topics.status.to_owned())
                     .build()
@@ -250,6 +304,7 @@ build_container! {
                 StorageServerUploadDispatcherServiceImpl::builder()
                     .resources_service(storage_server_resource_service.clone())
                     .storage_server_broker_service(storage_server_broker_service.clone())
+                    .replicas(self.co_config.storage_placement.replicas)
                     .build()
             )
         }
@@ -261,11 +316,53 @@ build_container! {
                 StorageServerDownloadDispatcherServiceImpl::builder()
                     .resources_service(storage_server_resource_service.clone())
                     .storage_server_broker_service(storage_server_broker_service.clone())
+                    .meta_storage_service(meta_storage_service.clone())
                     .build()
             )
         }
     }
 
+    scoped package_repo: Arc<dyn PackageRepo> {
+        build {
+            let package_repo: Arc<dyn PackageRepo> = match &self.co_config.package_registry {
+                PackageRegistryConfig::CoRepo => Arc::new(ContentRepository::new(
+                    self.http_client.clone(),
+                    self.co_config.co_repo_domain.clone(),
+                )),
+                PackageRegistryConfig::Store { dir } => Arc::new(
+                    PackageStoreRepository::builder()
+                        .dir(dir)
+                        .download_service(storage_server_download_dispatcher_service.clone())
+                        .meta_repo(sea_orm_repository.clone())
+                        .build(),
+                ),
+            };
+            let package_repo: Arc<dyn PackageRepo> = if self.co_config.package_cache.enabled {
+                Arc::new(
+                    CachedPackageRepository::builder()
+                        .inner(package_repo)
+                        .cache(self.package_cache.clone())
+                        .build(),
+                )
+            } else {
+                package_repo
+            };
+            package_repo
+        }
+    }
+
+    scoped co_software_computing_usecase_service: Arc<dyn SoftwareComputingUsecaseInfoService> {
+        build {
+            Arc::new(SoftwareComputingUsecaseInfoServiceImpl::builder().package_repo(package_repo.clone()).build())
+        }
+    }
+
+    scoped node_draft_service: Arc<dyn NodeDraftService> {
+        build {
+            Arc::new(NodeDraftServiceImpl::builder().package_repo(package_repo.clone()).build())
+        }
+    }
+
     scoped net_disk_service: Arc<dyn NetDiskService> {
         build {
             Arc::new(
@@ -278,6 +375,39 @@ build_container! {
         }
     }
 
+    scoped archive_writer: Arc<ArchiveWriter> {
+        build {
+            Arc::new(
+                ArchiveWriter::builder()
+                    .download_service(storage_server_download_dispatcher_service.clone())
+                    .build()
+            )
+        }
+    }
+
+    scoped archive_extract_service: Arc<dyn ArchiveExtractService> {
+        build {
+            Arc::new(
+                ArchiveExtractServiceImpl::builder()
+                    .net_disk_repo(sea_orm_repository.clone())
+                    .net_disk_service(net_disk_service.clone())
+                    .meta_storage_service(meta_storage_service.clone())
+                    .upload_service(storage_server_upload_dispatcher_service.clone())
+                    .download_service(storage_server_download_dispatcher_service.clone())
+                    .cache_service(self.cache_service.clone())
+                    .extract_sender_and_topic((
+                        self.internal_message_queue_producer.clone(),
+                        self.internal_topics.archive_extract.clone()
+                    ))
+                    .base(&self.common_config.host.upload_file_path)
+                    .max_total_size(self.co_config.archive_extract.max_total_size)
+                    .max_entry_count(self.co_config.archive_extract.max_entry_count)
+                    .user_id(user_id)
+                    .build()
+            )
+        }
+    }
+
     scoped multipart_service: Arc<dyn MultipartService> {
         build {
             Arc::new(
@@ -316,16 +446,54 @@ build_container! {
         }
     }
 
+    scoped direct_upload_service: Arc<dyn DirectUploadService> {
+        build {
+            Arc::new(
+                DirectUploadServiceImpl::builder()
+                    .multipart_service(multipart_service.clone())
+                    .file_move_service(file_move_service.clone())
+                    .resources_service(storage_server_resource_service.clone())
+                    .storage_server_broker_service(storage_server_broker_service.clone())
+                    .enabled(self.co_config.direct_upload.enabled)
+                    .url_expire_secs(self.co_config.direct_upload.url_expire_secs)
+                    .verify_hash(self.co_config.direct_upload.verify_hash)
+                    .replicas(self.co_config.storage_placement.replicas)
+                    .user_id(user_id)
+                    .build()
+            )
+        }
+    }
+
+    scoped upload_sweep_service: Arc<dyn UploadSweepService> {
+        build {
+            Arc::new(
+                UploadSweepServiceImpl::builder()
+                    .multipart_repo(redis_repository.clone())
+                    .move_registration_repo(redis_repository.clone())
+                    .cache_service(self.cache_service.clone())
+                    .direct_upload_service(direct_upload_service.clone())
+                    .status_mq_producer(self.internal_message_queue_producer.clone())
+                    .status_mq_topic(self.co_config.internal_topics.status.to_owned())
+                    .session_ttl_secs(self.co_config.upload_sweep.session_ttl_secs)
+                    .build()
+            )
+        }
+    }
+
     scoped file_upload_runner: Arc<FileUploadRunner> {
         build {
             Arc::new(
                 FileUploadRunner::builder()
                     .upload_service(storage_server_upload_dispatcher_service.clone())
+                    .direct_upload_service(direct_upload_service.clone())
                     .cache_service(self.cache_service.clone())
                     .meta_storage_service(meta_storage_service.clone())
                     .net_disk_service(net_disk_service.clone())
                     .file_move_service(file_move_service.clone())
                     .multipart_service(multipart_service.clone())
+                    .task_repo(sea_orm_repository.clone())
+                    .node_repo(sea_orm_repository.clone())
+                    .flow_repo(sea_orm_repository.clone())
                     .status_mq_producer(self.internal_message_queue_producer.clone())
                     .status_mq_topic(self.co_config.internal_topics.status.to_owned())
                     .build()
@@ -353,7 +521,7 @@ build_container! {
             let internal_message_queue_producer: Arc<InternalMessageQueueProducer> = sp.provide();
             Arc::new(
                 SoftwareComputingUsecaseServiceImpl::builder()
-                    .computing_usecase_repo(self.co_software_computing_usecase_service.clone())
+                    .computing_usecase_repo(co_software_computing_usecase_service.clone())
                     .text_storage_repository(redis_repository.clone())
                     .software_block_list_repository(sea_orm_repository.clone())
                     .installed_software_repository(sea_orm_repository.clone())
@@ -390,11 +558,26 @@ build_container! {
         }
     }
 
+    scoped condition_usecase_service: Arc<ConditionUsecaseServiceImpl> {
+        build {
+            Arc::new(
+                ConditionUsecaseServiceImpl::builder()
+                    .node_repo(sea_orm_repository.clone())
+                    .flow_repo(sea_orm_repository.clone())
+                    .text_storage_repository(redis_repository.clone())
+                    .status_mq_producer(self.internal_message_queue_producer.clone())
+                    .status_mq_topic(self.co_config.internal_topics.status.to_owned())
+                    .build()
+            )
+        }
+    }
+
     scoped usecase_select_service: Arc<dyn UsecaseSelectService> {
         build {
             let mut map: HashMap<NodeInstanceKind, Arc<dyn UsecaseParseService>> = HashMap::new();
             map.insert(self.no_action_usecase_service.get_service_type(), self.no_action_usecase_service.clone());
             map.insert(software_computing_usecase_service.get_service_type(), software_computing_usecase_service.clone());
+            map.insert(condition_usecase_service.get_service_type(), condition_usecase_service.clone());
             // map.insert(script_usecase_service.get_service_type(), script_usecase_service.clone());
             Arc::new(InnerUsecaseSelectService::builder().usecases(map).build())
         }
@@ -408,27 +591,13 @@ build_container! {
                     .status_mq_producer(internal_message_queue_producer)
                     .status_mq_topic(self.co_config.internal_topics.status.to_owned())
                     .queue_resource_service(queue_resource_service.clone())
+                    .task_repo(sea_orm_repository.clone())
                     .queue_id(scoped_config.device_info.map(|i|i.id))
                     .build()
             )
         }
     }
 
-    scoped workflow_service: Arc<dyn ControlService> {
-        build{
-            Arc::new(
-                ControlServiceImpl::builder()
-                    .draft_repo(sea_orm_repository.clone())
-                    .instance_repo(sea_orm_repository.clone())
-                    .node_repo(sea_orm_repository.clone())
-                    .file_meta_repo(sea_orm_repository.clone())
-                    .status_mq_producer(self.internal_message_queue_producer.clone())
-                    .status_mq_topic(self.co_config.internal_topics.status.to_owned())
-                    .build()
-            )
-        }
-    }
-
     scoped text_storage_service: Arc<dyn TextStorageService> {
         build{
             Arc::new(
@@ -471,6 +640,10 @@ build_container! {
             Arc::new(
                 TaskScheduleServiceImpl::builder()
                     .task_repo(sea_orm_repository.clone())
+                    .node_repo(sea_orm_repository.clone())
+                    .flow_repo(sea_orm_repository.clone())
+                    .queue_resource_service(queue_resource_service.clone())
+                    .quota_service(quota_service.clone())
                     .mq_producer_task(self.kafka_mq_producer.clone())
                     .status_mq_producer(self.internal_message_queue_producer.clone())
                     .status_mq_topic(self.co_config.internal_topics.status.to_owned())
@@ -493,6 +666,25 @@ build_container! {
         }
     }
 
+    scoped workflow_service: Arc<dyn ControlService> {
+        build{
+            Arc::new(
+                ControlServiceImpl::builder()
+                    .draft_repo(sea_orm_repository.clone())
+                    .instance_repo(sea_orm_repository.clone())
+                    .node_repo(sea_orm_repository.clone())
+                    .task_repo(sea_orm_repository.clone())
+                    .file_meta_repo(sea_orm_repository.clone())
+                    .batch_service(batch_service.clone())
+                    .quota_service(quota_service.clone())
+                    .computing_usecase_repo(co_software_computing_usecase_service.clone())
+                    .status_mq_producer(self.internal_message_queue_producer.clone())
+                    .status_mq_topic(self.co_config.internal_topics.status.to_owned())
+                    .build()
+            )
+        }
+    }
+
     scoped node_scheduler: Arc<NodeScheduleServiceImpl> {
         build {
             Arc::new(
@@ -504,6 +696,8 @@ build_container! {
                     .status_mq_topic(self.co_config.internal_topics.status.to_owned())
                     .usecase_select_service(usecase_select_service.clone())
                     .batch_service(batch_service.clone())
+                    .quota_service(quota_service.clone())
+                    .queue_resource_service(queue_resource_service.clone())
                     .bill_mq_producer(self.kafka_mq_producer.clone())
                     .bill_mq_topic(self.co_config.bill_topic.to_owned())
                     .build()
@@ -533,6 +727,12 @@ build_container! {
         let ws_server_topic = internal_topics.web_socket.to_owned();
         let file_upload_topic = internal_topics.file_upload.to_owned();
         let status_topic = internal_topics.status.to_owned();
+        let backlog_topic = internal_topics.backlog.to_owned();
+        let backlog_topic_for_drain = backlog_topic.clone();
+        let file_gc_topic = internal_topics.file_gc.to_owned();
+        let upload_sweep_topic = internal_topics.upload_sweep.to_owned();
+        let task_retry_topic = internal_topics.task_retry.to_owned();
+        let archive_extract_topic = internal_topics.archive_extract.to_owned();
 
         let realtime_ws_topic = internal_topics.ws_messages.realtime.to_owned();
 
@@ -540,6 +740,11 @@ build_container! {
         fn_mapper.insert(file_upload_topic, internal_message_consumer::file_upload_runner_consumer);
         fn_mapper.insert(ws_server_topic, internal_message_consumer::ws_server_operator);
         fn_mapper.insert(status_topic, internal_message_consumer::status_consumer);
+        fn_mapper.insert(backlog_topic, internal_message_consumer::backlog_consumer);
+        fn_mapper.insert(file_gc_topic.clone(), internal_message_consumer::file_gc_consumer);
+        fn_mapper.insert(upload_sweep_topic.clone(), internal_message_consumer::upload_sweep_consumer);
+        fn_mapper.insert(task_retry_topic.clone(), internal_message_consumer::task_retry_consumer);
+        fn_mapper.insert(archive_extract_topic, internal_message_consumer::archive_extract_consumer);
 
         // Websocket message consumer.
         fn_mapper.insert(realtime_ws_topic, websocket_message_consumer::ws_realtime);
@@ -547,5 +752,36 @@ build_container! {
         let internal_message_queue_producer: Arc<InternalMessageQueueProducer> = arc_sp.provide();
         let mq = Arc::new(InternalMessageQueueConsumer::new(internal_message_queue_producer.get_receiver(), arc_sp, fn_mapper));
         sp.background_services.push(mq);
+
+        if config.file_gc.enabled {
+            let file_gc_trigger = IntervalTrigger::builder()
+                .mq_producer(internal_message_queue_producer.clone())
+                .topic(file_gc_topic)
+                .interval(std::time::Duration::from_secs(config.file_gc.interval_secs))
+                .build();
+            sp.background_services.push(Arc::new(file_gc_trigger));
+        }
+        let task_retry_trigger = IntervalTrigger::builder()
+            .mq_producer(internal_message_queue_producer.clone())
+            .topic(task_retry_topic)
+            .interval(std::time::Duration::from_secs(config.task_retry.interval_secs))
+            .build();
+        sp.background_services.push(Arc::new(task_retry_trigger));
+        // A drain is missed when its notification fails, so the backlog is also drained
+        // periodically.
+        let backlog_drain_trigger = IntervalTrigger::builder()
+            .mq_producer(internal_message_queue_producer.clone())
+            .topic(backlog_topic_for_drain)
+            .interval(std::time::Duration::from_secs(config.backlog_drain.interval_secs))
+            .build();
+        sp.background_services.push(Arc::new(backlog_drain_trigger));
+        if config.upload_sweep.enabled {
+            let upload_sweep_trigger = IntervalTrigger::builder()
+                .mq_producer(internal_message_queue_producer)
+                .topic(upload_sweep_topic)
+                .interval(std::time::Duration::from_secs(config.upload_sweep.interval_secs))
+                .build();
+            sp.background_services.push(Arc::new(upload_sweep_trigger));
+        }
     }
 }
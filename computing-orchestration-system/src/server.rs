@@ -112,6 +112,7 @@ pub async fn initialize_web_host(sp: Arc<ServiceProvider>) {
                     .service(api::workflow_engine::pause_workflow)
                     .service(api::workflow_engine::continue_workflow)
                     .service(api::workflow_engine::terminate_workflow)
+                    .service(api::workflow_engine::retry_workflow)
                     .service(api::workflow_engine::receive_task_status)
                     .service(api::workflow_engine::get_node_cmd)
                     .service(api::text_storage::upload)
@@ -133,6 +134,7 @@ pub async fn initialize_web_host(sp: Arc<ServiceProvider>) {
                         web::post().to(api::file_storage::prepare_partial_upload),
                     )
                     .service(api::file_storage::partial_upload)
+                    .service(api::file_storage::complete_direct_upload)
                     .service(api::file_storage::get_partial_upload_info)
                     .service(api::file_storage::get_file_download_url)
                     .service(api::file_storage::get_file_download_urls)
@@ -147,6 +149,16 @@ pub async fn initialize_web_host(sp: Arc<ServiceProvider>) {
                     .service(api::snapshot::get_snapshots_infos)
                     .service(api::snapshot::get_snapshot)
                     .service(api::snapshot::del_snapshot)
+                    .service(api::net_disk::list_net_disk)
+                    .service(api::net_disk::rename_net_disk_file)
+                    .service(api::net_disk::move_net_disk_file)
+                    .service(api::net_disk::copy_net_disk_file)
+                    .service(api::net_disk::delete_net_disk_file)
+                    .service(api::net_disk::get_net_disk_usage)
+                    .service(api::net_disk::download_net_disk_archive)
+                    .service(api::net_disk::download_flow_instance_archive)
+                    .service(api::net_disk::download_node_instance_archive)
+                    .service(api::net_disk::extract_net_disk_archive)
                     .service(api::agent::register)
                     .service(api::agent::update_used_resource)
                     .service(api::agent::get_queue_cache_info),
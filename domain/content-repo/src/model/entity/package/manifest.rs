@@ -1,4 +1,6 @@
+use anyhow::Context;
 use schemars::JsonSchema;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 
 /// 标准描述清单
@@ -20,6 +22,47 @@ pub struct Manifest {
     pub git: Option<String>,
     /// 主页
     pub home_page: Option<String>,
+    /// 依赖的其他软件包，例如 MPI 或共享库
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<PackageDependency>,
+}
+
+/// 软件包依赖声明
+#[derive(JsonSchema, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageDependency {
+    /// 依赖的系统名称
+    pub system_name: String,
+    /// 依赖的版本范围，例如 ">=4.0, <5"、"^1.2"
+    pub version_range: String,
+}
+
+impl PackageDependency {
+    /// 判断版本是否满足依赖的版本范围
+    pub fn matches(&self, version: &str) -> anyhow::Result<bool> {
+        let range = VersionReq::parse(&self.version_range).with_context(|| {
+            format!(
+                "Invalid version range: {} of dependency: {}",
+                self.version_range, self.system_name
+            )
+        })?;
+        Ok(parse_version(version).map(|el| range.matches(&el)).unwrap_or_default())
+    }
+}
+
+/// 宽松地解析版本号，允许 v 前缀并补齐缺少的次版本号与修订号，例如 "v4.1" 解析为 4.1.0
+pub fn parse_version(version: &str) -> Option<Version> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let (core, rest) = match version.find(['-', '+']) {
+        Some(i) => version.split_at(i),
+        None => (version, ""),
+    };
+    let mut numbers = core.split('.').collect::<Vec<_>>();
+    if numbers.is_empty() || numbers.len() > 3 {
+        return None;
+    }
+    numbers.resize(3, "0");
+    Version::parse(&format!("{}{rest}", numbers.join("."))).ok()
 }
 
 /// 内容能力种类
@@ -47,3 +90,26 @@ pub enum SoftwareComputingRepo {
         dependency_version_range: String,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dependency_matches() {
+        let dependency = PackageDependency {
+            system_name: "openmpi".to_owned(),
+            version_range: ">=4.0, <5".to_owned(),
+        };
+        assert!(dependency.matches("4.1.5").unwrap());
+        assert!(dependency.matches("v4.1").unwrap());
+        assert!(!dependency.matches("5.0.0").unwrap());
+        assert!(!dependency.matches("latest").unwrap());
+
+        let dependency = PackageDependency {
+            version_range: "not a range".to_owned(),
+            ..dependency
+        };
+        assert!(dependency.matches("4.1.5").is_err());
+    }
+}
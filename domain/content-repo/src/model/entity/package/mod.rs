@@ -1,6 +1,8 @@
 mod manifest;
 
-pub use self::manifest::{AbilityKind, Manifest, SoftwareComputingRepo};
+pub use self::manifest::{
+    parse_version, AbilityKind, Manifest, PackageDependency, SoftwareComputingRepo,
+};
 
 use std::io::{Cursor, Read};
 
@@ -23,10 +25,12 @@ use crate::model::vo::abilities::software_computing::{
 };
 
 /// 包内容对象
-#[derive(Debug, AggregateRoot)]
+#[derive(Debug, Clone, AggregateRoot)]
 pub struct Package {
     /// 版本 id
     pub version_id: Uuid,
+    /// tar 包内容的 blake3 哈希，大写十六进制
+    pub hash: String,
     /// 清单对象
     pub manifest: Manifest,
     /// 数据对象
@@ -34,14 +38,14 @@ pub struct Package {
 }
 
 /// 包中的数据对象
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Data {
     /// 软件用例计算类型
     SoftwareUsecaseComputing(SoftwareUsecaseComputingData),
 }
 
 /// 软件用例计算的数据对象
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum SoftwareUsecaseComputingData {
     /// 软件包
     Software(SoftwareData),
@@ -76,9 +80,9 @@ pub struct SoftwareData {
 }
 
 /// tar 包根路径祖先数量
-const PACKAGE_ANCESTORS_COUNT: usize = 3;
+pub const PACKAGE_ANCESTORS_COUNT: usize = 3;
 /// 模板文件夹名称
-const TEMPLATE_FILE_FOLDER_NAME: &str = "templates";
+pub const TEMPLATE_FILE_FOLDER_NAME: &str = "templates";
 
 impl Package {
     /// 解析获得要解析到草稿节点时软件包中的数据对象
@@ -156,6 +160,7 @@ impl Package {
 
         Ok(Self {
             version_id: id,
+            hash: blake3::hash(bytes).to_string().to_uppercase(),
             manifest,
             data,
         })
@@ -201,7 +206,7 @@ impl Package {
                 }
             }
         }
-        let spec = spec.unwrap();
+        let spec = spec.context("software package has no SoftwareSpec!")?;
 
         Ok(Data::SoftwareUsecaseComputing(
             SoftwareUsecaseComputingData::Software(SoftwareData {
@@ -267,17 +272,20 @@ impl Package {
                 }
             }
         }
-        let spec = spec.unwrap();
+        let spec = spec.context("usecase package has no UsecaseSpec!")?;
 
-        template_file_infos.iter_mut().for_each(|el| {
+        for el in template_file_infos.iter_mut() {
             el.descriptor = spec
                 .template_files
                 .iter()
                 .find(|el2| el2.path.eq(&el.file_name))
-                .unwrap()
+                .context(format!(
+                    "template file: {} is not in UsecaseSpec!",
+                    el.file_name
+                ))?
                 .descriptor
-                .to_owned()
-        });
+                .to_owned();
+        }
 
         Ok(Data::SoftwareUsecaseComputing(
             SoftwareUsecaseComputingData::Usecase(Box::new(UsecaseData {
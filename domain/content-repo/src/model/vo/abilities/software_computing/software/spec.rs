@@ -17,4 +17,37 @@ pub enum SoftwareSpec {
         /// tag
         tag: String,
     },
+    Apptainer {
+        /// apptainer 镜像名
+        image: String,
+        /// tag
+        tag: String,
+    },
+    /// OCI 镜像，由 docker 或 podman 运行
+    Oci {
+        /// 镜像名，可包含仓库地址
+        image: String,
+        /// 镜像摘要，如 `sha256:...`，保证镜像内容不变
+        digest: String,
+    },
+    /// conda 环境，可由 conda 或 mamba 创建
+    Conda {
+        /// 环境名
+        name: String,
+        /// 安装的包，如 `numpy=1.26`
+        packages: Vec<String>,
+        /// 频道列表
+        #[serde(default)]
+        channels: Vec<String>,
+    },
+    /// EasyBuild 安装、Lmod 加载的模块
+    Module {
+        /// 模块名
+        name: String,
+        /// 模块版本
+        version: String,
+        /// 模块未安装时用于安装的 easyconfig，默认由名称和版本查找
+        #[serde(default)]
+        easyconfig: Option<String>,
+    },
 }
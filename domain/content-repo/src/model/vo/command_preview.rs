@@ -144,6 +144,38 @@ pub enum FacilityKind {
         /// 镜像 tag
         tag: String,
     },
+    /// apptainer
+    Apptainer {
+        /// 镜像名
+        image: String,
+        /// 镜像 tag
+        tag: String,
+    },
+    /// oci 镜像
+    Oci {
+        /// 镜像名
+        image: String,
+        /// 镜像摘要
+        digest: String,
+    },
+    /// conda 环境
+    Conda {
+        /// 环境名
+        name: String,
+        /// 安装的包
+        packages: Vec<String>,
+        /// 频道列表
+        channels: Vec<String>,
+    },
+    /// easybuild 模块
+    Module {
+        /// 模块名
+        name: String,
+        /// 模块版本
+        version: String,
+        /// easyconfig
+        easyconfig: Option<String>,
+    },
 }
 
 /// 资源使用
@@ -173,6 +205,26 @@ impl From<SoftwareSpec> for FacilityKind {
                 argument_list,
             },
             SoftwareSpec::Singularity { image, tag } => FacilityKind::Singularity { image, tag },
+            SoftwareSpec::Apptainer { image, tag } => FacilityKind::Apptainer { image, tag },
+            SoftwareSpec::Oci { image, digest } => FacilityKind::Oci { image, digest },
+            SoftwareSpec::Conda {
+                name,
+                packages,
+                channels,
+            } => FacilityKind::Conda {
+                name,
+                packages,
+                channels,
+            },
+            SoftwareSpec::Module {
+                name,
+                version,
+                easyconfig,
+            } => FacilityKind::Module {
+                name,
+                version,
+                easyconfig,
+            },
         }
     }
 }
@@ -2,7 +2,10 @@ pub mod abilities;
 pub mod command_preview;
 pub mod node_ability_kind;
 pub mod node_draft;
+mod package_version;
+mod pinned_package;
 mod software_computing_usecase;
+mod software_dependency;
 mod template_keys;
 mod validate;
 
@@ -11,7 +14,10 @@ pub use {
     command_preview::CommandPreview,
     node_ability_kind::NodeAbilityKind,
     node_draft::NodeDraft,
+    package_version::PackageVersion,
+    pinned_package::PinnedPackage,
     software_computing_usecase::SoftwareComputingUsecase,
+    software_dependency::SoftwareDependency,
     template_keys::TemplateKeys,
     validate::ValidateData,
 };
@@ -0,0 +1,10 @@
+use uuid::Uuid;
+
+/// 内容实体的一个版本
+#[derive(Debug, Clone)]
+pub struct PackageVersion {
+    /// 版本 id
+    pub version_id: Uuid,
+    /// 版本号
+    pub version: String,
+}
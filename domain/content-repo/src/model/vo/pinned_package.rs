@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 固定了内容哈希的软件包版本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedPackage {
+    /// 版本 id
+    pub version_id: Uuid,
+    /// 包内容的哈希
+    pub hash: String,
+}
@@ -17,6 +17,10 @@ pub struct SoftwareComputingUsecase {
     pub usecase_version_id: Uuid,
     /// 软件包 id
     pub software_version_id: Uuid,
+    /// 用例包哈希
+    pub usecase_hash: String,
+    /// 软件包哈希
+    pub software_hash: String,
     /// 用例规格
     pub usecase_spec: UsecaseSpec,
     /// 软件规格
@@ -46,9 +50,19 @@ impl SoftwareComputingUsecase {
                 let (software_version_id, software_data) =
                     p1.software_package_data().or(p2.software_package_data()).unwrap();
 
+                let hash_of = |version_id: Uuid| {
+                    if p1.version_id == version_id {
+                        p1.hash.to_owned()
+                    } else {
+                        p2.hash.to_owned()
+                    }
+                };
+
                 Self {
                     usecase_version_id,
                     software_version_id,
+                    usecase_hash: hash_of(usecase_version_id),
+                    software_hash: hash_of(software_version_id),
                     usecase_spec: usecase_data.spec,
                     software_spec: software_data.spec,
                     arguments: software_data.arguments,
@@ -0,0 +1,18 @@
+use uuid::Uuid;
+
+use super::abilities::software_computing::software::SoftwareSpec;
+
+/// 解析得到的软件包依赖
+#[derive(Debug, Clone)]
+pub struct SoftwareDependency {
+    /// 软件包 id
+    pub software_version_id: Uuid,
+    /// 系统名称
+    pub system_name: String,
+    /// 版本号
+    pub version: String,
+    /// 包内容的哈希
+    pub hash: String,
+    /// 软件规格
+    pub software_spec: SoftwareSpec,
+}
@@ -1,11 +1,34 @@
 use alice_architecture::repository::ReadOnlyRepository;
+use anyhow::bail;
 use async_trait::async_trait;
 use uuid::Uuid;
 
-use crate::model::entity::package::Package;
+use crate::model::{entity::package::Package, vo::PackageVersion};
 
 #[async_trait]
 pub trait PackageRepo: ReadOnlyRepository<Package> + Send + Sync {
+    /// get package tarball by ID
+    async fn get_package_tar(&self, content_entity_ver_id: Uuid) -> anyhow::Result<Vec<u8>>;
     /// get package by ID
-    async fn get_package(&self, content_entity_ver_id: Uuid) -> anyhow::Result<Package>;
+    async fn get_package(&self, content_entity_ver_id: Uuid) -> anyhow::Result<Package> {
+        let tar = self.get_package_tar(content_entity_ver_id).await?;
+        Package::extract_package(content_entity_ver_id, &tar)
+    }
+    /// get package by ID, fails if the package is changed since its hash is pinned
+    async fn get_pinned_package(
+        &self,
+        content_entity_ver_id: Uuid,
+        hash: &str,
+    ) -> anyhow::Result<Package> {
+        let package = self.get_package(content_entity_ver_id).await?;
+        if package.hash != hash {
+            bail!(
+                "Package: {content_entity_ver_id} is changed, pinned hash: {hash}, current hash: {}",
+                package.hash
+            );
+        }
+        Ok(package)
+    }
+    /// get all versions of the content entity by its system name
+    async fn get_versions(&self, system_name: &str) -> anyhow::Result<Vec<PackageVersion>>;
 }
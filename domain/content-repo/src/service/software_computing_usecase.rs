@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use uuid::Uuid;
 
-use crate::model::vo::SoftwareComputingUsecase;
+use crate::model::vo::{PinnedPackage, SoftwareComputingUsecase, SoftwareDependency};
 
 #[async_trait]
 pub trait SoftwareComputingUsecaseInfoService: Send + Sync {
@@ -11,4 +11,22 @@ pub trait SoftwareComputingUsecaseInfoService: Send + Sync {
         software_ver_id: Uuid,
         usecase_ver_id: Uuid,
     ) -> anyhow::Result<SoftwareComputingUsecase>;
+    /// get the data for parsing a software usecase node from the packages of the pinned hashes
+    async fn get_pinned_computing_usecase(
+        &self,
+        software_ver_id: Uuid,
+        software_hash: &str,
+        usecase_ver_id: Uuid,
+        usecase_hash: &str,
+    ) -> anyhow::Result<SoftwareComputingUsecase>;
+    /// get the dependency closure of a software package, dependencies come before their dependents
+    async fn get_software_dependencies(
+        &self,
+        software_ver_id: Uuid,
+    ) -> anyhow::Result<Vec<SoftwareDependency>>;
+    /// get the dependencies resolved before from the packages of the pinned hashes, in the same order
+    async fn get_pinned_software_dependencies(
+        &self,
+        pinned: &[PinnedPackage],
+    ) -> anyhow::Result<Vec<SoftwareDependency>>;
 }
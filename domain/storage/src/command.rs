@@ -16,6 +16,15 @@ pub struct FileUploadCommand {
     pub task_id: Option<Uuid>,
 }
 
+/// Extract an uploaded archive into the net disk directory created for it, in background.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractArchiveCommand {
+    pub archive_id: Uuid,
+    pub dir_id: Uuid,
+    pub user_id: Uuid,
+}
+
 /// View realtime file message.
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -42,16 +51,12 @@ pub struct RequestSnapshotCommand {
 }
 
 pub enum CacheOperateCommand {
-    /// Whole file upload or complete multipart.
-    WriteNormal { meta_id: Uuid, content: Vec<u8> },
     /// Complete a part of multipart.
     WritePart(Part),
     /// Remove multipart dir.
     RemoveMultipartDir { meta_id: Uuid },
-    /// Remove normal file.
-    RemoveNormal { meta_id: Uuid },
-    /// Change normal file to snapshot file.
-    ChangeNormalToSnapshot { meta_id: Uuid },
+    /// Assemble parts of a completed multipart into snapshot file, and remove the parts.
+    ChangeMultipartToSnapshot { meta_id: Uuid },
     /// Remove snapshot file.
     RemoveSnapshot { meta_id: Uuid },
     /// Ok if exists, else Err
@@ -59,7 +64,6 @@ pub enum CacheOperateCommand {
 }
 
 pub enum CacheReadCommand {
-    ReadNormal { meta_id: Uuid },
     ReadSnapshot { meta_id: Uuid },
     ReadPart { meta_id: Uuid, nth: u64 },
 }
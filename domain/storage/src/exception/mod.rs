@@ -52,6 +52,49 @@ pub enum FileException {
     #[status(106)]
     EmptyFile,
 
+    #[error("Net disk file: {id} can't be found.")]
+    #[status(107)]
+    NetDiskFileNotFound {
+        #[content]
+        id: Uuid,
+    },
+
+    #[error("File name: {name} is invalid.")]
+    #[status(108)]
+    InvalidFileName {
+        #[content]
+        name: String,
+    },
+
+    #[error("Net disk file: {id} can't be moved or copied into {parent_id}, which is itself or inside it.")]
+    #[status(109)]
+    InvalidNetDiskDestination { id: Uuid, parent_id: Uuid },
+
+    #[error("Root directory of net disk can't be modified.")]
+    #[status(110)]
+    NetDiskRootNotModifiable,
+
+    #[error("File: {name} isn't a supported archive, only tar, tar.gz and zip are supported.")]
+    #[status(111)]
+    UnsupportedArchive {
+        #[content]
+        name: String,
+    },
+
+    #[error("Archive can't be extracted, it has {reason}.")]
+    #[status(113)]
+    ArchiveLimitExceeded {
+        #[content]
+        reason: String,
+    },
+
+    #[error("Direct upload isn't available: {reason}")]
+    #[status(112)]
+    DirectUploadUnavailable {
+        #[content]
+        reason: String,
+    },
+
     #[error("File internal error: {source}")]
     #[status(500)]
     InternalError {
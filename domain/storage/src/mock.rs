@@ -1,15 +1,26 @@
+use std::{ops::Range, time::Duration};
+
 use crate::{
     command::{FileUploadCommand, RequestSnapshotCommand},
+    exception::FileResult,
     model::{
         entity::{
-            FileMeta, FileStorage, MoveRegistration, Multipart, NetDisk, Snapshot, TextStorage,
+            FileMeta, FileStorage, MoveRegistration, Multipart, NetDisk, Snapshot, StorageServer,
+            TextStorage,
+        },
+        vo::{
+            DirectUpload, FileReferences, HashAlgorithm, NetDiskPage, NetDiskUsage, Part,
+            PlacementHint, PresignedUpload, ServerUrl,
         },
-        vo::HashAlgorithm,
     },
     repository::{
         FileMetaRepo, FileStorageRepo, MoveRegistrationRepo, MultipartRepo, NetDiskRepo,
         SnapshotRepo, TextStorageRepo,
     },
+    service::{
+        CachedParts, FileMoveService, MultipartService, StorageServerBrokerService,
+        StorageServerResourceService,
+    },
 };
 use alice_architecture::{
     message_queue::producer::MessageQueueProducerTemplate,
@@ -86,6 +97,14 @@ mock! {
             file_name: &str,
         ) -> anyhow::Result<bool>;
         async fn create_root(&self) -> anyhow::Result<Uuid>;
+        async fn get_children(&self, parent_id: Uuid) -> anyhow::Result<Vec<NetDisk>>;
+        async fn get_children_paged(
+            &self,
+            parent_id: Uuid,
+            page: u64,
+            page_size: u64,
+        ) -> anyhow::Result<NetDiskPage>;
+        async fn get_usage(&self) -> anyhow::Result<NetDiskUsage>;
     }
     impl DBRepository<NetDisk> for NetDiskRepo {}
     impl ReadOnlyRepository<NetDisk> for NetDiskRepo {}
@@ -101,12 +120,23 @@ mock! {
             storage_server_id: Uuid,
             meta_id: Uuid,
         ) -> anyhow::Result<String>;
+        async fn get_all_by_meta_id(&self, meta_id: Uuid) -> anyhow::Result<Vec<FileStorage>>;
+        async fn get_used_size(&self, storage_server_id: Uuid) -> anyhow::Result<u64>;
+        async fn delete_by_meta_id(&self, meta_id: Uuid) -> anyhow::Result<()>;
     }
     impl DBRepository<FileStorage> for FileStorageRepo {}
     impl ReadOnlyRepository<FileStorage> for FileStorageRepo {}
     impl MutableRepository<FileStorage> for FileStorageRepo {}
 }
 
+mock! {
+    pub StorageServerRepo {}
+    #[async_trait]
+    impl ReadOnlyRepository<StorageServer> for StorageServerRepo {
+        async fn get_by_id(&self, uuid: Uuid) -> anyhow::Result<StorageServer>;
+    }
+}
+
 mock! {
     pub FileMetaRepo {}
     #[async_trait]
@@ -116,10 +146,23 @@ mock! {
             hash: &str,
             hash_algorithm: &HashAlgorithm,
         ) -> anyhow::Result<Option<FileMeta>>;
+        async fn get_references(&self, meta_id: Uuid) -> anyhow::Result<FileReferences>;
+        async fn get_unreferenced(
+            &self,
+            dereferenced_before: chrono::DateTime<chrono::Utc>,
+            limit: u64,
+        ) -> anyhow::Result<Vec<FileMeta>>;
+        async fn tombstone(&self, meta_id: Uuid) -> anyhow::Result<bool>;
+        async fn set_pinned(&self, owner: &str, meta_ids: &[Uuid]) -> anyhow::Result<()>;
+        async fn restore(&self, meta_id: Uuid) -> anyhow::Result<()>;
     }
     impl DBRepository<FileMeta> for FileMetaRepo {}
     impl ReadOnlyRepository<FileMeta> for FileMetaRepo {}
-    impl MutableRepository<FileMeta> for FileMetaRepo {}
+    #[async_trait]
+    impl MutableRepository<FileMeta> for FileMetaRepo {
+        async fn delete_by_id(&self, uuid: Uuid) -> anyhow::Result<()>;
+        async fn save_changed(&self) -> anyhow::Result<bool>;
+    }
 }
 
 mock! {
@@ -127,6 +170,7 @@ mock! {
     #[async_trait]
     impl MultipartRepo for MultipartRepo {
         async fn get_one_by_key_regex(&self, regex: &str) -> anyhow::Result<Option<Multipart>>;
+        async fn get_all_by_key_regex(&self, regex: &str) -> anyhow::Result<Vec<Multipart>>;
         async fn delete_by_key_regex(&self, regex: &str) -> anyhow::Result<()>;
         async fn remove_nth(&self, id: Uuid, nth: u64, ttl: i64) -> crate::exception::FileResult<Multipart> ;
     }
@@ -148,3 +192,117 @@ mock! {
     impl ReadOnlyRepository<TextStorage> for TextStorageRepo {}
     impl MutableRepository<TextStorage> for TextStorageRepo {}
 }
+
+mock! {
+    pub StorageServerBrokerService {}
+    #[async_trait]
+    impl StorageServerBrokerService for StorageServerBrokerService {
+        async fn upload(
+            &self,
+            storage_server: &StorageServer,
+            parts: &CachedParts,
+        ) -> anyhow::Result<ServerUrl>;
+        async fn download(&self, storage_server: &StorageServer, meta_id: Uuid) -> anyhow::Result<()>;
+        async fn get_download_url(
+            &self,
+            storage_server: &StorageServer,
+            meta_id: Uuid,
+        ) -> anyhow::Result<String>;
+        async fn get_bytes(
+            &self,
+            storage_server: &StorageServer,
+            meta_id: Uuid,
+        ) -> anyhow::Result<Vec<u8>>;
+        async fn get_text(
+            &self,
+            storage_server: &StorageServer,
+            meta_id: Uuid,
+        ) -> anyhow::Result<String>;
+        async fn rangely_get_file(
+            &self,
+            storage_server: &StorageServer,
+            meta_id: Uuid,
+            ranges: &[Range<u64>],
+        ) -> anyhow::Result<Vec<Vec<u8>>>;
+        async fn get_file_size(
+            &self,
+            storage_server: &StorageServer,
+            meta_id: Uuid,
+        ) -> anyhow::Result<u64>;
+        async fn delete(&self, storage_server: &StorageServer, meta_id: Uuid) -> anyhow::Result<()>;
+        async fn create_direct_upload(
+            &self,
+            storage_server: &StorageServer,
+            meta_id: Uuid,
+            part_count: u64,
+            expires_in: Duration,
+        ) -> anyhow::Result<PresignedUpload>;
+        async fn complete_direct_upload(
+            &self,
+            storage_server: &StorageServer,
+            meta_id: Uuid,
+            upload: &DirectUpload,
+            part_count: u64,
+        ) -> anyhow::Result<ServerUrl>;
+        async fn abort_direct_upload(
+            &self,
+            storage_server: &StorageServer,
+            meta_id: Uuid,
+            upload: &DirectUpload,
+        ) -> anyhow::Result<()>;
+    }
+}
+
+mock! {
+    pub StorageServerResourceService {}
+    #[async_trait]
+    impl StorageServerResourceService for StorageServerResourceService {
+        async fn default_file_storage_server(&self) -> anyhow::Result<StorageServer>;
+        async fn get_storage_server(&self, id: Uuid) -> anyhow::Result<StorageServer>;
+        async fn place(&self, size: u64, hint: &PlacementHint) -> anyhow::Result<Vec<StorageServer>>;
+    }
+}
+
+mock! {
+    pub MultipartService {}
+    #[async_trait]
+    impl MultipartService for MultipartService {
+        async fn create(
+            &self,
+            meta_id: Uuid,
+            hash: &str,
+            hash_algorithm: HashAlgorithm,
+            count: u64,
+        ) -> FileResult<()>;
+        async fn create_direct(
+            &self,
+            meta_id: Uuid,
+            hash: &str,
+            hash_algorithm: HashAlgorithm,
+            count: u64,
+            direct_upload: DirectUpload,
+        ) -> FileResult<()>;
+        async fn complete_part(&self, part: Part) -> FileResult<Vec<u64>>;
+        async fn info(&self, meta_id: Uuid) -> FileResult<Multipart>;
+        async fn remove(&self, meta_id: Uuid) -> FileResult<()>;
+    }
+}
+
+mock! {
+    pub FileMoveService {}
+    #[async_trait]
+    impl FileMoveService for FileMoveService {
+        async fn register_move(&self, info: MoveRegistration) -> FileResult<()>;
+        async fn do_registered_moves(&self, meta_id: Uuid) -> FileResult<()>;
+        async fn if_possible_do_flash_upload(&self, info: &MoveRegistration) -> FileResult<()>;
+        async fn set_all_moves_with_same_meta_id_as_failed(
+            &self,
+            meta_id: Uuid,
+            failed_reason: &str,
+        ) -> FileResult<()>;
+        async fn set_move_as_failed(&self, move_id: Uuid, failed_reason: &str) -> FileResult<()>;
+        async fn get_move_info(&self, move_id: Uuid) -> FileResult<Option<MoveRegistration>>;
+        async fn get_meta_id_failed_info(&self, meta_id: Uuid) -> FileResult<(bool, Option<String>)>;
+        async fn remove_all_with_meta_id(&self, meta_id: Uuid) -> FileResult<()>;
+    }
+}
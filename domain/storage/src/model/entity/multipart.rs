@@ -1,8 +1,9 @@
 use alice_architecture::model::AggregateRoot;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::model::vo::HashAlgorithm;
+use crate::model::vo::{DirectUpload, HashAlgorithm};
 
 /// Multipart upload information.
 #[derive(Debug, Serialize, Deserialize, AggregateRoot)]
@@ -16,4 +17,13 @@ pub struct Multipart {
     /// Are parts of the multipart uploaded.
     pub shards: Vec<u64>,
     pub part_count: u64,
+    /// Task that uploads the file, it is failed if the upload expires.
+    #[serde(default)]
+    pub task_id: Option<Uuid>,
+    /// Time of the last completed part, or of creation if no part is completed.
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+    /// Set if parts are uploaded to the object storage directly instead of the local cache.
+    #[serde(default)]
+    pub direct_upload: Option<DirectUpload>,
 }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use alice_architecture::model::AggregateRoot;
 use anyhow::bail;
 use database_model::storage_server;
@@ -24,6 +26,27 @@ pub enum StorageType {
         #[serde(flatten)]
         options: ObjectServerOption,
     },
+    /// Local or mounted network filesystem, e.g. NFS.
+    FileSystem {
+        #[serde(flatten)]
+        options: FileSystemOption,
+    },
+    /// Any other backend supported by opendal, e.g. WebDAV, SFTP.
+    OpenDal {
+        #[serde(flatten)]
+        options: OpenDalOption,
+    },
+}
+
+impl StorageType {
+    /// Endpoint that files can be downloaded from, if any.
+    pub fn download_endpoint(&self) -> Option<&str> {
+        match self {
+            StorageType::ObjectStorage { options } => Some(&options.download_endpoint),
+            StorageType::FileSystem { options } => options.download_endpoint.as_deref(),
+            StorageType::OpenDal { options } => options.download_endpoint.as_deref(),
+        }
+    }
 }
 
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
@@ -31,12 +54,38 @@ pub enum StorageType {
 pub struct ObjectServerOption {
     pub endpoint: String,
     pub download_endpoint: String,
+    /// Endpoint that clients upload files to directly, `endpoint` is used if not set.
+    #[serde(default)]
+    pub upload_endpoint: Option<String>,
     pub default_bucket: String,
     pub access_key_id: String,
     pub secret_access_key: String,
     pub region: String,
 }
 
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSystemOption {
+    /// Root directory to store files in.
+    pub root: String,
+    /// Endpoint serving the root directory, e.g. a static file server.
+    #[serde(default)]
+    pub download_endpoint: Option<String>,
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenDalOption {
+    /// Opendal service scheme, e.g. `webdav`, `sftp`.
+    pub scheme: String,
+    /// Service config passed to opendal as is, e.g. `endpoint`, `root`, `user`.
+    #[serde(default)]
+    pub config: HashMap<String, String>,
+    /// Endpoint serving the stored files, if any.
+    #[serde(default)]
+    pub download_endpoint: Option<String>,
+}
+
 impl TryFrom<storage_server::Model> for StorageServer {
     type Error = anyhow::Error;
 
@@ -50,12 +99,17 @@ impl TryFrom<storage_server::Model> for StorageServer {
             available_zone_id: _,
         } = model;
         let capacity: u64 = capacity.parse()?;
-        let storage_type = if storage_type == 0 {
-            StorageType::ObjectStorage {
+        let storage_type = match storage_type {
+            0 => StorageType::ObjectStorage {
+                options: serde_json::from_value(options)?,
+            },
+            1 => StorageType::FileSystem {
+                options: serde_json::from_value(options)?,
+            },
+            2 => StorageType::OpenDal {
                 options: serde_json::from_value(options)?,
-            }
-        } else {
-            bail!("unsupported storage type");
+            },
+            _ => bail!("unsupported storage type"),
         };
 
         Ok(Self {
@@ -0,0 +1,29 @@
+use uuid::Uuid;
+
+/// Net disk directory to archive.
+#[derive(Debug, Clone)]
+pub enum ArchiveTarget {
+    /// Any net disk directory.
+    NetDisk { id: Uuid },
+    /// Directory of a workflow instance's outputs.
+    FlowInstance { id: Uuid },
+    /// Directory of a node instance's outputs.
+    NodeInstance { id: Uuid },
+}
+
+/// A file or directory in an archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    /// Path in the archive, starts with the archived directory's name.
+    pub path: String,
+    /// File meta of a file, None for a directory.
+    pub meta_id: Option<Uuid>,
+}
+
+/// Content of an archive, parents always come before their children.
+#[derive(Debug, Clone)]
+pub struct ArchiveListing {
+    /// Name of the archived directory.
+    pub name: String,
+    pub entries: Vec<ArchiveEntry>,
+}
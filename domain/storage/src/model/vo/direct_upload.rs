@@ -0,0 +1,21 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Multipart upload that clients send parts of to the object storage directly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectUpload {
+    /// Storage server the file is uploaded to.
+    pub storage_server_id: Uuid,
+    /// Upload id given by the object storage.
+    pub upload_id: String,
+}
+
+/// Presigned urls to upload parts of a direct upload.
+pub struct PresignedUpload {
+    pub upload: DirectUpload,
+    /// Url of the nth part, parts are uploaded with `PUT` requests.
+    pub part_urls: Vec<String>,
+    /// Urls can't be used after it.
+    pub expires_at: DateTime<Utc>,
+}
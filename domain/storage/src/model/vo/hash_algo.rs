@@ -7,12 +7,27 @@ use serde::{Deserialize, Serialize};
 pub enum HashAlgorithm {
     #[default]
     Blake3,
+    Sha256,
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    /// Whether files with the same hash can be taken as the same file. xxh3 is fast but not
+    /// collision resistant, it only verifies transports and never deduplicates files.
+    pub fn is_collision_resistant(&self) -> bool {
+        match self {
+            Self::Blake3 | Self::Sha256 => true,
+            Self::Xxh3 => false,
+        }
+    }
 }
 
 impl std::fmt::Display for HashAlgorithm {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Blake3 => write!(f, "blake3"),
+            Self::Sha256 => write!(f, "sha256"),
+            Self::Xxh3 => write!(f, "xxh3"),
         }
     }
 }
@@ -23,6 +38,8 @@ impl std::str::FromStr for HashAlgorithm {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "blake3" => Ok(Self::Blake3),
+            "sha256" => Ok(Self::Sha256),
+            "xxh3" => Ok(Self::Xxh3),
             _ => bail!("{s} can't be transformed to HashAlgorithm"),
         }
     }
@@ -1,18 +1,28 @@
+mod archive;
 mod content_extractor;
+mod direct_upload;
 mod hash_algo;
 mod mover;
 mod multipart;
+mod net_disk;
+mod placement;
 mod record;
+mod reference;
 mod server;
 mod snapshot;
 
 #[rustfmt::skip]
 pub use {
+    archive::*,
+    direct_upload::*,
     hash_algo::*,
     multipart::*,
+    net_disk::*,
+    placement::*,
     server::*,
     content_extractor::*,
     mover::*,
     record::*,
+    reference::*,
     snapshot::*,
 };
@@ -0,0 +1,19 @@
+use crate::model::entity::NetDisk;
+
+/// A page of net disk files in a directory.
+#[derive(Debug, Clone)]
+pub struct NetDiskPage {
+    /// Files of the page, directories first then by name.
+    pub items: Vec<NetDisk>,
+    /// Count of all files in the directory.
+    pub total: u64,
+}
+
+/// Storage used by a user's net disk.
+#[derive(Debug, Clone, Default)]
+pub struct NetDiskUsage {
+    /// Total size of files, a file referenced twice is counted twice.
+    pub used: u64,
+    /// Count of files, directories excluded.
+    pub file_count: u64,
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::model::entity::StorageServer;
+
+/// Where to store uploaded files and how many copies to keep.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlacementConfig {
+    /// Policy to pick storage servers.
+    #[serde(default)]
+    pub policy: PlacementPolicy,
+    /// Candidate storage servers besides the default one.
+    #[serde(default)]
+    pub storage_server_ids: Vec<Uuid>,
+    /// Number of copies stored on different servers.
+    #[serde(default = "PlacementConfig::default_replicas")]
+    pub replicas: usize,
+}
+
+impl PlacementConfig {
+    fn default_replicas() -> usize {
+        1
+    }
+}
+
+impl Default for PlacementConfig {
+    fn default() -> Self {
+        Self {
+            policy: Default::default(),
+            storage_server_ids: vec![],
+            replicas: Self::default_replicas(),
+        }
+    }
+}
+
+/// Policy to pick storage servers.
+///
+/// Servers preferred by the policy come first, the others follow by free capacity. Servers
+/// without enough free capacity for the file are never picked.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PlacementPolicy {
+    /// Prefer the default storage server.
+    #[default]
+    Default,
+    /// Prefer the server with the most free capacity.
+    Capacity,
+    /// Prefer servers assigned to the project, then the ones assigned to the user.
+    Affinity {
+        #[serde(default)]
+        users: HashMap<Uuid, Vec<Uuid>>,
+        #[serde(default)]
+        projects: HashMap<Uuid, Vec<Uuid>>,
+    },
+    /// Prefer servers near the queue that consumes the file.
+    QueueProximity { queues: HashMap<Uuid, Vec<Uuid>> },
+}
+
+/// Who uploads the file and where it is consumed, used by placement policies.
+#[derive(Clone, Debug, Default)]
+pub struct PlacementHint {
+    pub user_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    /// Queue the file is consumed on.
+    pub queue_id: Option<Uuid>,
+}
+
+/// A candidate storage server with its used capacity.
+#[derive(Clone, Debug)]
+pub struct StorageServerUsage {
+    pub storage_server: StorageServer,
+    /// Bytes stored on the server.
+    pub used: u64,
+}
+
+impl StorageServerUsage {
+    fn free(&self) -> u64 {
+        self.storage_server.capacity.saturating_sub(self.used)
+    }
+}
+
+impl PlacementPolicy {
+    fn preferred(&self, hint: &PlacementHint, default_server_id: Uuid) -> Vec<Uuid> {
+        let lookup = |map: &HashMap<Uuid, Vec<Uuid>>, key: Option<Uuid>| {
+            key.and_then(|el| map.get(&el)).cloned().unwrap_or_default()
+        };
+        match self {
+            PlacementPolicy::Default => vec![default_server_id],
+            PlacementPolicy::Capacity => vec![],
+            PlacementPolicy::Affinity { users, projects } => {
+                let mut preferred = lookup(projects, hint.project_id);
+                preferred.extend(lookup(users, hint.user_id));
+                preferred
+            }
+            PlacementPolicy::QueueProximity { queues } => lookup(queues, hint.queue_id),
+        }
+    }
+
+    /// Order candidate servers to store a file, servers that can't hold it are left out.
+    pub fn place(
+        &self,
+        mut candidates: Vec<StorageServerUsage>,
+        size: u64,
+        hint: &PlacementHint,
+        default_server_id: Uuid,
+    ) -> Vec<StorageServer> {
+        let preferred = self.preferred(hint, default_server_id);
+        candidates.retain(|el| el.free() >= size);
+        candidates.sort_by_key(|el| {
+            let rank = preferred
+                .iter()
+                .position(|id| id.eq(&el.storage_server.id))
+                .unwrap_or(preferred.len());
+            (rank, u64::MAX - el.free())
+        });
+        candidates.into_iter().map(|el| el.storage_server).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::entity::{ObjectServerOption, StorageType};
+
+    use super::*;
+
+    #[test]
+    fn test_place() {
+        let usage = |capacity, used| StorageServerUsage {
+            storage_server: StorageServer {
+                id: Uuid::new_v4(),
+                name: String::new(),
+                capacity,
+                storage_type: StorageType::ObjectStorage {
+                    options: ObjectServerOption::default(),
+                },
+            },
+            used,
+        };
+        let default = usage(100, 90);
+        let roomy = usage(100, 10);
+        let full = usage(100, 100);
+        let ids = |servers: Vec<StorageServer>| servers.iter().map(|el| el.id).collect::<Vec<_>>();
+        let candidates = vec![roomy.clone(), full.clone(), default.clone()];
+        let hint = PlacementHint::default();
+
+        assert_eq!(
+            ids(PlacementPolicy::Default.place(
+                candidates.clone(),
+                5,
+                &hint,
+                default.storage_server.id
+            )),
+            vec![default.storage_server.id, roomy.storage_server.id]
+        );
+        assert_eq!(
+            ids(PlacementPolicy::Capacity.place(
+                candidates.clone(),
+                5,
+                &hint,
+                default.storage_server.id
+            )),
+            vec![roomy.storage_server.id, default.storage_server.id]
+        );
+        assert_eq!(
+            ids(PlacementPolicy::Default.place(candidates, 20, &hint, default.storage_server.id)),
+            vec![roomy.storage_server.id]
+        );
+    }
+}
@@ -0,0 +1,19 @@
+/// References to a deduplicated file, the file can be collected when nothing references it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileReferences {
+    /// Net disk entries.
+    pub net_disk: u64,
+    /// Workflow instances whose slots use the file.
+    pub workflow: u64,
+    /// Owners outside the database that pin the file, e.g. the package store index.
+    pub pinned: u64,
+    /// Snapshots with the same content.
+    pub snapshot: u64,
+}
+
+impl FileReferences {
+    /// Total count of references.
+    pub fn total(&self) -> u64 {
+        self.net_disk + self.workflow + self.pinned + self.snapshot
+    }
+}
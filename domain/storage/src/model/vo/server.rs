@@ -14,6 +14,11 @@ impl std::fmt::Display for ServerUrl {
             storage_server_id,
             meta_id,
         } = self;
-        write!(f, "{bucket}/storage-{storage_server_id}/{meta_id}")
+        // Backends other than object storage have no bucket.
+        if bucket.is_empty() {
+            write!(f, "storage-{storage_server_id}/{meta_id}")
+        } else {
+            write!(f, "{bucket}/storage-{storage_server_id}/{meta_id}")
+        }
     }
 }
@@ -12,4 +12,14 @@ pub trait FileStorageRepo: DBRepository<FileStorage> + Send + Sync {
         storage_server_id: Uuid,
         meta_id: Uuid,
     ) -> anyhow::Result<String>;
+
+    /// Get all replicas of a file.
+    async fn get_all_by_meta_id(&self, meta_id: Uuid) -> anyhow::Result<Vec<FileStorage>>;
+
+    /// Get total size of files stored on the storage server, from the usage counted when
+    /// storages are inserted and deleted.
+    async fn get_used_size(&self, storage_server_id: Uuid) -> anyhow::Result<u64>;
+
+    /// Delete records of all replicas of a file.
+    async fn delete_by_meta_id(&self, meta_id: Uuid) -> anyhow::Result<()>;
 }
@@ -1,13 +1,42 @@
 use alice_architecture::repository::DBRepository;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
 
-use crate::model::{entity::FileMeta, vo::HashAlgorithm};
+use crate::model::{
+    entity::FileMeta,
+    vo::{FileReferences, HashAlgorithm},
+};
 
 #[async_trait]
 pub trait FileMetaRepo: DBRepository<FileMeta> + Send + Sync {
+    /// Get the meta of the hash, tombstoned metas are skipped.
     async fn get_by_hash_and_algorithm(
         &self,
         hash: &str,
         hash_algorithm: &HashAlgorithm,
     ) -> anyhow::Result<Option<FileMeta>>;
+
+    /// Count references from net disk and workflows, snapshots are not counted.
+    async fn get_references(&self, meta_id: Uuid) -> anyhow::Result<FileReferences>;
+
+    /// Get file metas that neither net disk nor workflows reference, whose last reference is
+    /// removed before the time, or that are never referenced and created before it. Tombstoned
+    /// metas are always included. Least recently dereferenced first.
+    async fn get_unreferenced(
+        &self,
+        dereferenced_before: DateTime<Utc>,
+        limit: u64,
+    ) -> anyhow::Result<Vec<FileMeta>>;
+
+    /// Tombstone the meta immediately if nothing references it, return whether it is
+    /// tombstoned. Adding references to a tombstoned meta fails, and flash uploads skip it.
+    async fn tombstone(&self, meta_id: Uuid) -> anyhow::Result<bool>;
+
+    /// Replace the metas pinned by the owner immediately, pinned metas are not collected. For
+    /// files used outside the database, e.g. by the package store index.
+    async fn set_pinned(&self, owner: &str, meta_ids: &[Uuid]) -> anyhow::Result<()>;
+
+    /// Undo the tombstone of the meta immediately, when it turns out to be still in use.
+    async fn restore(&self, meta_id: Uuid) -> anyhow::Result<()>;
 }
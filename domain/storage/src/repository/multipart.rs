@@ -8,6 +8,8 @@ use crate::{exception::FileResult, model::entity::Multipart};
 pub trait MultipartRepo: LeaseDBRepository<Multipart> + Send + Sync {
     async fn get_one_by_key_regex(&self, regex: &str) -> anyhow::Result<Option<Multipart>>;
 
+    async fn get_all_by_key_regex(&self, regex: &str) -> anyhow::Result<Vec<Multipart>>;
+
     async fn delete_by_key_regex(&self, regex: &str) -> anyhow::Result<()>;
 
     async fn remove_nth(&self, id: Uuid, nth: u64, ttl: i64) -> FileResult<Multipart>;
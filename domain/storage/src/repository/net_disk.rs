@@ -2,7 +2,10 @@ use alice_architecture::repository::DBRepository;
 use async_trait::async_trait;
 use uuid::Uuid;
 
-use crate::model::entity::NetDisk;
+use crate::model::{
+    entity::NetDisk,
+    vo::{NetDiskPage, NetDiskUsage},
+};
 
 #[async_trait]
 pub trait NetDiskRepo: DBRepository<NetDisk> + Send + Sync {
@@ -18,4 +21,15 @@ pub trait NetDiskRepo: DBRepository<NetDisk> + Send + Sync {
         file_name: &str,
     ) -> anyhow::Result<bool>;
     async fn create_root(&self) -> anyhow::Result<Uuid>;
+    /// Get all files in the directory.
+    async fn get_children(&self, parent_id: Uuid) -> anyhow::Result<Vec<NetDisk>>;
+    /// Get a page of files in the directory, page starts from 1.
+    async fn get_children_paged(
+        &self,
+        parent_id: Uuid,
+        page: u64,
+        page_size: u64,
+    ) -> anyhow::Result<NetDiskPage>;
+    /// Get storage used by the user's net disk.
+    async fn get_usage(&self) -> anyhow::Result<NetDiskUsage>;
 }
@@ -0,0 +1,18 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+/// Extract archives uploaded to net disk, supports tar, tar.gz and zip.
+///
+/// Each member becomes a net disk file with its own file meta, members with the same hash as an
+/// uploaded file are flash uploaded. Archives are unpacked on disk with limits of their total
+/// size and count of members.
+#[async_trait]
+pub trait ArchiveExtractService: Send + Sync {
+    /// Create a new directory named after the archive in `parent_id` or next to the archive, and
+    /// extract the archive into it in background. Returns id of the new directory.
+    async fn extract(&self, id: Uuid, parent_id: Option<Uuid>) -> anyhow::Result<Uuid>;
+
+    /// Extract the archive into the directory created by [`Self::extract`], the directory is
+    /// deleted if it fails.
+    async fn run_extraction(&self, archive_id: Uuid, dir_id: Uuid) -> anyhow::Result<()>;
+}
@@ -1,4 +1,8 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
 
 use crate::command::*;
 
@@ -9,4 +13,38 @@ use crate::command::*;
 pub trait CacheService: Send + Sync {
     async fn operate(&self, cmd: CacheOperateCommand) -> anyhow::Result<()>;
     async fn read(&self, cmd: CacheReadCommand) -> anyhow::Result<Vec<u8>>;
+    /// List cached multipart dirs.
+    async fn get_multipart_dirs(&self) -> anyhow::Result<Vec<MultipartDir>>;
+}
+
+/// A cached multipart dir.
+pub struct MultipartDir {
+    /// File meta id.
+    pub meta_id: Uuid,
+    /// Last time a part is added into the dir.
+    pub modified_at: DateTime<Utc>,
+}
+
+/// Cached parts of a completed multipart upload.
+///
+/// Parts are read one by one, so that the whole file is never loaded into memory.
+#[derive(Clone)]
+pub struct CachedParts {
+    /// File meta id.
+    pub meta_id: Uuid,
+    /// Count of parts.
+    pub part_count: u64,
+    pub cache_service: Arc<dyn CacheService>,
+}
+
+impl CachedParts {
+    /// Read the nth part.
+    pub async fn read(&self, nth: u64) -> anyhow::Result<Vec<u8>> {
+        self.cache_service
+            .read(CacheReadCommand::ReadPart {
+                meta_id: self.meta_id,
+                nth,
+            })
+            .await
+    }
 }
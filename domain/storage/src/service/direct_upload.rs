@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::{
+    exception::FileResult,
+    model::{
+        entity::MoveRegistration,
+        vo::{PresignedUpload, ServerUrl},
+    },
+};
+
+/// # Direct upload service
+///
+/// Clients upload parts to the object storage with presigned urls, so that files never pass
+/// through this system, only their metadata is recorded. Moves of the file are done the same
+/// way as cached multipart uploads once the parts are uploaded.
+#[async_trait]
+pub trait DirectUploadService: Send + Sync {
+    /// Start the upload and register the move, return presigned urls of each part.
+    ///
+    /// Error with flash upload if a file with the same hash exists. Unavailable when files are
+    /// stored with more than one replica, parts are uploaded to only one server.
+    async fn prepare(
+        &self,
+        registration: MoveRegistration,
+        count: u64,
+    ) -> FileResult<PresignedUpload>;
+
+    /// Assemble the uploaded parts and verify the hash, return stored file's urls.
+    async fn complete(&self, meta_id: Uuid) -> FileResult<Vec<ServerUrl>>;
+
+    /// Abort the upload on the storage server, Ok if it isn't a direct upload.
+    async fn abort(&self, meta_id: Uuid) -> FileResult<()>;
+}
@@ -0,0 +1,15 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::model::vo::FileReferences;
+
+/// Reference counting and garbage collection of deduplicated files.
+#[async_trait]
+pub trait FileGcService: Send + Sync {
+    /// Count references to the file from net disk, workflows and snapshots.
+    async fn get_references(&self, meta_id: Uuid) -> anyhow::Result<FileReferences>;
+
+    /// Delete files that nothing references from storage servers along with their records,
+    /// return count of collected files.
+    async fn collect(&self) -> anyhow::Result<usize>;
+}
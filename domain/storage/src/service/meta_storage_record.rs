@@ -6,16 +6,17 @@ use crate::model::vo::{HashAlgorithm, RecordFileMeta, RecordFileStorage};
 /// Record file_metadata and file_storage.
 #[async_trait]
 pub trait MetaStorageService: Send + Sync {
-    /// Record uploaded file into file_metadata and file_storage.
+    /// Record uploaded file into file_metadata and file_storage, one file_storage for each
+    /// replica.
     async fn record_meta_and_storage(
         &self,
         meta_id: Uuid,
         file_meta_info: RecordFileMeta,
-        file_storage_info: RecordFileStorage,
+        file_storage_infos: Vec<RecordFileStorage>,
     ) -> anyhow::Result<()>;
 
     /// Look up file_metadata to judge whether the same hash file is uploaded.
-    /// If satisfy, return meta_id.
+    /// If satisfy, return meta_id. Only collision resistant hashes are looked up.
     async fn satisfy_flash_upload(
         &self,
         hash: &str,
@@ -28,4 +29,7 @@ pub trait MetaStorageService: Send + Sync {
         storage_server_id: Uuid,
         meta_id: Uuid,
     ) -> anyhow::Result<String>;
+
+    /// Get ids of storage servers that hold a replica of the file.
+    async fn get_storage_server_ids(&self, meta_id: Uuid) -> anyhow::Result<Vec<Uuid>>;
 }
@@ -1,5 +1,8 @@
+mod archive_extract;
 mod cache;
 mod content_extractor;
+mod direct_upload;
+mod file_gc;
 mod meta_storage_record;
 mod mover;
 mod multipart;
@@ -11,11 +14,15 @@ mod storage_server_download_dispatcher;
 mod storage_server_resource;
 mod storage_server_upload_dispatcher;
 mod text;
+mod upload_sweep;
 
 #[rustfmt::skip]
 pub use {
-    cache::CacheService,
+    archive_extract::ArchiveExtractService,
+    cache::{CacheService, CachedParts, MultipartDir},
     content_extractor::ContentExtractorService,
+    direct_upload::DirectUploadService,
+    file_gc::FileGcService,
     meta_storage_record::MetaStorageService,
     mover::FileMoveService,
     multipart::MultipartService,
@@ -27,4 +34,5 @@ pub use {
     storage_server_resource::StorageServerResourceService,
     storage_server_upload_dispatcher::StorageServerUploadDispatcherService,
     text::TextStorageService,
+    upload_sweep::UploadSweepService,
 };
@@ -3,12 +3,13 @@ use uuid::Uuid;
 
 use crate::exception::FileResult;
 use crate::model::entity::Multipart;
-use crate::model::vo::{HashAlgorithm, Part};
+use crate::model::vo::{DirectUpload, HashAlgorithm, Part};
 
 /// # Multipart file service
 ///
 /// A multipart upload will generate local caches and a lease record,
-/// when the parts are all uploaded and merged to one file, the local caches and record will be removed.
+/// when the parts are all uploaded, they are kept as is until the file is moved to its
+/// destination, then the local caches and record will be removed.
 #[async_trait]
 pub trait MultipartService: Send + Sync {
     /// Create multipart upload record with expire time in milliseconds.
@@ -22,10 +23,22 @@ pub trait MultipartService: Send + Sync {
         count: u64,
     ) -> FileResult<()>;
 
+    /// Create multipart upload record of a direct upload, parts of it are never cached.
+    ///
+    /// Error when a multipart with same meta_id or hash already exists.
+    async fn create_direct(
+        &self,
+        meta_id: Uuid,
+        hash: &str,
+        hash_algorithm: HashAlgorithm,
+        count: u64,
+        direct_upload: DirectUpload,
+    ) -> FileResult<()>;
+
     /// Complete a part.
     ///
-    /// If the multipart is completed, it will validate hash, if passed, return merged cache file,
-    /// else return None.
+    /// Return the parts not uploaded yet, if the multipart is completed, it will validate hash of
+    /// the parts and return empty.
     async fn complete_part(&self, part: Part) -> FileResult<Vec<u64>>;
 
     /// Get multipart info.
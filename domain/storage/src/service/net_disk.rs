@@ -1,6 +1,10 @@
 use async_trait::async_trait;
+use uuid::Uuid;
 
-use crate::command::CreateNetDiskFileCommand;
+use crate::{
+    command::CreateNetDiskFileCommand,
+    model::vo::{ArchiveListing, ArchiveTarget, NetDiskPage, NetDiskUsage},
+};
 
 /// Net disk service.
 ///
@@ -11,4 +15,35 @@ use crate::command::CreateNetDiskFileCommand;
 pub trait NetDiskService: Send + Sync {
     /// Create net disk file.
     async fn create_file(&self, command: CreateNetDiskFileCommand) -> anyhow::Result<()>;
+
+    /// List files in the directory, the user's root directory if `parent_id` is None.
+    async fn list(
+        &self,
+        parent_id: Option<Uuid>,
+        page: u64,
+        page_size: u64,
+    ) -> anyhow::Result<NetDiskPage>;
+
+    /// Create a directory, a suffix is added if the name is taken. Returns id of the directory.
+    async fn create_dir(&self, parent_id: Option<Uuid>, name: &str) -> anyhow::Result<Uuid>;
+
+    /// Rename the file, a suffix is added if the name is taken in the directory.
+    async fn rename(&self, id: Uuid, name: &str) -> anyhow::Result<()>;
+
+    /// Move the file into the directory, the user's root directory if `parent_id` is None.
+    async fn move_to(&self, id: Uuid, parent_id: Option<Uuid>) -> anyhow::Result<()>;
+
+    /// Copy the file into the directory recursively, returns id of the copy.
+    ///
+    /// Copies share file metas with the originals, so file content is not copied.
+    async fn copy(&self, id: Uuid, parent_id: Option<Uuid>) -> anyhow::Result<Uuid>;
+
+    /// Delete the file, and all files in it if it's a directory.
+    async fn delete(&self, id: Uuid) -> anyhow::Result<()>;
+
+    /// Get storage used by the user's net disk.
+    async fn get_usage(&self) -> anyhow::Result<NetDiskUsage>;
+
+    /// Get all files in the directory subtree to put in an archive.
+    async fn get_archive_listing(&self, target: ArchiveTarget) -> anyhow::Result<ArchiveListing>;
 }
@@ -32,7 +32,8 @@ pub trait SnapshotService: Send + Sync {
         node_id: Uuid,
         meta_id: Uuid,
     ) -> anyhow::Result<Vec<Snapshot>>;
-    /// Judge whether the same hash snapshot is uploaded.
+    /// Judge whether the same hash snapshot is uploaded, only collision resistant hashes are
+    /// looked up.
     async fn satisfy_flash_upload(
         &self,
         hash: &str,
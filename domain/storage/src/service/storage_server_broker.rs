@@ -1,20 +1,23 @@
 use async_trait::async_trait;
-use std::ops::Range;
+use std::{ops::Range, time::Duration};
 use uuid::Uuid;
 
-use crate::model::{entity::StorageServer, vo::ServerUrl};
+use super::CachedParts;
+use crate::model::{
+    entity::StorageServer,
+    vo::{DirectUpload, PresignedUpload, ServerUrl},
+};
 
 /// Transport file between local and server.
 ///
 /// Each upload has a status cache record.
 #[async_trait]
 pub trait StorageServerBrokerService: Send + Sync {
-    /// Transport local file to server, return stored file's url.
+    /// Transport cached parts to server as one file by streaming, return stored file's url.
     async fn upload(
         &self,
         storage_server: &StorageServer,
-        meta_id: Uuid,
-        content: &[u8],
+        parts: &CachedParts,
     ) -> anyhow::Result<ServerUrl>;
 
     /// Transport server file to local.
@@ -55,4 +58,38 @@ pub trait StorageServerBrokerService: Send + Sync {
         storage_server: &StorageServer,
         meta_id: Uuid,
     ) -> anyhow::Result<u64>;
+
+    /// Delete file from server, Ok if the file doesn't exist.
+    async fn delete(&self, storage_server: &StorageServer, meta_id: Uuid) -> anyhow::Result<()>;
+
+    /// Start a multipart upload that clients upload parts of directly, return presigned urls of
+    /// each part that expire in `expires_in`.
+    ///
+    /// Only object storage servers support it.
+    async fn create_direct_upload(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+        part_count: u64,
+        expires_in: Duration,
+    ) -> anyhow::Result<PresignedUpload>;
+
+    /// Assemble the uploaded parts into the file, return stored file's url.
+    ///
+    /// Error if any of the parts isn't uploaded.
+    async fn complete_direct_upload(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+        upload: &DirectUpload,
+        part_count: u64,
+    ) -> anyhow::Result<ServerUrl>;
+
+    /// Abort the multipart upload, uploaded parts are discarded.
+    async fn abort_direct_upload(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+        upload: &DirectUpload,
+    ) -> anyhow::Result<()>;
 }
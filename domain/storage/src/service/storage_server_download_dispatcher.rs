@@ -3,7 +3,8 @@ use uuid::Uuid;
 
 use std::ops::Range;
 
-/// Dispatch storage server operations to a certain storage server.
+/// Dispatch storage server operations to the servers holding the file, falling back to another
+/// replica when one fails.
 #[async_trait]
 pub trait StorageServerDownloadDispatcherService: Send + Sync {
     /// Transport file to local.
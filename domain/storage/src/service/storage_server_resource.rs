@@ -1,10 +1,17 @@
 use async_trait::async_trait;
+use uuid::Uuid;
 
-use crate::model::entity::StorageServer;
+use crate::model::{entity::StorageServer, vo::PlacementHint};
 
 /// Manage regions, avz(available zone)s, servers and queues.
 #[async_trait]
 pub trait StorageServerResourceService: Send + Sync {
     /// Get co system default storage server.
     async fn default_file_storage_server(&self) -> anyhow::Result<StorageServer>;
+
+    /// Get storage server by id.
+    async fn get_storage_server(&self, id: Uuid) -> anyhow::Result<StorageServer>;
+
+    /// Get storage servers to store a file in order of preference by placement policy.
+    async fn place(&self, size: u64, hint: &PlacementHint) -> anyhow::Result<Vec<StorageServer>>;
 }
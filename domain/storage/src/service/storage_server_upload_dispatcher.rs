@@ -1,11 +1,19 @@
 use async_trait::async_trait;
-use uuid::Uuid;
 
-use crate::model::vo::ServerUrl;
+use super::CachedParts;
+use crate::model::vo::{PlacementHint, ServerUrl};
 
 /// Dispatch storage server operations to a certain storage server.
 #[async_trait]
 pub trait StorageServerUploadDispatcherService: Send + Sync {
-    /// Transport file to server, return stored file's url.
-    async fn upload(&self, meta_id: Uuid, content: &[u8]) -> anyhow::Result<ServerUrl>;
+    /// Transport file to the servers picked by placement policy, return stored urls of each
+    /// replica. Err when fewer replicas than configured are stored, nothing is kept then.
+    ///
+    /// `size` is the total size of the parts in bytes.
+    async fn upload(
+        &self,
+        parts: &CachedParts,
+        size: u64,
+        hint: &PlacementHint,
+    ) -> anyhow::Result<Vec<ServerUrl>>;
 }
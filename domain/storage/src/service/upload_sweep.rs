@@ -0,0 +1,10 @@
+use async_trait::async_trait;
+
+/// Cleanup of multipart uploads that are abandoned before completion.
+#[async_trait]
+pub trait UploadSweepService: Send + Sync {
+    /// Remove cached parts, multipart records and move registrations of expired uploads, and
+    /// fail tasks that wait for them, return count of swept uploads. Uploads with all parts
+    /// completed or marked as failed are skipped.
+    async fn sweep(&self) -> anyhow::Result<usize>;
+}
@@ -166,6 +166,36 @@ pub enum WorkflowException {
         descriptor: String,
     },
 
+    #[error("The branch of condition node: {node_id} activates node: {to_id}, which is not its out node.")]
+    #[status(217)]
+    NoSuchConditionBranch {
+        #[content]
+        node_id: Uuid,
+        #[content]
+        to_id: Uuid,
+    },
+
+    #[error("The node: {node_id} referenced by the until condition or feedbacks isn't in the loop body.")]
+    #[status(218)]
+    NodeNotInLoopBody {
+        #[content]
+        node_id: Uuid,
+    },
+
+    #[error("The max iterations of loop must be more than zero.")]
+    #[status(219)]
+    ZeroLoopMaxIterations,
+
+    #[error(
+        "The workflow instance: {id} is neither failed nor terminated, so it can't be retried."
+    )]
+    #[status(220)]
+    WorkflowNotRetryable { id: Uuid },
+
+    #[error("Resource quota exceeded: {reason}.")]
+    #[status(221)]
+    QuotaExceeded { reason: String },
+
     #[error("Workflow internal error: {source}")]
     #[status(500)]
     InternalError {
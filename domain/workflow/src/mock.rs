@@ -1,5 +1,6 @@
 use alice_architecture::repository::{DBRepository, MutableRepository, ReadOnlyRepository};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use mockall::mock;
 use uuid::Uuid;
 
@@ -44,6 +45,10 @@ mock! {
         ) -> anyhow::Result<Vec<NodeInstance>>;
         async fn get_nth_of_batch_tasks(&self, sub_node_id: Uuid) -> anyhow::Result<usize>;
         async fn get_node_spec(&self, node_id: Uuid) -> anyhow::Result<NodeSpec>;
+        async fn get_running_nodes_by_queue_ids(
+            &self,
+            queue_ids: &[Uuid],
+        ) -> anyhow::Result<Vec<NodeInstance>>;
         async fn update_immediately_with_lock(&self, entity: DbNodeInstance) -> anyhow::Result<()>;
     }
     impl DBRepository<NodeInstance> for NodeInstanceRepo {}
@@ -62,6 +67,8 @@ mock! {
     impl TaskRepo for TaskRepo {
         async fn get_same_node_tasks(&self, task_id: Uuid) -> anyhow::Result<Vec<Task>>;
         async fn get_tasks_by_node_id(&self, node_id: Uuid) -> anyhow::Result<Vec<Task>>;
+        async fn delete_by_node_id(&self, node_id: Uuid) -> anyhow::Result<()>;
+        async fn take_due_retries(&self, before: DateTime<Utc>) -> anyhow::Result<Vec<Uuid>>;
     }
     impl DBRepository<Task> for TaskRepo {}
     impl ReadOnlyRepository<Task> for TaskRepo {}
@@ -106,6 +106,8 @@ pub enum NodeInstanceKind {
     /// 脚本
     Script,
     Milestone,
+    /// 条件分支
+    Condition,
 }
 
 #[derive(FromPrimitive, ToPrimitive, Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
@@ -145,6 +147,9 @@ pub enum NodeInstanceStatus {
     /// # 正在恢复
     /// 作业实例的处理过程正在恢复
     Resuming,
+    /// # 已跳过
+    /// 作业实例所在的条件分支未被激活，不会被处理
+    Skipped,
 }
 
 impl TryFrom<node_instance::Model> for NodeInstance {
@@ -189,6 +194,7 @@ impl From<NodeKind> for NodeInstanceKind {
             NodeKind::NoAction => Self::NoAction,
             NodeKind::Script { .. } => Self::Script,
             NodeKind::Milestone { .. } => Self::Milestone,
+            NodeKind::Condition { .. } => Self::Condition,
         }
     }
 }
@@ -1,14 +1,10 @@
-use anyhow::{anyhow, bail};
+use anyhow::bail;
 use database_model::queue;
-use std::collections::HashMap;
 
 use alice_architecture::model::AggregateRoot;
-use once_cell::sync::Lazy;
-use tokio::sync::Mutex;
 use uuid::Uuid;
 
-pub static QUEUE_ID_TO_CACHE_INFO: Lazy<Mutex<HashMap<Uuid, QueueCacheInfo>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
+use crate::model::vo::Requirements;
 
 #[derive(Debug, Clone, Default, AggregateRoot)]
 pub struct Queue {
@@ -87,57 +83,112 @@ pub struct QueueResourceUsed {
     pub node_number_used: i64,
 }
 
+impl QueueResourceUsed {
+    /// Resources reserved on a queue for a node with the requirements, one core on one node when
+    /// not specified. Memory and storage are not required by nodes, so they are not reserved.
+    pub fn of_requirements(requirements: Option<&Requirements>) -> Self {
+        Self {
+            core_number_used: requirements.and_then(|el| el.cpu_cores).unwrap_or(1) as i64,
+            node_number_used: requirements.and_then(|el| el.node_count).unwrap_or(1) as i64,
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct QueueTaskCount {
     pub queuing_task_count: i64,
     pub running_task_count: i64,
 }
 
-impl Queue {
-    pub async fn update_resource(queue_id: Uuid, resource: &QueueCacheInfo) {
-        let mut queue_id_to_cache_info = QUEUE_ID_TO_CACHE_INFO.lock().await;
-        let old = queue_id_to_cache_info.entry(queue_id).or_insert(QueueCacheInfo::default());
-        *old = resource.to_owned();
-    }
+/// Why a queue is not selected.
+#[derive(Clone, Debug)]
+pub struct QueueRejection {
+    pub queue_id: Uuid,
+    pub queue_name: Option<String>,
+    pub reason: String,
+    /// The queue fits but is full for now, so it may be selected after its capacity is freed.
+    pub full: bool,
+}
 
-    pub async fn cache_resource(queue: &Queue) -> anyhow::Result<()> {
-        let mut queue_id_to_cache_info = QUEUE_ID_TO_CACHE_INFO.lock().await;
-        let old = queue_id_to_cache_info.entry(queue.id).or_insert(QueueCacheInfo::default());
-        old.cache(queue)
-    }
+/// Result of queue selection.
+#[derive(Clone, Debug, Default)]
+pub struct QueueSelection {
+    /// Selected queue, None when no queue is available.
+    pub queue: Option<Queue>,
+    /// Rejected queues in the order they are considered.
+    pub rejections: Vec<QueueRejection>,
+}
 
-    pub async fn release_resource(queue_id: Uuid, resource: &QueueResourceUsed) {
-        let mut queue_id_to_cache_info = QUEUE_ID_TO_CACHE_INFO.lock().await;
-        let old = queue_id_to_cache_info.entry(queue_id).or_insert(QueueCacheInfo::default());
-        old.release(resource);
+impl std::fmt::Display for QueueRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.queue_name {
+            Some(name) => write!(f, "queue {name}({}): {}", self.queue_id, self.reason),
+            None => write!(f, "queue {}: {}", self.queue_id, self.reason),
+        }
     }
+}
 
-    pub async fn task_started(queue: &Queue) -> anyhow::Result<()> {
-        let mut queue_id_to_cache_info = QUEUE_ID_TO_CACHE_INFO.lock().await;
-        let old = queue_id_to_cache_info.entry(queue.id).or_insert(QueueCacheInfo::default());
-        old.start_one(queue.max_running_task_count)
+impl QueueSelection {
+    /// Human readable message explaining the selection.
+    pub fn message(&self) -> String {
+        let rejections =
+            self.rejections.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ");
+        match &self.queue {
+            Some(queue) if rejections.is_empty() => format!("Selected queue {}.", queue.name),
+            Some(queue) => format!("Selected queue {}, rejected {rejections}.", queue.name),
+            None if rejections.is_empty() => "No queue available.".to_string(),
+            None => format!("No queue available, rejected {rejections}."),
+        }
     }
 
-    pub async fn is_resource_full(queue: &Queue) -> anyhow::Result<()> {
-        let queue_id_to_cache_info = QUEUE_ID_TO_CACHE_INFO.lock().await;
-        let cache_info = queue_id_to_cache_info.get(&queue.id);
-        match cache_info {
-            Some(el) => el.is_full(queue),
-            None => Ok(()),
-        }
+    /// Test if no queue is selected only because the fitting queues are full for now.
+    pub fn can_wait(&self) -> bool {
+        self.queue.is_none() && self.rejections.iter().any(|el| el.full)
     }
+}
 
-    pub async fn get_cache_info(queue_id: Uuid) -> anyhow::Result<QueueCacheInfo> {
-        let queue_id_to_cache_info = QUEUE_ID_TO_CACHE_INFO.lock().await;
-        let cache_info = queue_id_to_cache_info.get(&queue_id).ok_or(anyhow!(
-            "queue id {} not found in queue id to cache info",
-            queue_id
-        ))?;
-        Ok(cache_info.to_owned())
+impl Queue {
+    /// Test if the queue can physically run a job with the requirements, Err with the reason
+    /// when it can't.
+    pub fn fits(&self, requirements: Option<&Requirements>) -> anyhow::Result<()> {
+        let requirements = match requirements {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        if let Some(cpu_cores) = requirements.cpu_cores {
+            if cpu_cores as i64 > self.core_number {
+                bail!(
+                    "queue core number {} is less than required {}",
+                    self.core_number,
+                    cpu_cores
+                );
+            }
+        }
+        if let Some(node_count) = requirements.node_count {
+            if node_count as i64 > self.node_count {
+                bail!(
+                    "queue node count {} is less than required {}",
+                    self.node_count,
+                    node_count
+                );
+            }
+        }
+        Ok(())
     }
 }
 
 impl QueueCacheInfo {
+    /// Test if any resource or task slot is freed compared with the old info.
+    pub fn is_freed_from(&self, old: &QueueCacheInfo) -> bool {
+        self.used.memory_used < old.used.memory_used
+            || self.used.core_number_used < old.used.core_number_used
+            || self.used.storage_capacity_used < old.used.storage_capacity_used
+            || self.used.node_number_used < old.used.node_number_used
+            || self.task_count.queuing_task_count < old.task_count.queuing_task_count
+            || self.task_count.running_task_count < old.task_count.running_task_count
+    }
+
     pub fn is_full(&self, queue: &Queue) -> anyhow::Result<()> {
         if let Some(memory_alert) = queue.memory_alert {
             if self.used.memory_used >= memory_alert {
@@ -171,96 +222,4 @@ impl QueueCacheInfo {
         }
         Ok(())
     }
-
-    pub fn cache(&mut self, queue: &Queue) -> anyhow::Result<()> {
-        let cache_info = queue.cache_info.as_ref().ok_or(anyhow!("queue cache info is none"))?;
-        let used = &cache_info.used;
-
-        let memory_alert = queue.memory_alert;
-        let core_number_alert = queue.core_number_alert;
-        let storage_capacity_alert = queue.storage_capacity_alert;
-        let max_node_count = queue.max_node_count;
-        let max_queuing_task_count = queue.max_queuing_task_count;
-
-        if let Some(memory_alert) = memory_alert {
-            let new = self.used.memory_used + used.memory_used;
-            if new >= memory_alert {
-                bail!(
-                    "queue memory used {} is greater than memory alert {}",
-                    new,
-                    memory_alert
-                );
-            }
-            self.used.memory_used = new;
-        }
-        if let Some(core_number_alert) = core_number_alert {
-            let new = self.used.core_number_used + used.core_number_used;
-            if new >= core_number_alert {
-                bail!(
-                    "queue core number used {} is greater than core number alert {}",
-                    new,
-                    core_number_alert
-                );
-            }
-            self.used.core_number_used = new;
-        }
-        if let Some(storage_capacity_alert) = storage_capacity_alert {
-            let new = self.used.storage_capacity_used + used.storage_capacity_used;
-            if new >= storage_capacity_alert {
-                bail!(
-                    "queue storage capacity used {} is greater than storage capacity alert {}",
-                    new,
-                    storage_capacity_alert
-                );
-            }
-            self.used.storage_capacity_used = new;
-        }
-        if let Some(max_node_count) = max_node_count {
-            let new = self.used.node_number_used + used.node_number_used;
-            if new >= max_node_count {
-                bail!(
-                    "queue node number used {} is greater than max node count {}",
-                    new,
-                    max_node_count
-                );
-            }
-            self.used.node_number_used = new;
-        }
-        if let Some(max_queuing_task_count) = max_queuing_task_count {
-            let new = self.task_count.queuing_task_count + 1;
-            if new >= max_queuing_task_count {
-                bail!(
-                    "queue queuing task count {} is greater than max queuing task count {}",
-                    new,
-                    max_queuing_task_count
-                );
-            }
-            self.task_count.queuing_task_count = new;
-        }
-        Ok(())
-    }
-
-    pub fn release(&mut self, resource: &QueueResourceUsed) {
-        self.used.memory_used -= resource.memory_used;
-        self.used.core_number_used -= resource.core_number_used;
-        self.used.storage_capacity_used -= resource.storage_capacity_used;
-        self.used.node_number_used -= resource.node_number_used;
-        self.task_count.running_task_count -= 1;
-    }
-
-    pub fn start_one(&mut self, max_running_task_count: Option<i64>) -> anyhow::Result<()> {
-        self.task_count.queuing_task_count -= 1;
-        if let Some(max_running_task_count) = max_running_task_count {
-            let new = self.task_count.running_task_count + 1;
-            if new >= max_running_task_count {
-                bail!(
-                    "queue running task count {} is greater than max running task count {}",
-                    new,
-                    max_running_task_count
-                );
-            }
-            self.task_count.running_task_count = new;
-        }
-        Ok(())
-    }
 }
@@ -1,5 +1,7 @@
 use alice_architecture::model::AggregateRoot;
+use chrono::{DateTime, Utc};
 use num_derive::{FromPrimitive, ToPrimitive};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::model::vo::task_dto::{self, result::TaskResultStatus, StartTaskBody};
@@ -15,13 +17,30 @@ pub struct Task {
     pub message: Option<String>,
     pub used_resources: Option<serde_json::Value>,
     pub queue_topic: String,
+    /// Failed attempts history, the last one is the latest.
+    pub attempts: Vec<TaskAttempt>,
+    /// When the task is retried after backoff, none if no retry is pending.
+    pub next_attempt_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Default, ToPrimitive, FromPrimitive, Debug, Clone, PartialEq)]
+/// A failed attempt of a task.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskAttempt {
+    /// The queue topic the task was dispatched to.
+    pub queue_topic: String,
+    /// Failure message.
+    pub message: Option<String>,
+    /// When the attempt failed.
+    pub failed_time: DateTime<Utc>,
+}
+
+#[derive(Default, ToPrimitive, FromPrimitive, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum TaskType {
     #[default]
     DeploySoftware,
     DownloadFile,
+    #[serde(rename = "ExecuteUsecase")]
     ExeceteUsecase,
     UploadFile,
     CollectOutput,
@@ -91,4 +110,9 @@ impl TaskType {
             StartTaskBody::ExecuteScript(_) => Self::ExecuteScript,
         }
     }
+
+    /// Whether the task can be paused and resumed on agent, so that its node can be preempted.
+    pub fn is_pausable(&self) -> bool {
+        matches!(self, TaskType::ExeceteUsecase | TaskType::ExecuteScript)
+    }
 }
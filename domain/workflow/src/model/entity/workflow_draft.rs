@@ -28,6 +28,8 @@ pub struct WorkflowDraft {
     pub logo: Option<String>,
     /// 工作流草稿数据
     pub spec: WorkflowDraftSpec,
+    /// 所属项目 id
+    pub project_id: Option<Uuid>,
 }
 
 /// 工作流草稿 spec 数据
@@ -37,11 +39,17 @@ pub struct WorkflowDraftSpec {
     /// 调度策略
     #[serde(default)]
     pub scheduling_strategy: SchedulingStrategy,
+    /// 优先级，越大越先调度，必要时抢占低优先级工作流的节点
+    #[serde(default)]
+    pub priority: i32,
     /// 节点草稿列表
     pub node_drafts: Vec<NodeDraft>,
     /// 节点草稿关系列表
     #[serde(default)]
     pub node_relations: Vec<NodeRelation>,
+    /// 循环列表
+    #[serde(default)]
+    pub loops: Vec<NodeLoop>,
     /// 其他字段
     pub additional_data: Option<HashMap<String, Value>>,
 }
@@ -69,6 +77,12 @@ pub struct NodeDraft {
     pub scheduling_strategy: SchedulingStrategy,
     /// 资源需求覆盖（若没有则采取用例包规定的）
     pub requirements: Option<Requirements>,
+    /// 任务失败时的重试策略（若没有则不重试）
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    /// 优先级覆盖（若没有则采取工作流的）
+    #[serde(default)]
+    pub priority: Option<i32>,
     /// 其他字段
     pub additional_data: Option<HashMap<String, Value>>,
 }
@@ -119,7 +133,7 @@ impl TryFrom<flow_draft::Model> for WorkflowDraft {
             created_time: _,
             last_modified_time: _,
             r#type: _,
-            project_id: _,
+            project_id,
         } = model;
 
         Ok(Self {
@@ -128,6 +142,7 @@ impl TryFrom<flow_draft::Model> for WorkflowDraft {
             description,
             logo,
             spec: serde_json::from_value(spec)?,
+            project_id,
         })
     }
 }
@@ -154,7 +169,22 @@ impl WorkflowDraftSpec {
     /// 1. 节点依赖中提及的节点必须存在
     /// 2. 插槽依赖中提及的插槽必须存在
     /// 3. 文本输出只能对应文本输入，文件输出只能对应文件输入
+    /// 4. 条件节点分支激活的节点必须是它的出节点
     pub async fn validate_related_nodes(&self) -> WorkflowResult<Vec<String>> {
+        for node_draft in self.node_drafts.iter() {
+            if let NodeKind::Condition { data } = &node_draft.kind {
+                let node_id = node_draft.external_id;
+                for to_id in data.branch_to_ids() {
+                    if !self
+                        .node_relations
+                        .iter()
+                        .any(|el| el.from_id.eq(&node_id) && el.to_id.eq(&to_id))
+                    {
+                        return Err(WorkflowException::NoSuchConditionBranch { node_id, to_id });
+                    }
+                }
+            }
+        }
         let mut relied_input_slots = vec![];
         for node_relation in self.node_relations.iter() {
             let from_node_id = node_relation.from_id.to_owned();
@@ -224,6 +254,32 @@ impl WorkflowDraftSpec {
         Ok(relied_input_slots)
     }
 
+    /// 循环中提及的节点必须存在，终止条件节点与反馈关系中的节点必须在循环体中，
+    /// 最大迭代次数必须大于零
+    pub fn validate_loops(&self) -> WorkflowResult<()> {
+        for node_loop in self.loops.iter() {
+            if node_loop.max_iterations == 0 {
+                return Err(WorkflowException::ZeroLoopMaxIterations);
+            }
+            for node_id in node_loop.node_ids.iter() {
+                self.get_node(*node_id).ok_or(WorkflowException::NoSuchNode {
+                    id: node_id.to_owned(),
+                })?;
+            }
+            let referenced_node_ids = node_loop
+                .feedbacks
+                .iter()
+                .flat_map(|el| [el.from_id, el.to_id])
+                .chain([node_loop.until.node_id]);
+            for node_id in referenced_node_ids {
+                if !node_loop.node_ids.contains(&node_id) {
+                    return Err(WorkflowException::NodeNotInLoopBody { node_id });
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// 5. MatchRegex 类型批量输入必须等于 1
     /// 6. 调度策略 Manual 和 Prefer 至少选一个队列
     pub async fn validate_per_node(
@@ -256,17 +312,17 @@ impl WorkflowDraftSpec {
             }
             let mut flag = true;
             match &node_draft.scheduling_strategy {
-                SchedulingStrategy::Manual { queues } => {
+                SchedulingStrategy::Manual { queues, .. } => {
                     if queues.is_empty() {
                         flag = false
                     }
                 }
-                SchedulingStrategy::Prefer { queues } => {
+                SchedulingStrategy::Prefer { queues, .. } => {
                     if queues.is_empty() {
                         flag = false
                     }
                 }
-                SchedulingStrategy::Auto => {}
+                SchedulingStrategy::Auto { .. } => {}
             }
             if !flag {
                 return Err(WorkflowException::AtLeastOneQueue);
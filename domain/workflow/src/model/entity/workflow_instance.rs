@@ -38,6 +38,8 @@ pub struct WorkflowInstance {
     /// user_id.
     /// Use to get user id via task id: Get node_instance_id then get workflow_instance, then get
     pub user_id: Uuid,
+    /// 所属项目 id
+    pub project_id: Option<Uuid>,
 }
 
 /// 工作流实例规格
@@ -46,10 +48,16 @@ pub struct WorkflowInstance {
 pub struct WorkflowInstanceSpec {
     /// 调度策略
     pub scheduling_strategy: SchedulingStrategy,
+    /// 优先级，越大越先调度，必要时抢占低优先级工作流的节点
+    #[serde(default)]
+    pub priority: i32,
     /// 节点实例信息列表
     pub node_specs: Vec<NodeSpec>,
     /// 节点实例关系列表
     pub node_relations: Vec<NodeRelation>,
+    /// 循环列表
+    #[serde(default)]
+    pub loops: Vec<NodeLoop>,
     /// 其他字段
     #[serde(default)]
     pub additional_data: Option<HashMap<String, Value>>,
@@ -79,6 +87,12 @@ pub struct NodeSpec {
     /// 资源需求覆盖（若没有则采取用例包规定的）
     #[serde(default)]
     pub requirements: Option<Requirements>,
+    /// 任务失败时的重试策略（若没有则不重试）
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    /// 优先级覆盖（若没有则采取工作流的）
+    #[serde(default)]
+    pub priority: Option<i32>,
     /// 其他字段
     #[serde(default)]
     pub additional_data: Option<HashMap<String, Value>>,
@@ -175,7 +189,7 @@ impl TryFrom<flow_instance::Model> for WorkflowInstance {
             user_id,
             created_time: _,
             last_modified_time,
-            project_id: _,
+            project_id,
         } = model;
 
         Ok(Self {
@@ -187,6 +201,7 @@ impl TryFrom<flow_instance::Model> for WorkflowInstance {
             spec: serde_json::from_value(spec)?,
             last_modified_time,
             user_id,
+            project_id,
         })
     }
 }
@@ -206,6 +221,8 @@ impl From<NodeDraft> for NodeSpec {
             scheduling_strategy: l.scheduling_strategy,
             kind: l.kind,
             requirements: l.requirements,
+            retry_policy: l.retry_policy,
+            priority: l.priority,
             additional_data: l.additional_data,
         }
     }
@@ -248,6 +265,7 @@ impl From<WorkflowDraft> for WorkflowInstance {
             status: WorkflowInstanceStatus::Created,
             spec: WorkflowInstanceSpec::from(l.spec),
             last_modified_time: chrono::DateTime::default(),
+            project_id: l.project_id,
             ..Default::default()
         }
     }
@@ -257,9 +275,14 @@ impl From<WorkflowDraftSpec> for WorkflowInstanceSpec {
     fn from(l: WorkflowDraftSpec) -> Self {
         let old_node_ids =
             l.node_drafts.iter().map(|el| el.external_id.to_owned()).collect::<Vec<_>>();
-        let node_specs = l.node_drafts.into_iter().map(NodeSpec::from).collect::<Vec<_>>();
+        let mut node_specs = l.node_drafts.into_iter().map(NodeSpec::from).collect::<Vec<_>>();
         let new_node_ids = node_specs.iter().map(|el| el.id).collect::<Vec<_>>();
         let old_new_id_map = old_node_ids.into_iter().zip(new_node_ids).collect::<HashMap<_, _>>();
+        for node_spec in node_specs.iter_mut() {
+            if let NodeKind::Condition { data } = &mut node_spec.kind {
+                data.update_id(&old_new_id_map);
+            }
+        }
         let node_relations = l
             .node_relations
             .into_iter()
@@ -268,10 +291,20 @@ impl From<WorkflowDraftSpec> for WorkflowInstanceSpec {
                 el
             })
             .collect::<Vec<_>>();
+        let loops = l
+            .loops
+            .into_iter()
+            .map(|mut el| {
+                el.update_id(&old_new_id_map);
+                el
+            })
+            .collect::<Vec<_>>();
         Self {
             scheduling_strategy: l.scheduling_strategy,
+            priority: l.priority,
             node_specs,
             node_relations,
+            loops,
             additional_data: l.additional_data,
         }
     }
@@ -305,6 +338,148 @@ impl WorkflowInstance {
             .collect::<Vec<_>>()
     }
 
+    /// 计算条件节点选择分支后需要跳过的节点 id 列表
+    /// 一个节点的所有入依赖都来自已跳过的节点或条件节点未激活的分支时，该节点被跳过
+    ///
+    /// # 参数
+    ///
+    /// * `condition_node_id` - 条件节点 id
+    /// * `inactive_to_ids` - 条件节点未激活的出节点 id
+    /// * `skipped_nodes_ids` - 已经被跳过的节点 id
+    pub fn nodes_to_skip(
+        &self,
+        condition_node_id: Uuid,
+        inactive_to_ids: &[Uuid],
+        skipped_nodes_ids: &[Uuid],
+    ) -> Vec<Uuid> {
+        let mut skipped = skipped_nodes_ids.to_vec();
+        let mut result = vec![];
+        loop {
+            let is_inactive = |el: &NodeRelation| {
+                skipped.contains(&el.from_id)
+                    || (el.from_id.eq(&condition_node_id) && inactive_to_ids.contains(&el.to_id))
+            };
+            let newly_skipped = self
+                .spec
+                .node_specs
+                .iter()
+                .map(|el| el.id)
+                .filter(|id| !skipped.contains(id))
+                .filter(|id| {
+                    let relations = self.node_dependency_relations(*id);
+                    !relations.is_empty() && relations.iter().all(is_inactive)
+                })
+                .collect::<Vec<_>>();
+            if newly_skipped.is_empty() {
+                break;
+            }
+            skipped.extend_from_slice(&newly_skipped);
+            result.extend(newly_skipped);
+        }
+        result
+    }
+
+    /// 从失败处重试工作流时需要重置的节点 id 列表，即失败的节点及其所有下游节点
+    ///
+    /// # 参数
+    ///
+    /// * `failed_nodes_ids` - 失败或被终止的节点 id
+    pub fn nodes_to_retry(&self, failed_nodes_ids: &[Uuid]) -> Vec<Uuid> {
+        let mut result = failed_nodes_ids.to_vec();
+        let mut i = 0;
+        while i < result.len() {
+            let from_id = result[i];
+            for relation in self.spec.node_relations.iter().filter(|el| el.from_id.eq(&from_id)) {
+                if !result.contains(&relation.to_id) {
+                    result.push(relation.to_id);
+                }
+            }
+            i += 1;
+        }
+        result
+    }
+
+    /// 生成循环下一次迭代的节点规格，并更新节点关系与循环信息
+    /// 1. 循环体的节点规格被克隆为新节点，输出插槽重新分配
+    /// 2. 循环体内的关系与循环体外指向循环体的关系复制到新节点上，由反馈关系提供的输入插槽除外
+    /// 3. 循环体指向循环体外的关系改为由新节点发出
+    /// 4. 反馈关系由本次迭代的节点指向新节点
+    ///
+    /// 返回新的节点规格
+    ///
+    /// # 参数
+    ///
+    /// * `loop_index` - 循环在工作流实例规格中的序号
+    pub fn next_loop_iteration(&mut self, loop_index: usize) -> anyhow::Result<Vec<NodeSpec>> {
+        let node_loop = self
+            .spec
+            .loops
+            .get(loop_index)
+            .ok_or(anyhow!("There is no loop with index: {loop_index}"))?
+            .to_owned();
+        let body_node_ids = &node_loop.node_ids;
+        let id_map =
+            body_node_ids.iter().map(|el| (*el, Uuid::new_v4())).collect::<HashMap<_, _>>();
+
+        let mut new_node_specs = vec![];
+        for node_id in body_node_ids.iter() {
+            let mut node_spec = self.spec.node(*node_id).to_owned();
+            node_spec.id = id_map.get(node_id).unwrap().to_owned();
+            node_spec.update_output_slots();
+            if let NodeKind::Condition { data } = &mut node_spec.kind {
+                data.update_id(&id_map);
+            }
+            new_node_specs.push(node_spec);
+        }
+
+        // 由反馈关系提供输入的插槽
+        let fed_slots = node_loop
+            .feedbacks
+            .iter()
+            .flat_map(|el| el.slot_relations.iter().map(|el2| (el.to_id, el2.to_slot.as_str())))
+            .collect::<Vec<_>>();
+        let mut new_node_relations = vec![];
+        for node_relation in self.spec.node_relations.iter_mut() {
+            let is_from_body = body_node_ids.contains(&node_relation.from_id);
+            let is_to_body = body_node_ids.contains(&node_relation.to_id);
+            match (is_from_body, is_to_body) {
+                (true, true) => {
+                    let mut new_node_relation = node_relation.to_owned();
+                    new_node_relation.update_id(&id_map);
+                    new_node_relations.push(new_node_relation);
+                }
+                (false, true) => {
+                    let mut new_node_relation = node_relation.to_owned();
+                    new_node_relation.slot_relations.retain(|el| {
+                        !fed_slots.contains(&(node_relation.to_id, el.to_slot.as_str()))
+                    });
+                    if node_relation.slot_relations.is_empty()
+                        || !new_node_relation.slot_relations.is_empty()
+                    {
+                        new_node_relation.update_id(&id_map);
+                        new_node_relations.push(new_node_relation);
+                    }
+                }
+                (true, false) => node_relation.update_id(&id_map),
+                (false, false) => {}
+            }
+        }
+        for feedback in node_loop.feedbacks.iter() {
+            new_node_relations.push(NodeRelation {
+                from_id: feedback.from_id,
+                to_id: id_map.get(&feedback.to_id).unwrap().to_owned(),
+                slot_relations: feedback.slot_relations.to_owned(),
+            });
+        }
+
+        self.spec.node_relations.extend(new_node_relations);
+        self.spec.node_specs.extend_from_slice(&new_node_specs);
+        let node_loop = self.spec.loops.get_mut(loop_index).unwrap();
+        node_loop.update_id(&id_map);
+        node_loop.iteration += 1;
+        Ok(new_node_specs)
+    }
+
     /// 填充被提供依赖的节点的所有输入插槽产生任务节点，如果是批量节点，提供使用批量节点的子节点输出次序
     ///
     /// # 参数
@@ -387,6 +562,14 @@ impl NodeSpecOutputSlot {
 }
 
 impl NodeSpec {
+    /// 取得生效的资源需求，节点没有覆盖时采取提交时用例包规定的
+    pub fn effective_requirements(&self) -> Option<&Requirements> {
+        self.requirements.as_ref().or(match &self.kind {
+            NodeKind::SoftwareUsecaseComputing { data } => data.package_requirements.as_ref(),
+            _ => None,
+        })
+    }
+
     /// 由任务输入数据更新输入插槽列表
     /// # 参数
     ///
@@ -483,6 +666,30 @@ impl NodeSpec {
         }
         result
     }
+
+    /// 得到节点上所有输入插槽的文件 id 与输出插槽预分配的文件 id
+    pub fn file_ids(&self) -> Vec<Uuid> {
+        let mut result = vec![];
+        for input_slot in self.input_slots.iter() {
+            if let NodeInputSlotKind::File {
+                contents: Some(contents),
+                ..
+            } = &input_slot.kind
+            {
+                result.extend(contents.iter().map(|el| el.file_metadata_id));
+            }
+        }
+        for output_slot in self.output_slots.iter() {
+            if let NodeSpecOutputSlotKind::File {
+                all_tasks_prepared_content_ids,
+                ..
+            } = &output_slot.kind
+            {
+                result.extend_from_slice(all_tasks_prepared_content_ids);
+            }
+        }
+        result
+    }
 }
 
 impl WorkflowInstanceSpec {
@@ -503,6 +710,27 @@ impl WorkflowInstanceSpec {
     pub fn node_mut(&mut self, id: Uuid) -> &mut NodeSpec {
         self.node_specs.iter_mut().find(|el| el.id.eq(&id)).unwrap()
     }
+
+    /// 取得节点的优先级，节点没有覆盖时采取工作流的
+    ///
+    /// # 参数：
+    ///
+    /// * `id` - 节点 id
+    pub fn node_priority(&self, id: Uuid) -> i32 {
+        self.node_specs
+            .iter()
+            .find(|el| el.id.eq(&id))
+            .and_then(|el| el.priority)
+            .unwrap_or(self.priority)
+    }
+
+    /// 得到所有节点使用的文件 id，去重
+    pub fn file_ids(&self) -> Vec<Uuid> {
+        let mut result = self.node_specs.iter().flat_map(NodeSpec::file_ids).collect::<Vec<_>>();
+        result.sort();
+        result.dedup();
+        result
+    }
 }
 
 impl WorkflowInstance {
@@ -557,34 +785,192 @@ impl WorkflowInstance {
             .spec
             .node_specs
             .iter()
-            .map(|node_spec| {
-                let mut node_instances = vec![];
-                let root_instance = NodeInstance {
-                    kind: NodeInstanceKind::from(node_spec.kind.to_owned()),
-                    id: node_spec.id.to_owned(),
-                    name: node_spec.name.to_owned(),
-                    is_parent: !node_spec.batch_strategies.is_empty(),
-                    flow_instance_id: self.id.to_owned(),
-                    ..Default::default()
-                };
-
-                node_instances.push(root_instance.to_owned());
-                if root_instance.is_parent {
-                    let count = self.sub_node_count(node_spec.id);
-                    for i in 0..count {
-                        node_instances.push(NodeInstance {
-                            id: Uuid::new_v4(),
-                            name: format!("{}_sub_task_{}", node_spec.name, i),
-                            is_parent: false,
-                            batch_parent_id: Some(root_instance.id.to_owned()),
-                            ..root_instance.to_owned()
-                        })
-                    }
-                }
-                node_instances
-            })
+            .map(|node_spec| self.node_instances(node_spec))
             .collect::<Vec<_>>();
 
         Ok(node_instances.into_iter().flatten().collect::<Vec<_>>())
     }
+
+    /// 解析一个节点规格得到其节点实例及批量子节点实例
+    ///
+    /// # 参数
+    ///
+    /// * `node_spec` - 工作流实例规格中的节点
+    pub fn node_instances(&self, node_spec: &NodeSpec) -> Vec<NodeInstance> {
+        let mut node_instances = vec![];
+        let root_instance = NodeInstance {
+            kind: NodeInstanceKind::from(node_spec.kind.to_owned()),
+            id: node_spec.id.to_owned(),
+            name: node_spec.name.to_owned(),
+            is_parent: !node_spec.batch_strategies.is_empty(),
+            flow_instance_id: self.id.to_owned(),
+            ..Default::default()
+        };
+
+        node_instances.push(root_instance.to_owned());
+        if root_instance.is_parent {
+            let count = self.sub_node_count(node_spec.id);
+            for i in 0..count {
+                node_instances.push(NodeInstance {
+                    id: Uuid::new_v4(),
+                    name: format!("{}_sub_task_{}", node_spec.name, i),
+                    is_parent: false,
+                    batch_parent_id: Some(root_instance.id.to_owned()),
+                    ..root_instance.to_owned()
+                })
+            }
+        }
+        node_instances
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_loop_iteration() {
+        let (init, solve, check, after) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        let relation = |from_id, to_id, from_slot: &str, to_slot: &str| NodeRelation {
+            from_id,
+            to_id,
+            slot_relations: vec![SlotRelation {
+                from_slot: from_slot.to_string(),
+                to_slot: to_slot.to_string(),
+                ..Default::default()
+            }],
+        };
+        let node_spec = |id| NodeSpec {
+            id,
+            ..Default::default()
+        };
+        let mut flow = WorkflowInstance {
+            spec: WorkflowInstanceSpec {
+                node_specs: vec![
+                    node_spec(init),
+                    node_spec(solve),
+                    node_spec(check),
+                    node_spec(after),
+                ],
+                node_relations: vec![
+                    relation(init, solve, "density", "density"),
+                    relation(solve, check, "energy", "energy"),
+                    relation(check, after, "result", "result"),
+                ],
+                loops: vec![NodeLoop {
+                    node_ids: vec![solve, check],
+                    until: LoopUntil {
+                        node_id: check,
+                        expression: ConditionExpression::Equals {
+                            slot: "converged".to_string(),
+                            value: "true".to_string(),
+                        },
+                    },
+                    max_iterations: 10,
+                    feedbacks: vec![relation(solve, solve, "newDensity", "density")],
+                    iteration: 0,
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let new_node_specs = flow.next_loop_iteration(0).unwrap();
+        let (new_solve, new_check) = (new_node_specs[0].id, new_node_specs[1].id);
+        let node_loop = &flow.spec.loops[0];
+        assert_eq!(node_loop.node_ids, vec![new_solve, new_check]);
+        assert_eq!(node_loop.until.node_id, new_check);
+        assert_eq!(node_loop.iteration, 1);
+        assert_eq!(flow.spec.node_specs.len(), 6);
+
+        let relations = flow
+            .spec
+            .node_relations
+            .iter()
+            .map(|el| (el.from_id, el.to_id))
+            .collect::<Vec<_>>();
+        // The input fed by feedback doesn't rely on the init node any more.
+        assert!(!relations.contains(&(init, new_solve)));
+        assert!(relations.contains(&(solve, new_solve)));
+        assert!(relations.contains(&(new_solve, new_check)));
+        // Downstream node waits for the new iteration.
+        assert!(relations.contains(&(new_check, after)));
+        assert!(!relations.contains(&(check, after)));
+    }
+
+    #[test]
+    fn test_nodes_to_retry() {
+        let ids = (0..5).map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+        let relation = |from: usize, to: usize| NodeRelation {
+            from_id: ids[from],
+            to_id: ids[to],
+            ..Default::default()
+        };
+        let flow = WorkflowInstance {
+            spec: WorkflowInstanceSpec {
+                node_relations: vec![
+                    relation(0, 1),
+                    relation(0, 2),
+                    relation(1, 3),
+                    relation(2, 3),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut to_retry = flow.nodes_to_retry(&[ids[1]]);
+        to_retry.sort();
+        let mut expected = vec![ids[1], ids[3]];
+        expected.sort();
+        assert_eq!(to_retry, expected);
+        assert_eq!(flow.nodes_to_retry(&[ids[4]]), vec![ids[4]]);
+    }
+
+    #[test]
+    fn test_file_ids() {
+        let (input, output) = (Uuid::new_v4(), Uuid::new_v4());
+        let file_input = |file_metadata_id| FileInput {
+            file_metadata_id,
+            ..Default::default()
+        };
+        let input_slot = |contents| NodeInputSlot {
+            kind: NodeInputSlotKind::File {
+                contents,
+                expected_file_name: None,
+                is_batch: false,
+            },
+            optional: false,
+            descriptor: "density".to_string(),
+            description: None,
+        };
+        let node_spec = NodeSpec {
+            input_slots: vec![input_slot(Some(vec![file_input(input)])), input_slot(None)],
+            output_slots: vec![NodeSpecOutputSlot {
+                kind: NodeSpecOutputSlotKind::File {
+                    origin: FileOutOrigin::UsecaseOut,
+                    is_batch: false,
+                    all_tasks_prepared_content_ids: vec![output],
+                },
+                descriptor: "energy".to_string(),
+                description: None,
+                optional: false,
+            }],
+            ..Default::default()
+        };
+        let spec = WorkflowInstanceSpec {
+            // 批量子节点复制了父节点的输入
+            node_specs: vec![node_spec.clone(), node_spec],
+            ..Default::default()
+        };
+
+        let mut expected = vec![input, output];
+        expected.sort();
+        assert_eq!(spec.file_ids(), expected);
+    }
 }
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 调度等待队列中的节点
+/// 没有队列有空闲资源时，节点保持 Pending 状态在此等待
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BacklogEntry {
+    /// 节点实例 id
+    pub node_id: Uuid,
+    /// 优先级，越大越先调度
+    pub priority: i32,
+    /// 进入等待队列的时间，同优先级时越早越先调度
+    pub enqueued_time: DateTime<Utc>,
+}
+
+impl BacklogEntry {
+    /// 创建当前时间进入等待队列的节点
+    ///
+    /// # 参数
+    ///
+    /// * `node_id` - 节点实例 id
+    /// * `priority` - 优先级
+    pub fn new(node_id: Uuid, priority: i32) -> Self {
+        Self {
+            node_id,
+            priority,
+            enqueued_time: Utc::now(),
+        }
+    }
+
+    /// 按调度顺序排序，优先级高的在前，同优先级等待久的在前
+    ///
+    /// # 参数
+    ///
+    /// * `entries` - 等待中的节点
+    pub fn sort(entries: &mut [Self]) {
+        entries.sort_by(|a, b| {
+            b.priority
+                .cmp(&a.priority)
+                .then(a.enqueued_time.cmp(&b.enqueued_time))
+                .then(a.node_id.cmp(&b.node_id))
+        });
+    }
+}
+
+/// 被抢占暂停的节点
+/// 抢占它的节点结束后恢复运行
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Preemption {
+    /// 被暂停的节点实例 id
+    pub node_id: Uuid,
+    /// 抢占它的节点实例 id
+    pub for_node_id: Uuid,
+    /// 被暂停的时间
+    pub preempted_time: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_backlog_sort() {
+        let now = Utc::now();
+        let entry = |priority, age_secs| BacklogEntry {
+            node_id: Uuid::new_v4(),
+            priority,
+            enqueued_time: now - Duration::seconds(age_secs),
+        };
+        let young_low = entry(0, 10);
+        let old_low = entry(0, 100);
+        let young_high = entry(5, 1);
+        let mut entries = vec![young_low.clone(), young_high.clone(), old_low.clone()];
+        BacklogEntry::sort(&mut entries);
+        assert_eq!(entries, vec![young_high, old_low, young_low]);
+    }
+}
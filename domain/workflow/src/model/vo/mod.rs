@@ -1,23 +1,57 @@
+pub mod backlog;
 pub mod msg;
+pub mod quota;
 pub mod task_dto;
 
-use domain_content_repo::model::vo::abilities::common::OutValidator;
+use domain_content_repo::model::vo::{abilities::common::OutValidator, PinnedPackage};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 use uuid::Uuid;
 
+use crate::model::entity::{queue::QueueCacheInfo, task::TaskType, Queue};
+
 /// 调度策略
-#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+/// scoring 为空时采用配置中的默认队列评分策略
+#[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(tag = "type", deny_unknown_fields)]
 pub enum SchedulingStrategy {
     /// 手动指定一些队列，系统算法必须在这些里面选择
-    Manual { queues: Vec<Uuid> },
+    Manual {
+        queues: Vec<Uuid>,
+        #[serde(default)]
+        scoring: Option<QueueScoring>,
+    },
     /// 使用系统算法选择
-    #[default]
-    Auto,
+    Auto {
+        #[serde(default)]
+        scoring: Option<QueueScoring>,
+    },
     /// 手动指定一些队列，系统算法优先在这些里面选择
-    Prefer { queues: Vec<Uuid> },
+    Prefer {
+        queues: Vec<Uuid>,
+        #[serde(default)]
+        scoring: Option<QueueScoring>,
+    },
+}
+
+/// 队列评分策略，决定在可用的队列中选择哪一个
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum QueueScoring {
+    /// 随机选择
+    #[default]
+    Random,
+    /// 选择空闲核心数最贴合需求的队列，其次选择空闲内存最多的队列
+    BestFit,
+    /// 选择排队任务最少的队列
+    LeastQueued,
+    /// 按权重轮询，未指定权重的队列权重为 1，权重为 0 的队列不会被选择，
+    /// 除非所有候选队列权重都为 0
+    WeightedRoundRobin {
+        #[serde(default)]
+        weights: HashMap<Uuid, u32>,
+    },
 }
 
 /// 节点依赖关系
@@ -71,6 +105,23 @@ pub struct Requirements {
     pub stop_time: Option<usize>,
 }
 
+impl
+    From<domain_content_repo::model::vo::abilities::software_computing::usecase::spec::Requirements>
+    for Requirements
+{
+    fn from(
+        value: domain_content_repo::model::vo::abilities::software_computing::usecase::spec::Requirements,
+    ) -> Self {
+        Self {
+            cpu_cores: value.cpu_cores,
+            node_count: value.node_count,
+            max_wall_time: value.max_wall_time,
+            max_cpu_time: value.max_cpu_time,
+            stop_time: value.stop_time,
+        }
+    }
+}
+
 /// 批量策略
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
@@ -251,6 +302,11 @@ pub enum NodeKind {
         #[serde(flatten)]
         data: Milestone,
     },
+    /// 条件分支节点
+    Condition {
+        #[serde(flatten)]
+        data: Condition,
+    },
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -307,6 +363,18 @@ pub struct SoftwareUsecaseComputing {
     pub usecase_version_id: Uuid,
     /// 软件包 id
     pub software_version_id: Uuid,
+    /// 提交时用例包内容的哈希，重新运行时使用相同内容的包
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usecase_package_hash: Option<String>,
+    /// 提交时软件包内容的哈希
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub software_package_hash: Option<String>,
+    /// 提交时解析得到的软件包依赖，依赖在依赖它的软件包之前，重新运行时使用相同的版本与内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned_dependencies: Option<Vec<PinnedPackage>>,
+    /// 提交时用例包规定的资源需求，节点没有覆盖时采取
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_requirements: Option<Requirements>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -317,17 +385,217 @@ pub struct Milestone {
     pub custom_message: String,
 }
 
+/// 条件分支
+/// 按顺序判断分支条件，只激活第一个条件成立的分支上的出节点；
+/// 未在任何分支中出现的出节点总是被激活
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Condition {
+    /// 分支列表
+    pub branches: Vec<ConditionBranch>,
+    /// 所有分支条件都不成立时激活的出节点 id 列表
+    #[serde(default)]
+    pub otherwise_to_ids: Vec<Uuid>,
+}
+
+/// 条件分支中的一个分支
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionBranch {
+    /// 条件表达式
+    pub expression: ConditionExpression,
+    /// 条件成立时激活的出节点 id 列表
+    pub to_ids: Vec<Uuid>,
+}
+
+/// 条件表达式，作用于条件节点文本输入插槽上的内容（去除首尾空白）
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+pub enum ConditionExpression {
+    /// 文本相等
+    Equals { slot: String, value: String },
+    /// 文本不相等
+    NotEquals { slot: String, value: String },
+    /// 文本包含
+    Contains { slot: String, value: String },
+    /// 文本匹配正则
+    MatchRegex { slot: String, regex: String },
+    /// 数值大于
+    GreaterThan { slot: String, value: f64 },
+    /// 数值小于
+    LessThan { slot: String, value: f64 },
+    /// 全部成立
+    And {
+        expressions: Vec<ConditionExpression>,
+    },
+    /// 任一成立
+    Or {
+        expressions: Vec<ConditionExpression>,
+    },
+    /// 取反
+    Not {
+        expression: Box<ConditionExpression>,
+    },
+}
+
+/// 循环
+/// 循环体中的节点在每次迭代时被克隆为新的节点实例，直到终止条件成立或达到最大迭代次数
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeLoop {
+    /// 当前迭代中循环体的节点 id 列表
+    pub node_ids: Vec<Uuid>,
+    /// 终止条件
+    pub until: LoopUntil,
+    /// 最大迭代次数
+    pub max_iterations: usize,
+    /// 本次迭代输出插槽到下一次迭代输入插槽的关系
+    #[serde(default)]
+    pub feedbacks: Vec<NodeRelation>,
+    /// 当前迭代的序号，从 0 开始
+    #[serde(default)]
+    pub iteration: usize,
+}
+
+/// 循环终止条件
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LoopUntil {
+    /// 判断终止条件的节点 id，该节点完成时判断是否进行下一次迭代
+    pub node_id: Uuid,
+    /// 条件表达式，作用于该节点的文本输出插槽
+    pub expression: ConditionExpression,
+}
+
+/// 任务重试策略
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// 最大尝试次数（包括第一次执行）
+    pub max_attempts: usize,
+    /// 退避策略
+    #[serde(default)]
+    pub backoff: Backoff,
+    /// 可重试的任务类型，为空时所有类型都可重试
+    #[serde(default)]
+    pub retryable_task_types: Vec<TaskType>,
+    /// 可重试的失败信息正则，为空时所有失败信息都可重试
+    #[serde(default)]
+    pub retryable_messages: Vec<String>,
+    /// 重试时是否重新选择队列
+    #[serde(default)]
+    pub reselect_queue: bool,
+}
+
+/// 重试退避策略
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum Backoff {
+    /// 固定间隔
+    #[serde(rename_all = "camelCase")]
+    Fixed { delay_secs: u64 },
+    /// 指数增长的间隔
+    #[serde(rename_all = "camelCase")]
+    Exponential {
+        initial_delay_secs: u64,
+        max_delay_secs: u64,
+    },
+}
+
 impl Default for NodeKind {
     fn default() -> Self {
         Self::SoftwareUsecaseComputing {
             data: SoftwareUsecaseComputing {
                 usecase_version_id: Uuid::default(),
                 software_version_id: Uuid::default(),
+                usecase_package_hash: None,
+                software_package_hash: None,
+                pinned_dependencies: None,
+                package_requirements: None,
             },
         }
     }
 }
 
+impl Default for SchedulingStrategy {
+    fn default() -> Self {
+        Self::Auto { scoring: None }
+    }
+}
+
+impl SchedulingStrategy {
+    /// 调度策略中指定的队列评分策略
+    pub fn scoring(&self) -> Option<&QueueScoring> {
+        match self {
+            Self::Manual { scoring, .. }
+            | Self::Auto { scoring }
+            | Self::Prefer { scoring, .. } => scoring.as_ref(),
+        }
+    }
+}
+
+impl QueueScoring {
+    /// 按评分策略从候选队列中选出一个队列
+    ///
+    /// # 参数
+    ///
+    /// * `candidates` - 满足资源需求的候选队列及其资源使用情况
+    /// * `requirements` - 计算资源需求
+    /// * `round` - 轮询计数
+    pub fn select(
+        &self,
+        mut candidates: Vec<(Queue, QueueCacheInfo)>,
+        requirements: Option<&Requirements>,
+        round: usize,
+    ) -> Option<Queue> {
+        match self {
+            Self::Random => {}
+            Self::BestFit => {
+                let cpu_cores = requirements.and_then(|el| el.cpu_cores).unwrap_or_default() as i64;
+                candidates.sort_by_key(|(queue, cache_info)| {
+                    let free_cores = queue.core_number - cache_info.used.core_number_used;
+                    let free_memory = queue.memory - cache_info.used.memory_used;
+                    (
+                        free_cores < cpu_cores,
+                        (free_cores - cpu_cores).abs(),
+                        std::cmp::Reverse(free_memory),
+                    )
+                });
+            }
+            Self::LeastQueued => candidates.sort_by_key(|(_, cache_info)| {
+                (
+                    cache_info.task_count.queuing_task_count,
+                    cache_info.task_count.running_task_count,
+                )
+            }),
+            Self::WeightedRoundRobin { weights } => {
+                let weight = |queue: &Queue| *weights.get(&queue.id).unwrap_or(&1) as usize;
+                let total_weight = candidates.iter().map(|(queue, _)| weight(queue)).sum::<usize>();
+                // 候选队列权重都为 0 时平均轮询
+                if total_weight == 0 {
+                    if candidates.is_empty() {
+                        return None;
+                    }
+                    let i = round % candidates.len();
+                    return Some(candidates.swap_remove(i).0);
+                }
+                // 第 slot 个槽位落在累计权重超过它的第一个队列上
+                let mut slot = round % total_weight;
+                let i = candidates.iter().position(|(queue, _)| {
+                    let weight = weight(queue);
+                    if slot < weight {
+                        return true;
+                    }
+                    slot -= weight;
+                    false
+                })?;
+                return Some(candidates.swap_remove(i).0);
+            }
+        }
+        candidates.into_iter().next().map(|(queue, _)| queue)
+    }
+}
+
 impl NodeRelation {
     /// 改变节点关系中的旧 id 为新 id
     ///
@@ -346,6 +614,197 @@ impl NodeRelation {
     }
 }
 
+impl Condition {
+    /// 根据输入插槽上的文本选择激活的出节点 id 列表
+    ///
+    /// # 参数
+    ///
+    /// * `texts` - 输入插槽描述符与文本内容对应关系
+    pub fn select(&self, texts: &HashMap<String, String>) -> anyhow::Result<Vec<Uuid>> {
+        for branch in self.branches.iter() {
+            if branch.expression.evaluate(texts)? {
+                return Ok(branch.to_ids.to_owned());
+            }
+        }
+        Ok(self.otherwise_to_ids.to_owned())
+    }
+
+    /// 所有分支中出现的出节点 id
+    pub fn branch_to_ids(&self) -> Vec<Uuid> {
+        let mut result = self.otherwise_to_ids.to_owned();
+        for to_id in self.branches.iter().flat_map(|el| el.to_ids.iter()) {
+            if !result.contains(to_id) {
+                result.push(to_id.to_owned());
+            }
+        }
+        result
+    }
+
+    /// 未被激活的出节点 id
+    ///
+    /// # 参数
+    ///
+    /// * `selected_to_ids` - 被激活的出节点 id
+    pub fn inactive_to_ids(&self, selected_to_ids: &[Uuid]) -> Vec<Uuid> {
+        self.branch_to_ids()
+            .into_iter()
+            .filter(|el| !selected_to_ids.contains(el))
+            .collect()
+    }
+
+    /// 改变分支中的旧 id 为新 id
+    ///
+    /// # 参数
+    ///
+    /// * `id_map` - 旧 id 与新 id 对照 map
+    pub fn update_id(&mut self, id_map: &HashMap<Uuid, Uuid>) {
+        let to_ids = self
+            .branches
+            .iter_mut()
+            .flat_map(|el| el.to_ids.iter_mut())
+            .chain(self.otherwise_to_ids.iter_mut());
+        for to_id in to_ids {
+            if let Some(new_id) = id_map.get(to_id) {
+                *to_id = new_id.to_owned();
+            }
+        }
+    }
+}
+
+impl NodeLoop {
+    /// 当前迭代完成后，判断循环是否结束
+    ///
+    /// # 参数
+    ///
+    /// * `texts` - 终止条件节点输出插槽描述符与文本内容对应关系
+    pub fn is_done(&self, texts: &HashMap<String, String>) -> anyhow::Result<bool> {
+        Ok(self.iteration + 1 >= self.max_iterations || self.until.expression.evaluate(texts)?)
+    }
+
+    /// 改变循环中的旧 id 为新 id
+    ///
+    /// # 参数
+    ///
+    /// * `id_map` - 旧 id 与新 id 对照 map
+    pub fn update_id(&mut self, id_map: &HashMap<Uuid, Uuid>) {
+        for node_id in self.node_ids.iter_mut().chain([&mut self.until.node_id]) {
+            if let Some(new_id) = id_map.get(node_id) {
+                *node_id = new_id.to_owned();
+            }
+        }
+        for feedback in self.feedbacks.iter_mut() {
+            feedback.update_id(id_map);
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 判断失败的任务是否应该重试
+    ///
+    /// # 参数
+    ///
+    /// * `attempts` - 包括本次在内已经失败的次数
+    /// * `task_type` - 任务类型
+    /// * `message` - 失败信息
+    pub fn should_retry(
+        &self,
+        attempts: usize,
+        task_type: &TaskType,
+        message: Option<&str>,
+    ) -> anyhow::Result<bool> {
+        if attempts >= self.max_attempts {
+            return Ok(false);
+        }
+        if !self.retryable_task_types.is_empty() && !self.retryable_task_types.contains(task_type) {
+            return Ok(false);
+        }
+        if self.retryable_messages.is_empty() {
+            return Ok(true);
+        }
+        let message = message.unwrap_or_default();
+        for retryable_message in self.retryable_messages.iter() {
+            if regex::Regex::new(retryable_message)?.is_match(message) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::Fixed { delay_secs: 10 }
+    }
+}
+
+impl Backoff {
+    /// 第 n 次失败后，下次尝试前的等待时间
+    ///
+    /// # 参数
+    ///
+    /// * `attempts` - 包括本次在内已经失败的次数
+    pub fn delay(&self, attempts: usize) -> Duration {
+        Duration::from_secs(match self {
+            Self::Fixed { delay_secs } => *delay_secs,
+            Self::Exponential {
+                initial_delay_secs,
+                max_delay_secs,
+            } => {
+                let exp = attempts.saturating_sub(1).min(u32::MAX as usize) as u32;
+                initial_delay_secs
+                    .saturating_mul(2_u64.saturating_pow(exp))
+                    .min(*max_delay_secs)
+            }
+        })
+    }
+}
+
+impl ConditionExpression {
+    /// 计算表达式是否成立
+    ///
+    /// # 参数
+    ///
+    /// * `texts` - 输入插槽描述符与文本内容对应关系
+    pub fn evaluate(&self, texts: &HashMap<String, String>) -> anyhow::Result<bool> {
+        let text = |slot: &str| -> anyhow::Result<&str> {
+            texts
+                .get(slot)
+                .map(|el| el.trim())
+                .ok_or(anyhow::anyhow!("No text input on slot: {slot}"))
+        };
+        let number = |slot: &str| -> anyhow::Result<f64> {
+            text(slot)?
+                .parse::<f64>()
+                .map_err(|e| anyhow::anyhow!("Text input on slot: {slot} is not a number: {e}"))
+        };
+        Ok(match self {
+            Self::Equals { slot, value } => text(slot)?.eq(value),
+            Self::NotEquals { slot, value } => text(slot)?.ne(value),
+            Self::Contains { slot, value } => text(slot)?.contains(value.as_str()),
+            Self::MatchRegex { slot, regex } => regex::Regex::new(regex)?.is_match(text(slot)?),
+            Self::GreaterThan { slot, value } => number(slot)? > *value,
+            Self::LessThan { slot, value } => number(slot)? < *value,
+            Self::And { expressions } => {
+                for expression in expressions.iter() {
+                    if !expression.evaluate(texts)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            Self::Or { expressions } => {
+                for expression in expressions.iter() {
+                    if expression.evaluate(texts)? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+            Self::Not { expression } => !expression.evaluate(texts)?,
+        })
+    }
+}
+
 impl Filler {
     /// 匹配输入提供文本的正则部分，返回填充后的文本列表
     ///
@@ -411,3 +870,118 @@ impl NodeInputSlot {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_condition_select() {
+        let (to_a, to_b, to_c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let condition: Condition = serde_json::from_value(serde_json::json!({
+            "branches": [
+                {
+                    "expression": {
+                        "type": "And",
+                        "expressions": [
+                            { "type": "Equals", "slot": "state", "value": "converged" },
+                            { "type": "LessThan", "slot": "energy", "value": 0.5 }
+                        ]
+                    },
+                    "toIds": [to_a]
+                },
+                {
+                    "expression": { "type": "MatchRegex", "slot": "state", "regex": "^fail" },
+                    "toIds": [to_b]
+                }
+            ],
+            "otherwiseToIds": [to_c]
+        }))
+        .unwrap();
+
+        let texts = |state: &str, energy: &str| {
+            HashMap::from([
+                ("state".to_string(), state.to_string()),
+                ("energy".to_string(), energy.to_string()),
+            ])
+        };
+        assert_eq!(
+            condition.select(&texts("converged\n", "0.1")).unwrap(),
+            vec![to_a]
+        );
+        assert_eq!(
+            condition.select(&texts("failed", "0.1")).unwrap(),
+            vec![to_b]
+        );
+        assert_eq!(
+            condition.select(&texts("converged", "0.9")).unwrap(),
+            vec![to_c]
+        );
+        assert!(condition.select(&texts("converged", "NaN?")).is_err());
+        assert_eq!(condition.inactive_to_ids(&[to_b]), vec![to_c, to_a]);
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let policy: RetryPolicy = serde_json::from_value(serde_json::json!({
+            "maxAttempts": 3,
+            "backoff": { "type": "Exponential", "initialDelaySecs": 30, "maxDelaySecs": 100 },
+            "retryableTaskTypes": ["DownloadFile", "ExecuteUsecase"],
+            "retryableMessages": ["(?i)timed? ?out", "connection reset"]
+        }))
+        .unwrap();
+
+        assert!(policy.should_retry(1, &TaskType::DownloadFile, Some("Read timeout")).unwrap());
+        assert!(policy
+            .should_retry(2, &TaskType::ExeceteUsecase, Some("connection reset"))
+            .unwrap());
+        assert!(!policy.should_retry(3, &TaskType::DownloadFile, Some("timeout")).unwrap());
+        assert!(!policy.should_retry(1, &TaskType::UploadFile, Some("timeout")).unwrap());
+        assert!(!policy.should_retry(1, &TaskType::DownloadFile, Some("exit code 1")).unwrap());
+        assert!(!policy.should_retry(1, &TaskType::DownloadFile, None).unwrap());
+
+        assert_eq!(policy.backoff.delay(1), Duration::from_secs(30));
+        assert_eq!(policy.backoff.delay(2), Duration::from_secs(60));
+        assert_eq!(policy.backoff.delay(3), Duration::from_secs(100));
+    }
+
+    #[test]
+    fn test_queue_scoring_select() {
+        let queue = |core_number, core_number_used, queuing_task_count| {
+            let queue = Queue {
+                id: Uuid::new_v4(),
+                core_number,
+                memory: 1 << 30,
+                ..Default::default()
+            };
+            let mut cache_info = QueueCacheInfo::default();
+            cache_info.used.core_number_used = core_number_used;
+            cache_info.task_count.queuing_task_count = queuing_task_count;
+            (queue, cache_info)
+        };
+        let candidates = vec![queue(128, 0, 5), queue(64, 40, 1), queue(32, 0, 3)];
+        let ids = candidates.iter().map(|(q, _)| q.id).collect::<Vec<_>>();
+        let requirements = Requirements {
+            cpu_cores: Some(24),
+            ..Default::default()
+        };
+
+        let select = |scoring: QueueScoring, round| {
+            scoring.select(candidates.clone(), Some(&requirements), round).unwrap().id
+        };
+        assert_eq!(select(QueueScoring::BestFit, 0), ids[1]);
+        assert_eq!(select(QueueScoring::LeastQueued, 0), ids[1]);
+
+        let weighted = QueueScoring::WeightedRoundRobin {
+            weights: HashMap::from([(ids[0], 2), (ids[1], 0)]),
+        };
+        let selected = (0..4).map(|round| select(weighted.clone(), round)).collect::<Vec<_>>();
+        assert_eq!(selected, vec![ids[0], ids[0], ids[2], ids[0]]);
+
+        let all_zero = QueueScoring::WeightedRoundRobin {
+            weights: ids.iter().map(|id| (*id, 0)).collect(),
+        };
+        let selected = (0..4).map(|round| select(all_zero.clone(), round)).collect::<Vec<_>>();
+        assert_eq!(selected, vec![ids[0], ids[1], ids[2], ids[0]]);
+    }
+}
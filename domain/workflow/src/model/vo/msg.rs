@@ -74,6 +74,7 @@ pub enum NodeStatusChange {
     Pausing,
     Paused,
     Resuming,
+    Skipped,
 }
 
 impl ChangeInfo for FlowStatusChange {}
@@ -139,6 +140,7 @@ impl From<NodeStatusChange> for NodeInstanceStatus {
             NodeStatusChange::Pausing => Self::Pausing,
             NodeStatusChange::Paused => Self::Paused,
             NodeStatusChange::Resuming => Self::Resuming,
+            NodeStatusChange::Skipped => Self::Skipped,
         }
     }
 }
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 配额的归属对象
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum QuotaSubject {
+    /// 用户
+    User(Uuid),
+    /// 项目
+    Project(Uuid),
+}
+
+impl std::fmt::Display for QuotaSubject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuotaSubject::User(id) => write!(f, "user {id}"),
+            QuotaSubject::Project(id) => write!(f, "project {id}"),
+        }
+    }
+}
+
+/// 资源配额配置
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct QuotaConfig {
+    /// 未单独配置的用户的配额，为空时不限制
+    #[serde(default)]
+    pub default_user: Option<QuotaLimit>,
+    /// 未单独配置的项目的配额，为空时不限制
+    #[serde(default)]
+    pub default_project: Option<QuotaLimit>,
+    /// 各用户的配额
+    #[serde(default)]
+    pub users: HashMap<Uuid, QuotaLimit>,
+    /// 各项目的配额
+    #[serde(default)]
+    pub projects: HashMap<Uuid, QuotaLimit>,
+}
+
+impl QuotaConfig {
+    /// 获取对象的配额，没有配额时不限制
+    ///
+    /// # 参数
+    ///
+    /// * `subject` - 配额的归属对象
+    pub fn limit(&self, subject: &QuotaSubject) -> Option<&QuotaLimit> {
+        match subject {
+            QuotaSubject::User(id) => self.users.get(id).or(self.default_user.as_ref()),
+            QuotaSubject::Project(id) => self.projects.get(id).or(self.default_project.as_ref()),
+        }
+    }
+}
+
+/// 资源配额，字段为空时不限制
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct QuotaLimit {
+    /// 最大同时运行节点数
+    #[serde(default)]
+    pub max_running_nodes: Option<u64>,
+    /// 最大同时占用核心数
+    #[serde(default)]
+    pub max_cores: Option<u64>,
+    /// 最大累计核时
+    #[serde(default)]
+    pub max_core_hours: Option<f64>,
+}
+
+/// 资源配额使用情况
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QuotaUsage {
+    /// 运行中的节点数
+    pub running_nodes: u64,
+    /// 运行中的节点占用的核心数
+    pub cores: u64,
+    /// 累计消耗的核时
+    pub core_hours: f64,
+}
+
+/// 超出配额的原因
+#[derive(Clone, Debug)]
+pub struct QuotaViolation {
+    /// 原因
+    pub reason: String,
+    /// 是否可以等待运行中的节点结束后继续，累计核时耗尽时不能
+    pub can_wait: bool,
+}
+
+impl QuotaLimit {
+    /// 检查已使用的配额，再运行一个节点时是否超出配额
+    ///
+    /// # 参数
+    ///
+    /// * `subject` - 配额的归属对象
+    /// * `usage` - 已使用的配额
+    /// * `cores` - 新节点占用的核心数，为空时只检查配额是否已经用尽
+    pub fn check(
+        &self,
+        subject: &QuotaSubject,
+        usage: &QuotaUsage,
+        cores: Option<u64>,
+    ) -> Option<QuotaViolation> {
+        if let Some(max_core_hours) = self.max_core_hours {
+            if usage.core_hours >= max_core_hours {
+                return Some(QuotaViolation {
+                    reason: format!(
+                        "{subject} has spent {:.2} core hours of {max_core_hours}",
+                        usage.core_hours
+                    ),
+                    can_wait: false,
+                });
+            }
+        }
+        if let Some(max_running_nodes) = self.max_running_nodes {
+            if usage.running_nodes >= max_running_nodes {
+                return Some(QuotaViolation {
+                    reason: format!(
+                        "{subject} is running {} nodes of {max_running_nodes}",
+                        usage.running_nodes
+                    ),
+                    can_wait: true,
+                });
+            }
+        }
+        if let Some(max_cores) = self.max_cores {
+            let exceeded = match cores {
+                Some(cores) => usage.cores + cores > max_cores,
+                None => usage.cores >= max_cores,
+            };
+            if exceeded {
+                return Some(QuotaViolation {
+                    reason: format!("{subject} is using {} cores of {max_cores}", usage.cores),
+                    can_wait: true,
+                });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_check() {
+        let subject = QuotaSubject::User(Uuid::new_v4());
+        let limit = QuotaLimit {
+            max_running_nodes: Some(2),
+            max_cores: Some(8),
+            max_core_hours: Some(100.0),
+        };
+        let usage = QuotaUsage {
+            running_nodes: 1,
+            cores: 4,
+            core_hours: 10.0,
+        };
+        assert!(limit.check(&subject, &usage, None).is_none());
+        assert!(limit.check(&subject, &usage, Some(4)).is_none());
+        assert!(limit.check(&subject, &usage, Some(5)).unwrap().can_wait);
+
+        let usage = QuotaUsage {
+            running_nodes: 2,
+            ..usage
+        };
+        assert!(limit.check(&subject, &usage, None).unwrap().can_wait);
+        assert!(limit.check(&subject, &usage, Some(1)).unwrap().can_wait);
+
+        let usage = QuotaUsage {
+            core_hours: 100.0,
+            ..Default::default()
+        };
+        assert!(!limit.check(&subject, &usage, Some(1)).unwrap().can_wait);
+
+        let config = QuotaConfig {
+            default_user: Some(QuotaLimit::default()),
+            ..Default::default()
+        };
+        assert!(config.limit(&subject).is_some());
+        assert!(config.limit(&QuotaSubject::Project(Uuid::new_v4())).is_none());
+    }
+}
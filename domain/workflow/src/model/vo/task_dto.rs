@@ -93,6 +93,9 @@ pub struct ExecuteUsecase {
     pub name: String,
     /// 任务软件环境技术
     pub facility_kind: FacilityKind,
+    /// 依赖软件的环境技术，依赖在依赖它的软件之前，执行前与 facility_kind 一同加载
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependency_facility_kinds: Vec<FacilityKind>,
     /// 参数列表
     /// 例如： ["-i a.txt","--debug"]
     pub arguments: Vec<String>,
@@ -361,6 +364,42 @@ pub enum FacilityKind {
         /// 镜像 tag
         tag: String,
     },
+    /// apptainer
+    #[serde(rename_all = "camelCase")]
+    Apptainer {
+        /// 镜像名
+        image: String,
+        /// 镜像 tag
+        tag: String,
+    },
+    /// oci 镜像，由 docker 或 podman 运行
+    #[serde(rename_all = "camelCase")]
+    Oci {
+        /// 镜像名
+        image: String,
+        /// 镜像摘要
+        digest: String,
+    },
+    /// conda 环境，可由 conda 或 mamba 创建
+    #[serde(rename_all = "camelCase")]
+    Conda {
+        /// 环境名
+        name: String,
+        /// 安装的包
+        packages: Vec<String>,
+        /// 频道列表
+        channels: Vec<String>,
+    },
+    /// easybuild 安装、lmod 加载的模块
+    #[serde(rename_all = "camelCase")]
+    Module {
+        /// 模块名
+        name: String,
+        /// 模块版本
+        version: String,
+        /// 模块未安装时用于安装的 easyconfig
+        easyconfig: Option<String>,
+    },
 }
 
 impl From<domain_content_repo::model::vo::abilities::common::ValidateRuleEnum> for ValidateRule {
@@ -415,6 +454,32 @@ impl From<domain_content_repo::model::vo::abilities::software_computing::softwar
                 image,
                 tag,
             } => FacilityKind::Singularity { image, tag },
+            domain_content_repo::model::vo::abilities::software_computing::software::SoftwareSpec::Apptainer {
+                image,
+                tag,
+            } => FacilityKind::Apptainer { image, tag },
+            domain_content_repo::model::vo::abilities::software_computing::software::SoftwareSpec::Oci {
+                image,
+                digest,
+            } => FacilityKind::Oci { image, digest },
+            domain_content_repo::model::vo::abilities::software_computing::software::SoftwareSpec::Conda {
+                name,
+                packages,
+                channels,
+            } => FacilityKind::Conda {
+                name,
+                packages,
+                channels,
+            },
+            domain_content_repo::model::vo::abilities::software_computing::software::SoftwareSpec::Module {
+                name,
+                version,
+                easyconfig,
+            } => FacilityKind::Module {
+                name,
+                version,
+                easyconfig,
+            },
         }
     }
 }
@@ -1,5 +1,8 @@
 mod installed_software;
 mod node_instance;
+mod queue_cache;
+mod quota;
+mod scheduler_backlog;
 mod software_block_list;
 mod task;
 mod workflow_instance;
@@ -8,6 +11,9 @@ mod workflow_instance;
 pub use {
     installed_software::InstalledSoftwareRepo,
     node_instance::NodeInstanceRepo,
+    queue_cache::QueueCacheRepo,
+    quota::QuotaRepo,
+    scheduler_backlog::SchedulerBacklogRepo,
     software_block_list::SoftwareBlockListRepo,
     task::TaskRepo,
     workflow_instance::WorkflowInstanceRepo,
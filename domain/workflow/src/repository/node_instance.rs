@@ -34,6 +34,12 @@ pub trait NodeInstanceRepo: DBRepository<NodeInstance> + Send + Sync {
 
     async fn get_node_spec(&self, node_id: Uuid) -> anyhow::Result<NodeSpec>;
 
+    /// 获取在这些队列上运行中的节点
+    async fn get_running_nodes_by_queue_ids(
+        &self,
+        queue_ids: &[Uuid],
+    ) -> anyhow::Result<Vec<NodeInstance>>;
+
     /// For resource_meter update race.
     async fn update_immediately_with_lock(&self, entity: DbNodeInstance) -> anyhow::Result<()>;
 }
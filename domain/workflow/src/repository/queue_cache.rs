@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::model::entity::{
+    queue::{QueueCacheInfo, QueueResourceUsed},
+    Queue,
+};
+
+/// 队列资源使用情况缓存，由所有编排服务实例共享
+///
+/// 编排服务为每个节点预留的资源与代理上报的资源使用情况分开记录，代理上报不会覆盖预留
+#[async_trait]
+pub trait QueueCacheRepo: Send + Sync {
+    /// 获取队列资源使用情况，每一项取预留的与代理上报的较大值，没有记录时为默认值
+    ///
+    /// # 参数
+    ///
+    /// * `queue_id` - 队列 id
+    async fn get_cache_info(&self, queue_id: Uuid) -> anyhow::Result<QueueCacheInfo>;
+
+    /// 覆盖代理上报的队列资源使用情况
+    ///
+    /// # 参数
+    ///
+    /// * `queue_id` - 队列 id
+    /// * `info` - 资源使用情况
+    async fn set_reported_usage(&self, queue_id: Uuid, info: &QueueCacheInfo)
+        -> anyhow::Result<()>;
+
+    /// 原子地为节点预留资源并增加一个排队任务，超过队列阈值时失败且不做任何改变，
+    /// 节点已有预留时不做任何改变
+    ///
+    /// # 参数
+    ///
+    /// * `queue` - 队列
+    /// * `node_id` - 节点 id
+    /// * `used` - 预留的资源
+    async fn reserve(
+        &self,
+        queue: &Queue,
+        node_id: Uuid,
+        used: &QueueResourceUsed,
+    ) -> anyhow::Result<()>;
+
+    /// 原子地将节点的预留由排队变为运行中，任务已经在代理上运行，因此不检查最大运行任务数，
+    /// 节点没有预留或已经运行时不做任何改变
+    ///
+    /// # 参数
+    ///
+    /// * `queue_id` - 队列 id
+    /// * `node_id` - 节点 id
+    async fn start(&self, queue_id: Uuid, node_id: Uuid) -> anyhow::Result<()>;
+
+    /// 原子地释放节点的预留，返回是否有预留被释放，因此每个预留只会被释放一次
+    ///
+    /// # 参数
+    ///
+    /// * `queue_id` - 队列 id
+    /// * `node_id` - 节点 id
+    async fn release(&self, queue_id: Uuid, node_id: Uuid) -> anyhow::Result<bool>;
+
+    /// 原子地取得一组队列的轮询计数并加一
+    ///
+    /// # 参数
+    ///
+    /// * `queue_set` - 队列集合的标识
+    async fn next_round(&self, queue_set: &str) -> anyhow::Result<usize>;
+}
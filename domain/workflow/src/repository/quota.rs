@@ -0,0 +1,66 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::model::vo::quota::{QuotaLimit, QuotaSubject, QuotaUsage};
+
+/// 资源配额使用情况，由所有编排服务实例共享
+#[async_trait]
+pub trait QuotaRepo: Send + Sync {
+    /// 获取配额使用情况，没有记录时为默认值
+    ///
+    /// # 参数
+    ///
+    /// * `subject` - 配额的归属对象
+    async fn get_usage(&self, subject: &QuotaSubject) -> anyhow::Result<QuotaUsage>;
+
+    /// 记录运行中的节点，重复记录同一节点时覆盖
+    ///
+    /// # 参数
+    ///
+    /// * `subject` - 配额的归属对象
+    /// * `node_id` - 节点实例 id
+    /// * `cores` - 节点占用的核心数
+    async fn add_running_node(
+        &self,
+        subject: &QuotaSubject,
+        node_id: Uuid,
+        cores: u64,
+    ) -> anyhow::Result<()>;
+
+    /// 原子地检查运行节点数和核心数配额并记录运行中的节点，超出配额时不做任何改变并返回
+    /// 检查时的使用情况，节点已经记录时不做任何改变，不检查累计核时
+    ///
+    /// # 参数
+    ///
+    /// * `subject` - 配额的归属对象
+    /// * `node_id` - 节点实例 id
+    /// * `cores` - 节点占用的核心数
+    /// * `limit` - 配额
+    async fn try_add_running_node(
+        &self,
+        subject: &QuotaSubject,
+        node_id: Uuid,
+        cores: u64,
+        limit: &QuotaLimit,
+    ) -> anyhow::Result<Option<QuotaUsage>>;
+
+    /// 移除运行中的节点，节点不存在时不做任何改变
+    ///
+    /// # 参数
+    ///
+    /// * `subject` - 配额的归属对象
+    /// * `node_id` - 节点实例 id
+    async fn remove_running_node(
+        &self,
+        subject: &QuotaSubject,
+        node_id: Uuid,
+    ) -> anyhow::Result<()>;
+
+    /// 累加消耗的核时
+    ///
+    /// # 参数
+    ///
+    /// * `subject` - 配额的归属对象
+    /// * `core_hours` - 消耗的核时
+    async fn add_core_hours(&self, subject: &QuotaSubject, core_hours: f64) -> anyhow::Result<()>;
+}
@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::model::vo::backlog::{BacklogEntry, Preemption};
+
+/// 调度等待队列，由所有编排服务实例共享
+#[async_trait]
+pub trait SchedulerBacklogRepo: Send + Sync {
+    /// 节点进入等待队列，已在等待的节点保留原有的优先级和等待时间
+    ///
+    /// # 参数
+    ///
+    /// * `entry` - 等待的节点
+    async fn push(&self, entry: &BacklogEntry) -> anyhow::Result<()>;
+
+    /// 原子地将节点移出等待队列，节点不在等待队列中时返回 None
+    /// 多个实例同时移出同一节点时只有一个能拿到
+    ///
+    /// # 参数
+    ///
+    /// * `node_id` - 节点实例 id
+    async fn remove(&self, node_id: Uuid) -> anyhow::Result<Option<BacklogEntry>>;
+
+    /// 获取所有等待中的节点，不保证顺序
+    async fn get_all(&self) -> anyhow::Result<Vec<BacklogEntry>>;
+
+    /// 记录被抢占暂停的节点
+    ///
+    /// # 参数
+    ///
+    /// * `preemption` - 抢占记录
+    async fn push_preemption(&self, preemption: &Preemption) -> anyhow::Result<()>;
+
+    /// 原子地移除抢占记录，记录不存在时返回 None
+    ///
+    /// # 参数
+    ///
+    /// * `node_id` - 被暂停的节点实例 id
+    async fn remove_preemption(&self, node_id: Uuid) -> anyhow::Result<Option<Preemption>>;
+
+    /// 获取所有抢占记录
+    async fn get_preemptions(&self) -> anyhow::Result<Vec<Preemption>>;
+}
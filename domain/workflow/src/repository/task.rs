@@ -1,4 +1,5 @@
 use alice_architecture::repository::DBRepository;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 use crate::model::entity::task::Task;
@@ -10,4 +11,11 @@ pub trait TaskRepo: Send + Sync + DBRepository<Task> {
 
     /// Get tasks with node_id.
     async fn get_tasks_by_node_id(&self, node_id: Uuid) -> anyhow::Result<Vec<Task>>;
+
+    /// Delete tasks with node_id, so that the node can be parsed into tasks again.
+    async fn delete_by_node_id(&self, node_id: Uuid) -> anyhow::Result<()>;
+
+    /// Take tasks whose backoff ends before the time, their pending retries are cleared
+    /// immediately so that each retry is taken only once.
+    async fn take_due_retries(&self, before: DateTime<Utc>) -> anyhow::Result<Vec<Uuid>>;
 }
@@ -15,5 +15,9 @@ pub trait ControlService {
 
     async fn terminate(&self, instance_id: Uuid) -> WorkflowResult<()>;
 
+    /// Rerun failed or terminated nodes and their downstream nodes, reusing completed nodes'
+    /// outputs.
+    async fn retry_from_failure(&self, instance_id: Uuid) -> WorkflowResult<()>;
+
     async fn validate(&self, draft_id: Uuid)-> WorkflowResult<()>;
 }
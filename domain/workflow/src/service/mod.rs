@@ -1,7 +1,9 @@
 mod control;
 #[allow(clippy::module_inception)]
 mod queue_resource;
+mod quota;
 mod schedule;
+mod scheduler_backlog;
 mod status;
 mod task_status_receiver;
 mod usecase;
@@ -9,7 +11,9 @@ mod usecase;
 #[rustfmt::skip]
 pub use {
     queue_resource::QueueResourceService,
+    quota::QuotaService,
     schedule::ScheduleService,
+    scheduler_backlog::SchedulerBacklogService,
     task_status_receiver::TaskStatusReceiveService,
     usecase::*,
     status::StatusService,
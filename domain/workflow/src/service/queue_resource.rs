@@ -5,35 +5,54 @@ use uuid::Uuid;
 
 use crate::model::{
     entity::{
-        queue::{QueueCacheInfo, QueueResourceUsed},
+        queue::{QueueCacheInfo, QueueSelection},
         Queue,
     },
-    vo::SchedulingStrategy,
+    vo::{Requirements, SchedulingStrategy},
 };
 
 #[async_trait]
 /// Queue resource service.
 pub trait QueueResourceService: Send + Sync {
-    /// Get an available queue.
+    /// Get an available queue which fits the requirements, with resources reserved for the node.
+    /// Why queues are rejected is reported to the node. When the fitting queues are only full for
+    /// now, the node waits in the scheduler backlog and None is returned, otherwise the node is
+    /// reported as Failed.
     async fn get_queue(
         &self,
         node_id: Uuid,
         scheduling_strategy: &SchedulingStrategy,
-    ) -> anyhow::Result<Queue>;
+        requirements: Option<&Requirements>,
+    ) -> anyhow::Result<Option<Queue>>;
 
-    /// Add cached used queue resources.
-    async fn add_used_queue_resources(&self, queue: &Queue) -> anyhow::Result<()>;
+    /// Select a queue which fits the requirements, without reporting anything.
+    async fn select_queue(
+        &self,
+        scheduling_strategy: &SchedulingStrategy,
+        requirements: Option<&Requirements>,
+    ) -> anyhow::Result<QueueSelection>;
+
+    /// Reserve resources of the queue for the node atomically, Err when the queue is full.
+    /// Nothing is changed if the node already holds a reservation on the queue.
+    async fn reserve_node(
+        &self,
+        queue_id: Uuid,
+        node_id: Uuid,
+        requirements: Option<&Requirements>,
+    ) -> anyhow::Result<()>;
 
-    /// Release cached used queue resources.
-    async fn release_used_queue_resources(&self, queue_id: Uuid, resource_used: &QueueResourceUsed);
+    /// Release resources reserved for the node, and drain the scheduler backlog if anything is
+    /// released. Releasing twice is a no-op.
+    async fn release_node(&self, queue_id: Uuid, node_id: Uuid);
 
     /// Add new queue.
     async fn insert_queue(&self, queue: &Queue) -> anyhow::Result<()>;
 
-    /// Update cached queue info when task started.
-    async fn task_started(&self, queue_id: Uuid) -> anyhow::Result<()>;
+    /// Count the reservation of the node as running when its task started.
+    async fn task_started(&self, queue_id: Uuid, node_id: Uuid) -> anyhow::Result<()>;
 
-    /// Set cached queue info to new value.
+    /// Set usage reported by the agent of the queue, and drain the scheduler backlog if capacity
+    /// is freed. Reservations are kept.
     async fn update_queue_resource(&self, queue_id: Uuid, queue: &QueueCacheInfo);
 
     /// Test if a queue is run out of resource, Err when is run out of resource.
@@ -0,0 +1,41 @@
+//! Resource quota service.
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::model::{
+    entity::WorkflowInstance,
+    vo::{quota::QuotaViolation, task_dto::result::TaskUsedResource, Requirements},
+};
+
+#[async_trait]
+/// Enforce per user and per project resource quotas.
+pub trait QuotaService: Send + Sync {
+    /// Check if the quotas of the workflow's user or project are used up.
+    async fn check_flow(&self, flow: &WorkflowInstance) -> anyhow::Result<Option<QuotaViolation>>;
+
+    /// Check if running the node exceeds the quotas of its workflow's user or project, without
+    /// counting it. Other nodes may start in the meantime, use [`Self::start_node`] to start it.
+    async fn check_node(
+        &self,
+        node_id: Uuid,
+        requirements: Option<&Requirements>,
+    ) -> anyhow::Result<Option<QuotaViolation>>;
+
+    /// Check the quotas and count the node as running until it finishes atomically, nothing is
+    /// counted when the quotas are exceeded.
+    async fn start_node(
+        &self,
+        node_id: Uuid,
+        requirements: Option<&Requirements>,
+    ) -> anyhow::Result<Option<QuotaViolation>>;
+
+    /// Stop counting a node which is not started after all, without draining the backlog.
+    async fn cancel_node(&self, node_id: Uuid) -> anyhow::Result<()>;
+
+    /// Stop counting the node as running.
+    async fn node_finished(&self, node_id: Uuid) -> anyhow::Result<()>;
+
+    /// Accumulate core hours spent by a task of the node.
+    async fn record_spend(&self, node_id: Uuid, used: &TaskUsedResource) -> anyhow::Result<()>;
+}
@@ -0,0 +1,10 @@
+//! Scheduler backlog service.
+
+use async_trait::async_trait;
+
+#[async_trait]
+/// Dispatch nodes waiting in the scheduler backlog.
+pub trait SchedulerBacklogService: Send + Sync {
+    /// Dispatch waiting nodes by priority and age, until a node still has no queue available.
+    async fn drain(&self) -> anyhow::Result<()>;
+}
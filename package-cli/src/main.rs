@@ -0,0 +1,152 @@
+//! Package authoring tool, checks software and usecase packages locally with the same logic as
+//! `usecase-editor/PackageValidate`, and packs them for the content repository.
+
+mod pack;
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::Context;
+use domain_content_repo::{
+    model::{
+        entity::{
+            package::{AbilityKind, PackageDependency, SoftwareComputingRepo},
+            Package,
+        },
+        vo::{TemplateKeys, ValidateData},
+    },
+    service::ValidatePackageService,
+};
+use service_content_repo::ValidatePackageServiceImpl;
+use uuid::Uuid;
+
+const USAGE: &str = "\
+Usage:
+  package-cli check <software-dir> <usecase-dir>  Validate the packages and print the command preview
+  package-cli keys <template-file>...             List keys of the template files
+  package-cli pack <package-dir> [<output>]       Pack the package, into <package-dir>.tar by default";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["check", software_dir, usecase_dir] => {
+            check(Path::new(software_dir), Path::new(usecase_dir))
+        }
+        ["keys", files @ ..] if !files.is_empty() => keys(files),
+        ["pack", dir] => pack_package(Path::new(dir), None),
+        ["pack", dir, output] => pack_package(Path::new(dir), Some(Path::new(output))),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Validate the software and usecase packages, print keys of templates and the command preview.
+fn check(software_dir: &Path, usecase_dir: &Path) -> anyhow::Result<()> {
+    let software = load(software_dir)?;
+    let usecase = load(usecase_dir)?;
+    let (_, software_data) = software.software_package_data().context(format!(
+        "{} is not a software package.",
+        software_dir.display()
+    ))?;
+    let (_, usecase_data) = usecase.usecase_package_data().context(format!(
+        "{} is not a usecase package.",
+        usecase_dir.display()
+    ))?;
+    check_dependency(&software, &usecase);
+
+    for template in usecase_data.template_file_infos.iter() {
+        let TemplateKeys(keys) = template
+            .content
+            .parse::<TemplateKeys>()
+            .with_context(|| format!("Invalid template: {}", template.file_name))?;
+        println!(
+            "template {} ({}): {}",
+            template.file_name,
+            template.descriptor,
+            keys.join(", ")
+        );
+    }
+
+    let preview =
+        futures::executor::block_on(ValidatePackageServiceImpl.validate_package(ValidateData {
+            software_data,
+            usecase_data,
+        }))?;
+    println!("{}", serde_json::to_string_pretty(&preview)?);
+    Ok(())
+}
+
+/// Pack the directory and parse it the same way as packages from the content repository.
+fn load(dir: &Path) -> anyhow::Result<Package> {
+    let tar = pack::pack(dir)?;
+    Package::extract_package(Uuid::nil(), &tar)
+        .with_context(|| format!("Invalid package: {}", dir.display()))
+}
+
+/// Warn if the usecase doesn't depend on the software, it would never be paired with it.
+fn check_dependency(software: &Package, usecase: &Package) {
+    let (dependency_system_name, dependency_version_range) = match &usecase.manifest.ability {
+        AbilityKind::SoftwareComputing(SoftwareComputingRepo::Usecase {
+            dependency_system_name,
+            dependency_version_range,
+        }) => (dependency_system_name, dependency_version_range),
+        AbilityKind::SoftwareComputing(SoftwareComputingRepo::Software) => return,
+    };
+    if *dependency_system_name != software.manifest.system_name {
+        eprintln!(
+            "warning: usecase depends on {dependency_system_name}, not {}",
+            software.manifest.system_name
+        );
+        return;
+    }
+    let dependency = PackageDependency {
+        system_name: dependency_system_name.to_owned(),
+        version_range: dependency_version_range.to_owned(),
+    };
+    match dependency.matches(&software.manifest.version) {
+        Ok(true) => {}
+        Ok(false) => eprintln!(
+            "warning: usecase requires {dependency_system_name} {dependency_version_range}, \
+            but the software version is {}",
+            software.manifest.version
+        ),
+        Err(e) => eprintln!("warning: {e}"),
+    }
+}
+
+fn keys(files: &[&str]) -> anyhow::Result<()> {
+    for file in files {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read template: {file}"))?;
+        let TemplateKeys(keys) = content
+            .parse::<TemplateKeys>()
+            .with_context(|| format!("Invalid template: {file}"))?;
+        println!("{file}: {}", keys.join(", "));
+    }
+    Ok(())
+}
+
+fn pack_package(dir: &Path, output: Option<&Path>) -> anyhow::Result<()> {
+    let tar = pack::pack(dir)?;
+    Package::extract_package(Uuid::nil(), &tar)
+        .with_context(|| format!("Invalid package: {}", dir.display()))?;
+    let output = match output {
+        Some(el) => el.to_owned(),
+        None => PathBuf::from(format!("{}.tar", pack::root_name(dir)?)),
+    };
+    std::fs::write(&output, tar)
+        .with_context(|| format!("Failed to write: {}", output.display()))?;
+    println!("packed {} into {}", dir.display(), output.display());
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use domain_content_repo::model::entity::package::{
+    PACKAGE_ANCESTORS_COUNT, TEMPLATE_FILE_FOLDER_NAME,
+};
+
+/// Extensions of the manifest, the only file at the package root.
+const MANIFEST_EXTENSIONS: [&str; 2] = ["yaml", "yml"];
+
+/// Name of the root directory in the tar, the name of the package directory.
+pub fn root_name(dir: &Path) -> anyhow::Result<String> {
+    let dir = dir
+        .canonicalize()
+        .with_context(|| format!("No such directory: {}", dir.display()))?;
+    Ok(dir
+        .file_name()
+        .context(format!("Can't pack the root directory: {}", dir.display()))?
+        .to_string_lossy()
+        .to_string())
+}
+
+/// Pack the package directory in the layout parsed by `Package::extract_package`: the manifest
+/// is the only file under the root directory, materials are in subdirectories, and templates are
+/// in a `templates` directory of a subdirectory. Hidden files are skipped.
+pub fn pack(dir: &Path) -> anyhow::Result<Vec<u8>> {
+    let root = PathBuf::from(root_name(dir)?);
+    let files = list_files(dir)?;
+    check_layout(&root, &files)?;
+
+    let mut builder = tar::Builder::new(vec![]);
+    // Same content packs into the same tar, so that package hashes are stable.
+    builder.mode(tar::HeaderMode::Deterministic);
+    for file in files {
+        builder.append_path_with_name(dir.join(&file), root.join(&file))?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// List files in the directory as relative paths, sorted so that the tar is deterministic.
+fn list_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        for entry in std::fs::read_dir(dir.join(&relative))? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if name.to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(relative.join(name));
+            } else if file_type.is_file() {
+                files.push(relative.join(name));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn check_layout(root: &Path, files: &[PathBuf]) -> anyhow::Result<()> {
+    let mut manifest = None;
+    for file in files {
+        let path = root.join(file);
+        let ancestors_count = path.ancestors().count();
+        if ancestors_count == PACKAGE_ANCESTORS_COUNT {
+            if manifest.is_some() {
+                bail!(
+                    "Only the manifest is allowed at the package root, move {} into a subdirectory.",
+                    file.display()
+                );
+            }
+            if !path
+                .extension()
+                .is_some_and(|el| MANIFEST_EXTENSIONS.iter().any(|ext| el == *ext))
+            {
+                bail!("Manifest: {} is not a yaml file.", file.display());
+            }
+            manifest = Some(file);
+        } else if file
+            .parent()
+            .and_then(Path::file_name)
+            .is_some_and(|el| el == TEMPLATE_FILE_FOLDER_NAME)
+            && ancestors_count != PACKAGE_ANCESTORS_COUNT + 2
+        {
+            bail!(
+                "Template: {} must be in a {TEMPLATE_FILE_FOLDER_NAME} directory of a subdirectory, \
+                for example usecase/{TEMPLATE_FILE_FOLDER_NAME}/.",
+                file.display()
+            );
+        }
+    }
+    if manifest.is_none() {
+        bail!("Package has no manifest at its root.");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use domain_content_repo::model::entity::Package;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn write(dir: &Path, file: &str, content: &str) {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_pack() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "manifest.yaml",
+            "ability:\n  SoftwareComputing: Software\nname: Computing\nsystemName: computing\n\
+             version: 1.0.0\nmaintainers: []\n",
+        );
+        write(
+            dir.path(),
+            "software/spec.yaml",
+            "kind: SoftwareSpec\nspec:\n  spack:\n    argument_list: []\n    name: computing\n",
+        );
+        write(dir.path(), ".git/HEAD", "ref: refs/heads/main");
+
+        let tar = pack(dir.path()).unwrap();
+        assert_eq!(tar, pack(dir.path()).unwrap());
+        let package = Package::extract_package(Uuid::nil(), &tar).unwrap();
+        assert_eq!(package.manifest.system_name, "computing");
+        assert!(package.software_package_data().is_some());
+
+        write(dir.path(), "README.md", "");
+        assert!(pack(dir.path()).is_err());
+    }
+}
@@ -25,3 +25,8 @@ file-metadata-not-uploaded = The fileMetadata with id: { $fileMetadataId } in no
 dulplicated-batch-strategy = A slot can only have one batch strategy, but the slot { $inputSlotDescriptor } has multiple batch strategies.
 at-least-one-queue = Manual and Prefer must select one queue at least.
 batch-input-not-offer = The optional in batch input must not be true, but { $nodeId }'s input-slot: { $slot }'s optional is true.
+no-such-condition-branch = The branch of condition node: { $nodeId } activates node: { $toId }, which is not its out node.
+node-not-in-loop-body = The node: { $nodeId } referenced by the until condition or feedbacks isn't in the loop body.
+zero-loop-max-iterations = The max iterations of loop must be more than zero.
+workflow-not-retryable = The workflow instance: { $id } is neither failed nor terminated, so it can't be retried.
+quota-exceeded = Resource quota exceeded: { $reason }.
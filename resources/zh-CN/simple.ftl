@@ -25,3 +25,8 @@ file-metadata-not-uploaded = 节点：{ $nodeId }、输入插槽：{ $descriptor
 dulplicated-batch-strategy =  一个输入插槽只能有一个批量策略，但节点：{ $nodeId } 的输入插槽 { $descriptor } 有多个批量策略。
 at-least-one-queue = “手动”和“偏好”类型的调度策略必须至少选择一个队列。
 batch-input-not-offer = 批量输入中的 Optional 一定不能为 true，但 { $nodeId } 的输入插槽: { $descriptor } 的 Optional 为 true。
+no-such-condition-branch = 条件节点：{ $nodeId } 的分支激活的节点：{ $toId } 不是它的出节点。
+node-not-in-loop-body = 循环终止条件或反馈关系中的节点：{ $nodeId } 不在循环体中。
+zero-loop-max-iterations = 循环的最大迭代次数必须大于零。
+workflow-not-retryable = 工作流实例：{ $id } 既未失败也未被终止，无法重试。
+quota-exceeded = 超出资源配额：{ $reason }。
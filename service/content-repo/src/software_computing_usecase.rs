@@ -1,8 +1,15 @@
 use std::sync::Arc;
 
+use anyhow::{anyhow, bail};
 use async_trait::async_trait;
 use domain_content_repo::{
-    model::vo::{node_ability_kind::Packages, SoftwareComputingUsecase},
+    model::{
+        entity::package::{parse_version, Manifest, Package, PackageDependency},
+        vo::{
+            node_ability_kind::Packages, PackageVersion, PinnedPackage, SoftwareComputingUsecase,
+            SoftwareDependency,
+        },
+    },
     repository::PackageRepo,
     service::SoftwareComputingUsecaseInfoService,
 };
@@ -13,6 +20,26 @@ pub struct SoftwareComputingUsecaseInfoServiceImpl {
     package_repo: Arc<dyn PackageRepo>,
 }
 
+/// 依赖解析路径上的软件包
+struct PendingPackage {
+    /// 系统名称
+    system_name: String,
+    /// 解析得到的依赖，根软件包为 None
+    dependency: Option<SoftwareDependency>,
+    /// 尚未解析的依赖声明，倒序存放
+    dependencies: Vec<PackageDependency>,
+}
+
+impl PendingPackage {
+    fn new(manifest: Manifest, dependency: Option<SoftwareDependency>) -> Self {
+        Self {
+            system_name: manifest.system_name,
+            dependency,
+            dependencies: manifest.dependencies.into_iter().rev().collect(),
+        }
+    }
+}
+
 #[async_trait]
 impl SoftwareComputingUsecaseInfoService for SoftwareComputingUsecaseInfoServiceImpl {
     async fn get_computing_usecase(
@@ -25,6 +52,96 @@ impl SoftwareComputingUsecaseInfoService for SoftwareComputingUsecaseInfoService
         let packages = Packages::SoftwareComputing(software, usecase);
         Ok(SoftwareComputingUsecase::extract_packages(packages))
     }
+
+    async fn get_pinned_computing_usecase(
+        &self,
+        software_ver_id: Uuid,
+        software_hash: &str,
+        usecase_ver_id: Uuid,
+        usecase_hash: &str,
+    ) -> anyhow::Result<SoftwareComputingUsecase> {
+        let software = self.package_repo.get_pinned_package(software_ver_id, software_hash).await?;
+        let usecase = self.package_repo.get_pinned_package(usecase_ver_id, usecase_hash).await?;
+        let packages = Packages::SoftwareComputing(software, usecase);
+        Ok(SoftwareComputingUsecase::extract_packages(packages))
+    }
+
+    async fn get_software_dependencies(
+        &self,
+        software_ver_id: Uuid,
+    ) -> anyhow::Result<Vec<SoftwareDependency>> {
+        let software = self.package_repo.get_package(software_ver_id).await?;
+        let mut resolved: Vec<SoftwareDependency> = vec![];
+        // 深度优先遍历，依赖全部解析后软件包才出栈，所以依赖总在依赖它的软件包之前
+        let mut path = vec![PendingPackage::new(software.manifest, None)];
+        while let Some(pending) = path.last_mut() {
+            let dependency = match pending.dependencies.pop() {
+                Some(el) => el,
+                None => {
+                    if let Some(el) = path.pop().and_then(|el| el.dependency) {
+                        resolved.push(el);
+                    }
+                    continue;
+                }
+            };
+            if path.iter().any(|el| el.system_name == dependency.system_name) {
+                let cycle = path
+                    .iter()
+                    .map(|el| el.system_name.as_str())
+                    .chain([dependency.system_name.as_str()])
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                bail!("Circular software dependency: {cycle}");
+            }
+            // 同一软件包只解析一次，不回溯选择其他版本
+            if let Some(el) = resolved.iter().find(|el| el.system_name == dependency.system_name) {
+                if !dependency.matches(&el.version)? {
+                    bail!(
+                        "Software dependency conflict: {} {} is resolved, but {} requires {} \
+                        (resolution is greedy, no backtracking to other versions)",
+                        el.system_name,
+                        el.version,
+                        path.last().map(|el| el.system_name.as_str()).unwrap_or_default(),
+                        dependency.version_range
+                    );
+                }
+                continue;
+            }
+
+            let version = self.find_version(&dependency).await?;
+            let package = self.package_repo.get_package(version.version_id).await?;
+            let dependency = software_dependency(&package)?;
+            path.push(PendingPackage::new(package.manifest, Some(dependency)));
+        }
+        Ok(resolved)
+    }
+
+    async fn get_pinned_software_dependencies(
+        &self,
+        pinned: &[PinnedPackage],
+    ) -> anyhow::Result<Vec<SoftwareDependency>> {
+        let mut dependencies = Vec::with_capacity(pinned.len());
+        for el in pinned {
+            let package = self.package_repo.get_pinned_package(el.version_id, &el.hash).await?;
+            dependencies.push(software_dependency(&package)?);
+        }
+        Ok(dependencies)
+    }
+}
+
+/// 由依赖的软件包得到解析结果
+fn software_dependency(package: &Package) -> anyhow::Result<SoftwareDependency> {
+    let (software_version_id, data) = package.software_package_data().ok_or(anyhow!(
+        "Dependency: {} isn't a software package.",
+        package.manifest.system_name
+    ))?;
+    Ok(SoftwareDependency {
+        software_version_id,
+        system_name: package.manifest.system_name.to_owned(),
+        version: package.manifest.version.to_owned(),
+        hash: package.hash.to_owned(),
+        software_spec: data.spec,
+    })
 }
 
 impl SoftwareComputingUsecaseInfoServiceImpl {
@@ -32,4 +149,212 @@ impl SoftwareComputingUsecaseInfoServiceImpl {
     pub fn new(package_repo: Arc<dyn PackageRepo>) -> Self {
         Self { package_repo }
     }
+
+    /// 选出满足依赖版本范围的最高版本
+    async fn find_version(&self, dependency: &PackageDependency) -> anyhow::Result<PackageVersion> {
+        let mut matched = vec![];
+        for el in self.package_repo.get_versions(&dependency.system_name).await? {
+            if dependency.matches(&el.version)? {
+                matched.push(el);
+            }
+        }
+        matched.into_iter().max_by_key(|el| parse_version(&el.version)).ok_or(anyhow!(
+            "No version of software: {} satisfies {}.",
+            dependency.system_name,
+            dependency.version_range
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alice_architecture::repository::ReadOnlyRepository;
+    use domain_content_repo::model::{
+        entity::package::{
+            AbilityKind, Data, Package, SoftwareComputingRepo, SoftwareData,
+            SoftwareUsecaseComputingData,
+        },
+        vo::abilities::software_computing::software::SoftwareSpec,
+    };
+
+    use super::*;
+
+    /// 内存中的软件包仓库，系统名称与版本号对应唯一的软件包
+    #[derive(Default)]
+    struct StubPackageRepo {
+        packages: HashMap<Uuid, Package>,
+    }
+
+    impl StubPackageRepo {
+        /// 添加软件包，依赖写作 (系统名称, 版本范围)
+        fn add(&mut self, system_name: &str, version: &str, dependencies: &[(&str, &str)]) -> Uuid {
+            let version_id = Uuid::new_v4();
+            let package = Package {
+                version_id,
+                hash: format!("{system_name}@{version}"),
+                manifest: Manifest {
+                    ability: AbilityKind::SoftwareComputing(SoftwareComputingRepo::Software),
+                    name: system_name.to_owned(),
+                    system_name: system_name.to_owned(),
+                    version: version.to_owned(),
+                    maintainers: vec![],
+                    git: None,
+                    home_page: None,
+                    dependencies: dependencies
+                        .iter()
+                        .map(|(system_name, version_range)| PackageDependency {
+                            system_name: system_name.to_string(),
+                            version_range: version_range.to_string(),
+                        })
+                        .collect(),
+                },
+                data: Data::SoftwareUsecaseComputing(SoftwareUsecaseComputingData::Software(
+                    SoftwareData {
+                        spec: SoftwareSpec::Spack {
+                            name: system_name.to_owned(),
+                            argument_list: vec![format!("@{version}")],
+                        },
+                        arguments: vec![],
+                        environments: vec![],
+                        filesome_inputs: vec![],
+                        filesome_outputs: vec![],
+                    },
+                )),
+            };
+            self.packages.insert(version_id, package);
+            version_id
+        }
+
+        fn into_service(self) -> SoftwareComputingUsecaseInfoServiceImpl {
+            SoftwareComputingUsecaseInfoServiceImpl::new(Arc::new(self))
+        }
+    }
+
+    #[async_trait]
+    impl PackageRepo for StubPackageRepo {
+        async fn get_package_tar(&self, _content_entity_ver_id: Uuid) -> anyhow::Result<Vec<u8>> {
+            unreachable!()
+        }
+
+        async fn get_package(&self, content_entity_ver_id: Uuid) -> anyhow::Result<Package> {
+            self.packages
+                .get(&content_entity_ver_id)
+                .cloned()
+                .ok_or(anyhow!("No package: {content_entity_ver_id}"))
+        }
+
+        async fn get_versions(&self, system_name: &str) -> anyhow::Result<Vec<PackageVersion>> {
+            Ok(self
+                .packages
+                .values()
+                .filter(|el| el.manifest.system_name == system_name)
+                .map(|el| PackageVersion {
+                    version_id: el.version_id,
+                    version: el.manifest.version.to_owned(),
+                })
+                .collect())
+        }
+    }
+
+    #[async_trait]
+    impl ReadOnlyRepository<Package> for StubPackageRepo {}
+
+    fn names_and_versions(dependencies: &[SoftwareDependency]) -> Vec<(&str, &str)> {
+        dependencies
+            .iter()
+            .map(|el| (el.system_name.as_str(), el.version.as_str()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_highest_matching_version() {
+        let mut repo = StubPackageRepo::default();
+        repo.add("mpi", "1.0.0", &[]);
+        let expected = repo.add("mpi", "1.2.0", &[]);
+        repo.add("mpi", "2.0.0", &[]);
+        let app = repo.add("app", "1.0.0", &[("mpi", "^1")]);
+
+        let dependencies = repo.into_service().get_software_dependencies(app).await.unwrap();
+        assert_eq!(names_and_versions(&dependencies), [("mpi", "1.2.0")]);
+        assert_eq!(dependencies[0].software_version_id, expected);
+    }
+
+    #[tokio::test]
+    async fn test_diamond() {
+        let mut repo = StubPackageRepo::default();
+        repo.add("zlib", "1.0.0", &[]);
+        repo.add("zlib", "1.1.0", &[]);
+        repo.add("hdf5", "1.0.0", &[("zlib", "^1")]);
+        repo.add("netcdf", "1.0.0", &[("zlib", ">=1.1")]);
+        let app = repo.add("app", "1.0.0", &[("hdf5", "^1"), ("netcdf", "^1")]);
+
+        let dependencies = repo.into_service().get_software_dependencies(app).await.unwrap();
+        // 共同的依赖只解析一次，且在依赖它的软件包之前
+        assert_eq!(
+            names_and_versions(&dependencies),
+            [("zlib", "1.1.0"), ("hdf5", "1.0.0"), ("netcdf", "1.0.0")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pinned() {
+        let mut repo = StubPackageRepo::default();
+        repo.add("zlib", "1.0.0", &[]);
+        repo.add("hdf5", "1.0.0", &[("zlib", "^1")]);
+        let app = repo.add("app", "1.0.0", &[("hdf5", "^1")]);
+        let service = repo.into_service();
+        let pinned = service
+            .get_software_dependencies(app)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|el| PinnedPackage {
+                version_id: el.software_version_id,
+                hash: el.hash,
+            })
+            .collect::<Vec<_>>();
+
+        let dependencies = service.get_pinned_software_dependencies(&pinned).await.unwrap();
+        assert_eq!(
+            names_and_versions(&dependencies),
+            [("zlib", "1.0.0"), ("hdf5", "1.0.0")]
+        );
+
+        let changed = [PinnedPackage {
+            hash: "changed".to_owned(),
+            ..pinned[0].clone()
+        }];
+        assert!(service.get_pinned_software_dependencies(&changed).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cycle() {
+        let mut repo = StubPackageRepo::default();
+        repo.add("a", "1.0.0", &[("b", "^1")]);
+        repo.add("b", "1.0.0", &[("a", "^1")]);
+        let app = repo.add("app", "1.0.0", &[("a", "^1")]);
+
+        let e = repo.into_service().get_software_dependencies(app).await.unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Circular software dependency: app -> a -> b -> a"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conflict() {
+        let mut repo = StubPackageRepo::default();
+        repo.add("zlib", "1.0.0", &[]);
+        repo.add("zlib", "2.0.0", &[]);
+        repo.add("hdf5", "1.0.0", &[("zlib", "^1")]);
+        repo.add("netcdf", "1.0.0", &[("zlib", "^2")]);
+        let app = repo.add("app", "1.0.0", &[("hdf5", "^1"), ("netcdf", "^1")]);
+
+        let e = repo.into_service().get_software_dependencies(app).await.unwrap_err();
+        let message = e.to_string();
+        assert!(message.contains("zlib 1.0.0 is resolved, but netcdf requires ^2"));
+        assert!(message.contains("greedy, no backtracking"));
+    }
 }
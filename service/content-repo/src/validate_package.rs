@@ -8,9 +8,12 @@ use domain_content_repo::{
         abilities::{
             common::FileKind,
             software_computing::{
-                software::materials::{
-                    inputs::{Argument, Environment},
-                    outputs::FilesomeOutput,
+                software::{
+                    materials::{
+                        inputs::{Argument, Environment},
+                        outputs::FilesomeOutput,
+                    },
+                    SoftwareSpec,
                 },
                 usecase::{
                     collected_out::{CollectFrom, CollectRule, CollectTo},
@@ -42,6 +45,7 @@ impl ValidatePackageService for ValidatePackageServiceImpl {
         let collected_outs = usecase_data.collected_outs;
 
         let input_slots = usecase_spec.input_slots;
+        Self::validate_software_spec(&software_spec)?;
 
         let mut argument_formats_sorts = HashMap::<usize, FormatFillPreview>::new();
         let mut environment_formats = HashMap::<String, FormatFillPreview>::new();
@@ -676,6 +680,93 @@ impl ValidatePackageService for ValidatePackageServiceImpl {
 }
 
 impl ValidatePackageServiceImpl {
+    /// 校验软件规格，部署时依赖的字段不能为空
+    ///
+    /// # 参数
+    ///
+    /// * `software_spec` - 软件规格
+    fn validate_software_spec(software_spec: &SoftwareSpec) -> anyhow::Result<()> {
+        match software_spec {
+            SoftwareSpec::Spack { name, .. } => Self::validate_word("spack name", name),
+            SoftwareSpec::Singularity { image, tag } | SoftwareSpec::Apptainer { image, tag } => {
+                Self::validate_word("image", image)?;
+                Self::validate_word("image tag", tag)
+            }
+            SoftwareSpec::Oci { image, digest } => {
+                Self::validate_word("image", image)?;
+                if image.contains('@') {
+                    anyhow::bail!(
+                        "Image: {image} can't contain a digest, set it in digest instead."
+                    );
+                }
+                Self::validate_digest(digest)
+            }
+            SoftwareSpec::Conda {
+                name,
+                packages,
+                channels,
+            } => {
+                Self::validate_word("conda environment name", name)?;
+                if name.contains('/') {
+                    anyhow::bail!("Conda environment name: {name} can't contain '/'.");
+                }
+                if packages.is_empty() {
+                    anyhow::bail!("Conda environment: {name} has no packages.");
+                }
+                for package in packages.iter() {
+                    Self::validate_word("conda package", package)?;
+                }
+                for channel in channels.iter() {
+                    Self::validate_word("conda channel", channel)?;
+                }
+                Ok(())
+            }
+            SoftwareSpec::Module {
+                name,
+                version,
+                easyconfig,
+            } => {
+                Self::validate_word("module name", name)?;
+                Self::validate_word("module version", version)?;
+                // Lmod 以 `name/version` 加载模块
+                if name.contains('/') || version.contains('/') {
+                    anyhow::bail!("Module: {name}/{version} can't contain '/' in name or version.");
+                }
+                match easyconfig {
+                    Some(el) if !el.ends_with(".eb") => {
+                        anyhow::bail!("Easyconfig: {el} must be a '.eb' file.")
+                    }
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// 校验值非空且不含空白字符
+    fn validate_word(field: &str, value: &str) -> anyhow::Result<()> {
+        if value.is_empty() || value.chars().any(char::is_whitespace) {
+            anyhow::bail!("{field}: '{value}' can't be empty or contain whitespaces.");
+        }
+        Ok(())
+    }
+
+    /// 校验镜像摘要，形如 `sha256:<64 位十六进制>`
+    fn validate_digest(digest: &str) -> anyhow::Result<()> {
+        let hex = match digest.split_once(':') {
+            Some(("sha256", hex)) => (hex.len() == 64).then_some(hex),
+            Some(("sha512", hex)) => (hex.len() == 128).then_some(hex),
+            _ => None,
+        };
+        match hex {
+            Some(hex) if hex.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)) => {
+                Ok(())
+            }
+            _ => anyhow::bail!(
+                "Image digest: {digest} must be sha256 or sha512 in lowercase hex, e.g. 'sha256:...'."
+            ),
+        }
+    }
+
     /// 根据参数描述符获得参数值 format、以及初始化表示该 format 各占位符填充值的 HashMap
     ///
     /// # 参数
@@ -723,3 +814,38 @@ impl ValidatePackageServiceImpl {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_software_spec() {
+        let oci = |digest: &str| SoftwareSpec::Oci {
+            image: "docker.io/library/python".to_string(),
+            digest: digest.to_string(),
+        };
+        let digest = format!("sha256:{}", "a1".repeat(32));
+        assert!(ValidatePackageServiceImpl::validate_software_spec(&oci(&digest)).is_ok());
+        assert!(ValidatePackageServiceImpl::validate_software_spec(&oci("sha256:a1")).is_err());
+        let uppercase = format!("sha256:{}", "A1".repeat(32));
+        assert!(ValidatePackageServiceImpl::validate_software_spec(&oci(&uppercase)).is_err());
+
+        let conda = |packages: Vec<&str>| SoftwareSpec::Conda {
+            name: "analysis".to_string(),
+            packages: packages.into_iter().map(str::to_string).collect(),
+            channels: vec!["conda-forge".to_string()],
+        };
+        assert!(
+            ValidatePackageServiceImpl::validate_software_spec(&conda(vec!["numpy=1.26"])).is_ok()
+        );
+        assert!(ValidatePackageServiceImpl::validate_software_spec(&conda(vec![])).is_err());
+
+        let module = SoftwareSpec::Module {
+            name: "GROMACS".to_string(),
+            version: "2023.1-foss-2022a".to_string(),
+            easyconfig: Some("GROMACS-2023.1-foss-2022a.eb".to_string()),
+        };
+        assert!(ValidatePackageServiceImpl::validate_software_spec(&module).is_ok());
+    }
+}
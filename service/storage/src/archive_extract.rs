@@ -0,0 +1,495 @@
+use std::{
+    io::{BufReader, Read, Seek},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use alice_architecture::{
+    message_queue::producer::MessageQueueProducerTemplate, repository::ReadOnlyRepository,
+};
+use anyhow::{anyhow, bail};
+use async_trait::async_trait;
+use domain_storage::{
+    command::{CacheOperateCommand, CreateNetDiskFileCommand, ExtractArchiveCommand},
+    exception::FileException,
+    model::{
+        entity::{FileType, NetDisk, RecordNetDiskKind},
+        vo::{HashAlgorithm, Part, PlacementHint, RecordFileMeta, RecordFileStorage},
+    },
+    service::{
+        ArchiveExtractService, CacheService, CachedParts, MetaStorageService, NetDiskService,
+        StorageServerDownloadDispatcherService, StorageServerUploadDispatcherService,
+    },
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+use crate::hasher::ContentHasher;
+
+/// Size of each range of the archive read from storage servers, and of each part of its
+/// members uploaded.
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl ArchiveKind {
+    /// Judge the kind by file extension, returns the kind and the name without extension.
+    fn from_file_name(name: &str) -> Option<(Self, &str)> {
+        let lowercase = name.to_ascii_lowercase();
+        [
+            (".tar.gz", ArchiveKind::TarGz),
+            (".tgz", ArchiveKind::TarGz),
+            (".tar", ArchiveKind::Tar),
+            (".zip", ArchiveKind::Zip),
+        ]
+        .into_iter()
+        .find(|(extension, _)| lowercase.ends_with(extension))
+        .map(|(extension, kind)| (kind, &name[..name.len() - extension.len()]))
+    }
+
+    /// Unpack the archive file into the directory, members never escape the directory.
+    fn unpack(&self, archive: &Path, dir: &Path, limits: UnpackLimits) -> anyhow::Result<()> {
+        let reader = BufReader::new(std::fs::File::open(archive)?);
+        match self {
+            ArchiveKind::Tar => unpack_tar(reader, dir, limits),
+            ArchiveKind::TarGz => unpack_tar(flate2::read::GzDecoder::new(reader), dir, limits),
+            ArchiveKind::Zip => unpack_zip(reader, dir, limits),
+        }
+    }
+}
+
+/// Limits of members of an archive, checked before each member is unpacked.
+#[derive(Debug, Clone, Copy)]
+struct UnpackLimits {
+    max_total_size: u64,
+    max_entry_count: u64,
+}
+
+impl UnpackLimits {
+    /// Count the member of the size, error if the archive exceeds the limits with it.
+    fn count_member(&mut self, size: u64) -> Result<(), FileException> {
+        if self.max_entry_count == 0 {
+            return Err(FileException::ArchiveLimitExceeded {
+                reason: "too many members".to_owned(),
+            });
+        }
+        self.max_entry_count -= 1;
+        self.max_total_size =
+            self.max_total_size
+                .checked_sub(size)
+                .ok_or(FileException::ArchiveLimitExceeded {
+                    reason: "too large members in total".to_owned(),
+                })?;
+        Ok(())
+    }
+}
+
+fn unpack_tar<R: Read>(reader: R, dir: &Path, mut limits: UnpackLimits) -> anyhow::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    archive.set_preserve_permissions(false);
+    std::fs::create_dir_all(dir)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        limits.count_member(entry.header().size()?)?;
+        entry.unpack_in(dir)?;
+    }
+    Ok(())
+}
+
+fn unpack_zip<R: Read + Seek>(
+    reader: R,
+    dir: &Path,
+    mut limits: UnpackLimits,
+) -> anyhow::Result<()> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    std::fs::create_dir_all(dir)?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let size = file.size();
+        limits.count_member(size)?;
+        let path = match file.enclosed_name() {
+            Some(el) => dir.join(el),
+            None => bail!("Invalid member path: {}", file.name()),
+        };
+        // Symbolic links are skipped like when uploading.
+        let is_symlink = file.unix_mode().is_some_and(|mode| mode & 0o170000 == 0o120000);
+        if file.is_dir() {
+            std::fs::create_dir_all(&path)?;
+        } else if !is_symlink {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut output = std::fs::File::create(&path)?;
+            // The declared size is checked against the limits, so never write more than it.
+            let written = std::io::copy(&mut (&mut file).take(size + 1), &mut output)?;
+            if written > size {
+                bail!("Member: {} is larger than its declared size", file.name());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(TypedBuilder)]
+pub struct ArchiveExtractServiceImpl {
+    net_disk_repo: Arc<dyn ReadOnlyRepository<NetDisk>>,
+    net_disk_service: Arc<dyn NetDiskService>,
+    meta_storage_service: Arc<dyn MetaStorageService>,
+    upload_service: Arc<dyn StorageServerUploadDispatcherService>,
+    download_service: Arc<dyn StorageServerDownloadDispatcherService>,
+    cache_service: Arc<dyn CacheService>,
+    extract_sender_and_topic: (
+        Arc<dyn MessageQueueProducerTemplate<ExtractArchiveCommand>>,
+        String,
+    ),
+    /// Directory to unpack archives in before they are uploaded.
+    #[builder(default = "base_dir".into(), setter(into))]
+    base: PathBuf,
+    /// Max total size of members of an archive.
+    #[builder(default = 10 * 1024 * 1024 * 1024)]
+    max_total_size: u64,
+    /// Max count of members of an archive, including directories.
+    #[builder(default = 10000)]
+    max_entry_count: u64,
+    #[builder(default)]
+    user_id: Option<Uuid>,
+}
+
+#[async_trait]
+impl ArchiveExtractService for ArchiveExtractServiceImpl {
+    async fn extract(&self, id: Uuid, parent_id: Option<Uuid>) -> anyhow::Result<Uuid> {
+        let archive = self.net_disk_repo.get_by_id(id).await?;
+        let (_, _, dir_name) = parse_archive(&archive)?;
+        let user_id = self.user_id.ok_or(anyhow!("No provided user id in archive extract"))?;
+
+        let parent_id = parent_id.or(archive.parent_id);
+        let dir_id = self.net_disk_service.create_dir(parent_id, &dir_name).await?;
+        let command = ExtractArchiveCommand {
+            archive_id: id,
+            dir_id,
+            user_id,
+        };
+        let (sender, topic) = &self.extract_sender_and_topic;
+        if let Err(e) = sender.send_object(&command, topic).await {
+            self.delete_dir(dir_id).await;
+            return Err(e);
+        }
+        Ok(dir_id)
+    }
+
+    async fn run_extraction(&self, archive_id: Uuid, dir_id: Uuid) -> anyhow::Result<()> {
+        let work_dir = self.base.join(format!("extract/{}", Uuid::new_v4()));
+        let result = self.extract_into(archive_id, dir_id, &work_dir).await;
+        if tokio::fs::try_exists(&work_dir).await.unwrap_or_default() {
+            if let Err(e) = tokio::fs::remove_dir_all(&work_dir).await {
+                tracing::error!("Failed to remove unpacked archive: {work_dir:?}, {e}");
+            }
+        }
+        if result.is_err() {
+            self.delete_dir(dir_id).await;
+        }
+        result
+    }
+}
+
+/// Get file meta, kind and name without extension of the archive.
+fn parse_archive(archive: &NetDisk) -> Result<(Uuid, ArchiveKind, String), FileException> {
+    match (
+        archive.file_metadata_id,
+        ArchiveKind::from_file_name(&archive.name),
+    ) {
+        (Some(meta_id), Some((kind, dir_name))) if !archive.is_dict => {
+            Ok((meta_id, kind, dir_name.to_owned()))
+        }
+        _ => Err(FileException::UnsupportedArchive {
+            name: archive.name.to_owned(),
+        }),
+    }
+}
+
+impl ArchiveExtractServiceImpl {
+    /// Download the archive into the work directory, unpack it there and upload the members.
+    async fn extract_into(
+        &self,
+        archive_id: Uuid,
+        dir_id: Uuid,
+        work_dir: &Path,
+    ) -> anyhow::Result<()> {
+        let archive = self.net_disk_repo.get_by_id(archive_id).await?;
+        let (meta_id, kind, _) = parse_archive(&archive)?;
+        tokio::fs::create_dir_all(work_dir).await?;
+        let archive_path = work_dir.join("archive");
+        self.download_archive(meta_id, &archive_path).await?;
+
+        let unpack_dir = work_dir.join("unpacked");
+        let dir = unpack_dir.to_owned();
+        let limits = UnpackLimits {
+            max_total_size: self.max_total_size,
+            max_entry_count: self.max_entry_count,
+        };
+        tokio::task::spawn_blocking(move || kind.unpack(&archive_path, &dir, limits))
+            .await
+            .map_err(|e| anyhow!("Unpack task of archive: {archive_id} is aborted: {e}"))??;
+        self.upload_dir(&unpack_dir, dir_id).await
+    }
+
+    /// Write the archive into the file by ranges.
+    async fn download_archive(&self, meta_id: Uuid, path: &Path) -> anyhow::Result<()> {
+        let size = self.download_service.get_file_size(meta_id).await?;
+        let mut file = tokio::fs::File::create(path).await?;
+        let mut start = 0;
+        while start < size {
+            let end = (start + CHUNK_SIZE).min(size);
+            let chunk = self
+                .download_service
+                .rangely_get_file(meta_id, &[start..end])
+                .await?
+                .pop()
+                .ok_or(anyhow!(
+                    "No content of archive: {meta_id} in range {start}..{end}"
+                ))?;
+            file.write_all(&chunk).await?;
+            start = end;
+        }
+        file.flush().await?;
+        Ok(())
+    }
+
+    async fn delete_dir(&self, dir_id: Uuid) {
+        if let Err(e) = self.net_disk_service.delete(dir_id).await {
+            tracing::error!("Failed to delete directory: {dir_id} of a failed extraction, {e}");
+        }
+    }
+
+    /// Upload the unpacked directory into the net disk directory, symbolic links are skipped.
+    async fn upload_dir(&self, unpack_dir: &Path, dir_id: Uuid) -> anyhow::Result<()> {
+        let mut pending = vec![(unpack_dir.to_owned(), dir_id)];
+        while let Some((path, dir_id)) = pending.pop() {
+            let mut entries = tokio::fs::read_dir(&path).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let file_type = entry.file_type().await?;
+                let name = entry.file_name().to_string_lossy().to_string();
+                if file_type.is_dir() {
+                    let child_id = self.net_disk_service.create_dir(Some(dir_id), &name).await?;
+                    pending.push((entry.path(), child_id));
+                } else if file_type.is_file() {
+                    self.upload_file(&entry.path(), name, dir_id).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn upload_file(&self, path: &Path, name: String, dir_id: Uuid) -> anyhow::Result<()> {
+        let hash_algorithm = HashAlgorithm::Blake3;
+        let mut hasher = ContentHasher::new(&hash_algorithm);
+        let mut size = 0;
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let n = file.read(&mut buffer).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+            size += n as u64;
+        }
+        let hash = hasher.finalize();
+
+        let meta_id =
+            match self.meta_storage_service.satisfy_flash_upload(&hash, &hash_algorithm).await? {
+                Some(el) => el,
+                None => {
+                    let meta_id = Uuid::new_v4();
+                    let file_storage_infos = self.upload_content(meta_id, path, size).await?;
+                    self.meta_storage_service
+                        .record_meta_and_storage(
+                            meta_id,
+                            RecordFileMeta {
+                                name: name.to_owned(),
+                                hash,
+                                hash_algorithm,
+                                size,
+                            },
+                            file_storage_infos,
+                        )
+                        .await?;
+                    meta_id
+                }
+            };
+        self.net_disk_service
+            .create_file(CreateNetDiskFileCommand {
+                meta_id,
+                file_name: name,
+                file_type: FileType::Unkonwn,
+                kind: RecordNetDiskKind::Normal {
+                    parent_id: Some(dir_id),
+                },
+            })
+            .await
+    }
+
+    /// Upload the file part by part, returns storages of its replicas.
+    async fn upload_content(
+        &self,
+        meta_id: Uuid,
+        path: &Path,
+        size: u64,
+    ) -> anyhow::Result<Vec<RecordFileStorage>> {
+        let part_count = size.div_ceil(CHUNK_SIZE).max(1);
+        let uploaded = match self.cache_parts(meta_id, path, part_count).await {
+            Ok(()) => {
+                let parts = CachedParts {
+                    meta_id,
+                    part_count,
+                    cache_service: self.cache_service.clone(),
+                };
+                let hint = PlacementHint {
+                    user_id: self.user_id,
+                    ..Default::default()
+                };
+                self.upload_service.upload(&parts, size, &hint).await
+            }
+            Err(e) => Err(e),
+        };
+        self.cache_service
+            .operate(CacheOperateCommand::RemoveMultipartDir { meta_id })
+            .await?;
+        Ok(uploaded?
+            .iter()
+            .map(|el| RecordFileStorage {
+                storage_server_id: el.storage_server_id,
+                server_url: el.to_string(),
+            })
+            .collect())
+    }
+
+    async fn cache_parts(&self, meta_id: Uuid, path: &Path, part_count: u64) -> anyhow::Result<()> {
+        let mut file = tokio::fs::File::open(path).await?;
+        for nth in 0..part_count {
+            let mut content = vec![];
+            (&mut file).take(CHUNK_SIZE).read_to_end(&mut content).await?;
+            self.cache_service
+                .operate(CacheOperateCommand::WritePart(Part {
+                    meta_id,
+                    nth,
+                    content,
+                }))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_archive_kind() {
+        assert_eq!(
+            ArchiveKind::from_file_name("data.tar.gz"),
+            Some((ArchiveKind::TarGz, "data"))
+        );
+        assert_eq!(
+            ArchiveKind::from_file_name("Data.TGZ"),
+            Some((ArchiveKind::TarGz, "Data"))
+        );
+        assert_eq!(
+            ArchiveKind::from_file_name("data.v1.tar"),
+            Some((ArchiveKind::Tar, "data.v1"))
+        );
+        assert_eq!(
+            ArchiveKind::from_file_name("data.zip"),
+            Some((ArchiveKind::Zip, "data"))
+        );
+        assert_eq!(ArchiveKind::from_file_name("data.gz"), None);
+    }
+
+    const LIMITS: UnpackLimits = UnpackLimits {
+        max_total_size: 1024,
+        max_entry_count: 10,
+    };
+
+    /// Write the archive into the directory and unpack it into `unpacked`.
+    fn unpack(
+        dir: &TempDir,
+        kind: ArchiveKind,
+        content: &[u8],
+        limits: UnpackLimits,
+    ) -> anyhow::Result<()> {
+        let archive = dir.path().join("archive");
+        std::fs::write(&archive, content).unwrap();
+        kind.unpack(&archive, &dir.path().join("unpacked"), limits)
+    }
+
+    fn tar_of(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, path, *content).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_unpack_tar() {
+        let dir = tempfile::tempdir().unwrap();
+        unpack(
+            &dir,
+            ArchiveKind::Tar,
+            &tar_of(&[("inputs/a.txt", b"abc")]),
+            LIMITS,
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read(dir.path().join("unpacked/inputs/a.txt")).unwrap(),
+            b"abc"
+        );
+    }
+
+    #[test]
+    fn test_unpack_zip() {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+        writer.start_file("inputs/a.txt", Default::default()).unwrap();
+        std::io::Write::write_all(&mut writer, b"abc").unwrap();
+        let content = writer.finish().unwrap().into_inner();
+
+        let dir = tempfile::tempdir().unwrap();
+        unpack(&dir, ArchiveKind::Zip, &content, LIMITS).unwrap();
+        assert_eq!(
+            std::fs::read(dir.path().join("unpacked/inputs/a.txt")).unwrap(),
+            b"abc"
+        );
+    }
+
+    #[test]
+    fn test_unpack_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let too_large = tar_of(&[("a.txt", &[0; 1000]), ("b.txt", &[0; 100])]);
+        let e = unpack(&dir, ArchiveKind::Tar, &too_large, LIMITS).unwrap_err();
+        assert!(matches!(
+            e.downcast_ref::<FileException>(),
+            Some(FileException::ArchiveLimitExceeded { .. })
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        let files = (0..11).map(|i| format!("{i}.txt")).collect::<Vec<_>>();
+        let too_many = tar_of(&files.iter().map(|el| (el.as_str(), &b""[..])).collect::<Vec<_>>());
+        let e = unpack(&dir, ArchiveKind::Tar, &too_many, LIMITS).unwrap_err();
+        assert!(matches!(
+            e.downcast_ref::<FileException>(),
+            Some(FileException::ArchiveLimitExceeded { .. })
+        ));
+    }
+}
@@ -2,7 +2,7 @@ use anyhow::anyhow;
 use async_trait::async_trait;
 use domain_storage::{
     command::{CacheOperateCommand, CacheReadCommand},
-    service::CacheService,
+    service::{CacheService, MultipartDir},
 };
 use std::path::{Path, PathBuf};
 use typed_builder::TypedBuilder;
@@ -15,13 +15,12 @@ pub struct LocalCacheServiceImpl {
 }
 
 impl LocalCacheServiceImpl {
-    fn normal_path(&self, meta_id: Uuid) -> PathBuf {
-        self.base.join(format!("normal/{meta_id}"))
-    }
-
     fn part_path(&self, meta_id: Uuid, nth: u64) -> PathBuf {
         self.base.join(format!("multipart/{meta_id}/{nth}"))
     }
+    fn multipart_base(&self) -> PathBuf {
+        self.base.join("multipart")
+    }
     fn multipart_dir(&self, meta_id: Uuid) -> PathBuf {
         self.base.join(format!("multipart/{meta_id}"))
     }
@@ -40,10 +39,6 @@ impl CacheService for LocalCacheServiceImpl {
     async fn operate(&self, cmd: CacheOperateCommand) -> anyhow::Result<()> {
         use CacheOperateCommand::*;
         match cmd {
-            WriteNormal { meta_id, content } => {
-                let path = self.normal_path(meta_id);
-                create_parent_and_write(&path, &content).await?;
-            }
             WritePart(part) => {
                 let path = self.part_path(part.meta_id, part.nth);
                 create_parent_and_write(&path, &part.content).await?;
@@ -52,12 +47,7 @@ impl CacheService for LocalCacheServiceImpl {
                 let dir = self.multipart_dir(meta_id);
                 tokio::fs::remove_dir_all(dir).await?;
             }
-            RemoveNormal { meta_id } => {
-                let path = self.normal_path(meta_id);
-                tokio::fs::remove_file(path).await?;
-            }
-            ChangeNormalToSnapshot { meta_id } => {
-                let normal_path = self.normal_path(meta_id);
+            ChangeMultipartToSnapshot { meta_id } => {
                 let snapshot_path = self.snapshot_path(meta_id);
                 tokio::fs::create_dir_all(
                     &snapshot_path
@@ -65,7 +55,20 @@ impl CacheService for LocalCacheServiceImpl {
                         .ok_or(anyhow!("path: {snapshot_path:?} doesn't has parent."))?,
                 )
                 .await?;
-                tokio::fs::rename(normal_path, snapshot_path).await?;
+                // Copy parts one by one, so that the whole file is never loaded into memory.
+                let dir = self.multipart_dir(meta_id);
+                let mut entries = tokio::fs::read_dir(&dir).await?;
+                let mut part_count = 0;
+                while entries.next_entry().await?.is_some() {
+                    part_count += 1;
+                }
+                let mut snapshot = tokio::fs::File::create(&snapshot_path).await?;
+                for nth in 0..part_count {
+                    let mut part = tokio::fs::File::open(self.part_path(meta_id, nth)).await?;
+                    tokio::io::copy(&mut part, &mut snapshot).await?;
+                }
+                snapshot.sync_all().await?;
+                tokio::fs::remove_dir_all(dir).await?;
             }
             RemoveSnapshot { meta_id } => {
                 let path = self.snapshot_path(meta_id);
@@ -92,12 +95,29 @@ impl CacheService for LocalCacheServiceImpl {
                 let path = self.part_path(meta_id, nth);
                 tokio::fs::read(path).await?
             }
-            ReadNormal { meta_id } => {
-                let path = self.normal_path(meta_id);
-                tokio::fs::read(path).await?
-            }
         })
     }
+
+    async fn get_multipart_dirs(&self) -> anyhow::Result<Vec<MultipartDir>> {
+        let base = self.multipart_base();
+        if !tokio::fs::try_exists(&base).await? {
+            return Ok(vec![]);
+        }
+        let mut dirs = vec![];
+        let mut entries = tokio::fs::read_dir(&base).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let meta_id = match Uuid::parse_str(&entry.file_name().to_string_lossy()) {
+                Ok(el) => el,
+                Err(_) => continue,
+            };
+            let modified_at = entry.metadata().await?.modified()?.into();
+            dirs.push(MultipartDir {
+                meta_id,
+                modified_at,
+            });
+        }
+        Ok(dirs)
+    }
 }
 
 #[cfg(test)]
@@ -130,22 +150,31 @@ mod tests {
         service.operate(cmd1).await.unwrap();
         let content = service.read(ReadPart { meta_id, nth: 0 }).await.unwrap();
         assert_eq!(b"456", content.as_slice());
+        let dirs = service.get_multipart_dirs().await.unwrap();
+        assert!(dirs.iter().any(|el| el.meta_id == meta_id));
         service.operate(RemoveMultipartDir { meta_id }).await.unwrap();
+        let dirs = service.get_multipart_dirs().await.unwrap();
+        assert!(!dirs.iter().any(|el| el.meta_id == meta_id));
     }
 
     #[tokio::test]
     async fn snapshot() {
         let service = load();
         let meta_id = Uuid::new_v4();
-        service
-            .operate(WriteNormal {
-                meta_id,
-                content: b"789".to_vec(),
-            })
-            .await
-            .unwrap();
-        service.operate(ChangeNormalToSnapshot { meta_id }).await.unwrap();
+        for (nth, content) in [&b"78"[..], &b"9"[..]].iter().enumerate() {
+            service
+                .operate(WritePart(Part {
+                    meta_id,
+                    content: content.to_vec(),
+                    nth: nth as u64,
+                }))
+                .await
+                .unwrap();
+        }
+        service.operate(ChangeMultipartToSnapshot { meta_id }).await.unwrap();
         service.operate(IsSnapshotExists { meta_id }).await.unwrap();
+        let content = service.read(ReadSnapshot { meta_id }).await.unwrap();
+        assert_eq!(b"789", content.as_slice());
         service.operate(RemoveSnapshot { meta_id }).await.unwrap();
     }
 }
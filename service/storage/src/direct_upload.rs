@@ -0,0 +1,378 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use domain_storage::{
+    exception::{FileException, FileResult},
+    model::{
+        entity::{MoveRegistration, StorageServer, StorageType},
+        vo::{HashAlgorithm, MoveDestination, PlacementHint, PresignedUpload, ServerUrl},
+    },
+    service::{
+        DirectUploadService, FileMoveService, MultipartService, StorageServerBrokerService,
+        StorageServerResourceService,
+    },
+};
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+use crate::hasher::ContentHasher;
+
+/// Max count of parts of an S3 multipart upload.
+const MAX_PART_COUNT: u64 = 10_000;
+/// Size of each read from the server when the hash is verified.
+const VERIFY_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+#[derive(TypedBuilder)]
+pub struct DirectUploadServiceImpl {
+    multipart_service: Arc<dyn MultipartService>,
+    file_move_service: Arc<dyn FileMoveService>,
+    resources_service: Arc<dyn StorageServerResourceService>,
+    storage_server_broker_service: Arc<dyn StorageServerBrokerService>,
+    /// Clients must be able to reach the object storage to upload directly.
+    #[builder(default)]
+    enabled: bool,
+    /// Seconds before presigned urls expire.
+    #[builder(default = 6 * 60 * 60)]
+    url_expire_secs: u64,
+    /// Read the assembled file back from the server to verify its hash, files are deduplicated
+    /// by hash so a file with a wrong hash is served to anyone uploading the declared hash.
+    #[builder(default = true)]
+    verify_hash: bool,
+    /// Number of copies stored on different servers, parts are uploaded to only one server.
+    #[builder(default = 1)]
+    replicas: usize,
+    #[builder(default)]
+    user_id: Option<Uuid>,
+}
+
+impl DirectUploadServiceImpl {
+    /// Hash the file on the server chunk by chunk.
+    async fn hash_file(
+        &self,
+        storage_server: &StorageServer,
+        meta_id: Uuid,
+        hash_algorithm: &HashAlgorithm,
+    ) -> anyhow::Result<String> {
+        let size = self
+            .storage_server_broker_service
+            .get_file_size(storage_server, meta_id)
+            .await?;
+        let mut hasher = ContentHasher::new(hash_algorithm);
+        let mut start = 0;
+        while start < size {
+            let end = (start + VERIFY_CHUNK_SIZE).min(size);
+            let chunks = self
+                .storage_server_broker_service
+                .rangely_get_file(storage_server, meta_id, &[start..end])
+                .await?;
+            for chunk in chunks {
+                hasher.update(&chunk);
+            }
+            start = end;
+        }
+        Ok(hasher.finalize())
+    }
+}
+
+#[async_trait]
+impl DirectUploadService for DirectUploadServiceImpl {
+    async fn prepare(
+        &self,
+        registration: MoveRegistration,
+        count: u64,
+    ) -> FileResult<PresignedUpload> {
+        if !self.enabled {
+            return Err(FileException::DirectUploadUnavailable {
+                reason: "it is disabled".to_string(),
+            });
+        }
+        if !matches!(
+            registration.destination,
+            MoveDestination::StorageServer { .. }
+        ) {
+            return Err(FileException::DirectUploadUnavailable {
+                reason: "only files moved to storage servers can be uploaded directly".to_string(),
+            });
+        }
+        if self.replicas > 1 {
+            return Err(FileException::DirectUploadUnavailable {
+                reason: format!(
+                    "files are stored with {} replicas, but parts are uploaded to one server",
+                    self.replicas
+                ),
+            });
+        }
+        if count == 0 || count > MAX_PART_COUNT {
+            return Err(FileException::DirectUploadUnavailable {
+                reason: format!("count of parts must be within 1 to {MAX_PART_COUNT}"),
+            });
+        }
+        self.file_move_service.if_possible_do_flash_upload(&registration).await?;
+
+        let meta_id = registration.meta_id;
+        let hint = PlacementHint {
+            user_id: self.user_id,
+            ..Default::default()
+        };
+        let storage_server = self
+            .resources_service
+            .place(registration.size, &hint)
+            .await?
+            .into_iter()
+            .find(|el| matches!(el.storage_type, StorageType::ObjectStorage { .. }))
+            .ok_or(FileException::DirectUploadUnavailable {
+                reason: "no object storage server can store the file".to_string(),
+            })?;
+        let presigned = self
+            .storage_server_broker_service
+            .create_direct_upload(
+                &storage_server,
+                meta_id,
+                count,
+                Duration::from_secs(self.url_expire_secs),
+            )
+            .await?;
+        if let Err(e) = self
+            .multipart_service
+            .create_direct(
+                meta_id,
+                &registration.hash,
+                registration.hash_algorithm.to_owned(),
+                count,
+                presigned.upload.to_owned(),
+            )
+            .await
+        {
+            if let Err(abort_error) = self
+                .storage_server_broker_service
+                .abort_direct_upload(&storage_server, meta_id, &presigned.upload)
+                .await
+            {
+                tracing::error!("Failed to abort direct upload of file {meta_id}: {abort_error}");
+            }
+            return Err(e);
+        }
+        self.file_move_service.register_move(registration).await?;
+        Ok(presigned)
+    }
+
+    async fn complete(&self, meta_id: Uuid) -> FileResult<Vec<ServerUrl>> {
+        let multipart = self.multipart_service.info(meta_id).await?;
+        let upload = multipart.direct_upload.ok_or(FileException::DirectUploadUnavailable {
+            reason: format!("file {meta_id} isn't uploaded directly"),
+        })?;
+        let storage_server =
+            self.resources_service.get_storage_server(upload.storage_server_id).await?;
+        let server_url = self
+            .storage_server_broker_service
+            .complete_direct_upload(&storage_server, meta_id, &upload, multipart.part_count)
+            .await?;
+        if self.verify_hash {
+            let hash = self.hash_file(&storage_server, meta_id, &multipart.hash_algorithm).await?;
+            if hash.ne(&multipart.hash) {
+                self.storage_server_broker_service.delete(&storage_server, meta_id).await?;
+                return Err(FileException::UnmatchedHash {
+                    meta_id,
+                    provided_hash: multipart.hash,
+                    completed_hash: hash,
+                });
+            }
+        }
+        Ok(vec![server_url])
+    }
+
+    async fn abort(&self, meta_id: Uuid) -> FileResult<()> {
+        let multipart = match self.multipart_service.info(meta_id).await {
+            Ok(el) => el,
+            Err(FileException::MultipartNotFound { .. }) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let upload = match multipart.direct_upload {
+            Some(el) => el,
+            None => return Ok(()),
+        };
+        let storage_server =
+            self.resources_service.get_storage_server(upload.storage_server_id).await?;
+        self.storage_server_broker_service
+            .abort_direct_upload(&storage_server, meta_id, &upload)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use domain_storage::{
+        mock::{
+            MockFileMoveService, MockMultipartService, MockStorageServerBrokerService,
+            MockStorageServerResourceService,
+        },
+        model::{
+            entity::{FileSystemOption, ObjectServerOption},
+            vo::DirectUpload,
+        },
+    };
+
+    use super::*;
+
+    fn registration() -> MoveRegistration {
+        MoveRegistration {
+            id: Uuid::new_v4(),
+            meta_id: Uuid::new_v4(),
+            file_name: "result.txt".to_owned(),
+            hash: "5D41402ABC4B2A76B9719D911017C592".to_owned(),
+            hash_algorithm: HashAlgorithm::Blake3,
+            size: 16 * 1024 * 1024,
+            destination: MoveDestination::StorageServer {
+                record_net_disk: None,
+            },
+            is_upload_failed: false,
+            failed_reason: None,
+        }
+    }
+
+    fn server(storage_type: StorageType) -> StorageServer {
+        StorageServer {
+            id: Uuid::new_v4(),
+            name: String::new(),
+            capacity: 1024 * 1024 * 1024,
+            storage_type,
+        }
+    }
+
+    struct Mocks {
+        multipart_service: MockMultipartService,
+        file_move_service: MockFileMoveService,
+        resources_service: MockStorageServerResourceService,
+        broker_service: MockStorageServerBrokerService,
+    }
+
+    impl Mocks {
+        fn new() -> Self {
+            Self {
+                multipart_service: MockMultipartService::new(),
+                file_move_service: MockFileMoveService::new(),
+                resources_service: MockStorageServerResourceService::new(),
+                broker_service: MockStorageServerBrokerService::new(),
+            }
+        }
+
+        fn build(self, replicas: usize) -> DirectUploadServiceImpl {
+            DirectUploadServiceImpl::builder()
+                .multipart_service(Arc::new(self.multipart_service))
+                .file_move_service(Arc::new(self.file_move_service))
+                .resources_service(Arc::new(self.resources_service))
+                .storage_server_broker_service(Arc::new(self.broker_service))
+                .enabled(true)
+                .replicas(replicas)
+                .build()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prepare() {
+        let registration = registration();
+        let meta_id = registration.meta_id;
+        let object_server = server(StorageType::ObjectStorage {
+            options: ObjectServerOption::default(),
+        });
+        let object_server_id = object_server.id;
+        let fs_server = server(StorageType::FileSystem {
+            options: FileSystemOption::default(),
+        });
+
+        let mut mocks = Mocks::new();
+        mocks
+            .file_move_service
+            .expect_if_possible_do_flash_upload()
+            .returning(|_| Ok(()));
+        mocks
+            .resources_service
+            .expect_place()
+            .returning(move |_, _| Ok(vec![fs_server.clone(), object_server.clone()]));
+        // Only object storage servers support direct uploads.
+        mocks
+            .broker_service
+            .expect_create_direct_upload()
+            .withf(move |server, id, count, _| {
+                server.id == object_server_id && *id == meta_id && *count == 2
+            })
+            .times(1)
+            .returning(|server, _, count, _| {
+                Ok(PresignedUpload {
+                    upload: DirectUpload {
+                        storage_server_id: server.id,
+                        upload_id: "upload".to_owned(),
+                    },
+                    part_urls: (0..count).map(|nth| format!("http://s3/{nth}")).collect(),
+                    expires_at: Utc::now(),
+                })
+            });
+        mocks
+            .multipart_service
+            .expect_create_direct()
+            .withf(move |id, _, _, count, upload| {
+                *id == meta_id && *count == 2 && upload.storage_server_id == object_server_id
+            })
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(()));
+        mocks.file_move_service.expect_register_move().times(1).returning(|_| Ok(()));
+
+        let presigned = mocks.build(1).prepare(registration, 2).await.unwrap();
+        assert_eq!(presigned.part_urls.len(), 2);
+        assert_eq!(presigned.upload.storage_server_id, object_server_id);
+    }
+
+    #[tokio::test]
+    async fn test_prepare_abort_when_multipart_conflicts() {
+        let registration = registration();
+        let meta_id = registration.meta_id;
+        let object_server = server(StorageType::ObjectStorage {
+            options: ObjectServerOption::default(),
+        });
+
+        let mut mocks = Mocks::new();
+        mocks
+            .file_move_service
+            .expect_if_possible_do_flash_upload()
+            .returning(|_| Ok(()));
+        mocks
+            .resources_service
+            .expect_place()
+            .returning(move |_, _| Ok(vec![object_server.clone()]));
+        mocks.broker_service.expect_create_direct_upload().returning(|server, _, _, _| {
+            Ok(PresignedUpload {
+                upload: DirectUpload {
+                    storage_server_id: server.id,
+                    upload_id: "upload".to_owned(),
+                },
+                part_urls: vec![],
+                expires_at: Utc::now(),
+            })
+        });
+        mocks
+            .multipart_service
+            .expect_create_direct()
+            .returning(|meta_id, _, _, _, _| Err(FileException::ConflictedId { meta_id }));
+        mocks
+            .broker_service
+            .expect_abort_direct_upload()
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        mocks.file_move_service.expect_register_move().never();
+
+        let e = mocks.build(1).prepare(registration, 2).await.err().unwrap();
+        assert!(matches!(e, FileException::ConflictedId { meta_id: el } if el == meta_id));
+    }
+
+    #[tokio::test]
+    async fn test_prepare_reject_replicas() {
+        let mut mocks = Mocks::new();
+        mocks.broker_service.expect_create_direct_upload().never();
+        mocks.file_move_service.expect_register_move().never();
+
+        let e = mocks.build(2).prepare(registration(), 2).await.err().unwrap();
+        assert!(matches!(e, FileException::DirectUploadUnavailable { .. }));
+    }
+}
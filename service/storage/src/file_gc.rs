@@ -0,0 +1,293 @@
+use std::sync::Arc;
+
+use anyhow::bail;
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use domain_storage::{
+    model::{entity::FileMeta, vo::FileReferences},
+    repository::{FileMetaRepo, FileStorageRepo, SnapshotRepo},
+    service::{FileGcService, StorageServerBrokerService, StorageServerResourceService},
+};
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+use crate::snapshot::hash_key_regex;
+
+#[derive(TypedBuilder)]
+pub struct FileGcServiceImpl {
+    meta_repo: Arc<dyn FileMetaRepo>,
+    storage_repo: Arc<dyn FileStorageRepo>,
+    snapshot_repo: Arc<dyn SnapshotRepo>,
+    resources_service: Arc<dyn StorageServerResourceService>,
+    storage_server_broker_service: Arc<dyn StorageServerBrokerService>,
+    /// Files are kept for it after their last reference is removed, or after they are uploaded
+    /// if never referenced, so that a file is not collected before it is referenced.
+    #[builder(default = 7 * 24 * 60 * 60)]
+    grace_secs: i64,
+    /// Max count of files collected in one run.
+    #[builder(default = 100)]
+    batch_size: u64,
+}
+
+impl FileGcServiceImpl {
+    /// Snapshots are deduplicated by hash as well, and kept out of the database.
+    async fn count_snapshots(&self, meta: &FileMeta) -> anyhow::Result<u64> {
+        Ok(self
+            .snapshot_repo
+            .get_all_by_key_regex(&hash_key_regex(&meta.hash, &meta.hash_algorithm))
+            .await?
+            .len() as u64)
+    }
+
+    /// Delete all replicas of the tombstoned file, then its records. Objects go first so that
+    /// a failed run is retried by the next one.
+    async fn delete_file(&self, meta: &FileMeta) -> anyhow::Result<()> {
+        for storage in self.storage_repo.get_all_by_meta_id(meta.id).await? {
+            let storage_server =
+                self.resources_service.get_storage_server(storage.storage_server_id).await?;
+            self.storage_server_broker_service.delete(&storage_server, meta.id).await?;
+        }
+        self.storage_repo.delete_by_meta_id(meta.id).await?;
+        self.meta_repo.delete_by_id(meta.id).await?;
+        self.meta_repo.save_changed().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl FileGcService for FileGcServiceImpl {
+    async fn get_references(&self, meta_id: Uuid) -> anyhow::Result<FileReferences> {
+        let meta = self.meta_repo.get_by_id(meta_id).await?;
+        let mut references = self.meta_repo.get_references(meta_id).await?;
+        references.snapshot = self.count_snapshots(&meta).await?;
+        Ok(references)
+    }
+
+    async fn collect(&self) -> anyhow::Result<usize> {
+        let dereferenced_before = Utc::now() - Duration::seconds(self.grace_secs);
+        let candidates =
+            self.meta_repo.get_unreferenced(dereferenced_before, self.batch_size).await?;
+
+        let mut collected = 0;
+        let mut errors = vec![];
+        for meta in candidates {
+            if self.count_snapshots(&meta).await? > 0 {
+                continue;
+            }
+            // The file may be referenced since the candidates are queried, or by a flash upload
+            // at any time. Once tombstoned, neither can happen.
+            if !self.meta_repo.tombstone(meta.id).await? {
+                continue;
+            }
+            // Snapshots are kept out of the database, one may be taken by hash before the
+            // tombstone, and a reference may be committed right after it.
+            let references = self.meta_repo.get_references(meta.id).await?;
+            if references.total() + self.count_snapshots(&meta).await? > 0 {
+                self.meta_repo.restore(meta.id).await?;
+                continue;
+            }
+            match self.delete_file(&meta).await {
+                Ok(()) => collected += 1,
+                Err(e) => errors.push(format!("{}: {e}", meta.id)),
+            }
+        }
+        if !errors.is_empty() {
+            bail!(
+                "Collected {collected} files, failed to collect: {}",
+                errors.join("; ")
+            );
+        }
+        Ok(collected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain_storage::{
+        mock::{
+            MockFileMetaRepo, MockFileStorageRepo, MockSnapshotRepo,
+            MockStorageServerBrokerService, MockStorageServerResourceService,
+        },
+        model::{
+            entity::{FileStorage, FileSystemOption, Snapshot, StorageServer, StorageType},
+            vo::HashAlgorithm,
+        },
+    };
+
+    use super::*;
+
+    fn meta() -> FileMeta {
+        FileMeta {
+            id: Uuid::new_v4(),
+            name: "result.txt".to_owned(),
+            hash: "5d41402abc4b2a76b9719d911017c592".to_owned(),
+            hash_algorithm: HashAlgorithm::Blake3,
+            size: 5,
+        }
+    }
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            id: Uuid::new_v4(),
+            meta_id: Uuid::new_v4(),
+            node_id: Uuid::new_v4(),
+            file_id: Uuid::new_v4(),
+            timestamp: 0,
+            file_name: "result.txt".to_owned(),
+            size: 5,
+            hash: "5d41402abc4b2a76b9719d911017c592".to_owned(),
+            hash_algorithm: HashAlgorithm::Blake3,
+        }
+    }
+
+    struct Mocks {
+        meta_repo: MockFileMetaRepo,
+        storage_repo: MockFileStorageRepo,
+        snapshot_repo: MockSnapshotRepo,
+        resources_service: MockStorageServerResourceService,
+        broker_service: MockStorageServerBrokerService,
+    }
+
+    impl Mocks {
+        /// One unreferenced candidate without snapshots.
+        fn new(candidate: FileMeta) -> Self {
+            let mut meta_repo = MockFileMetaRepo::new();
+            meta_repo
+                .expect_get_unreferenced()
+                .returning(move |_, _| Ok(vec![candidate.clone()]));
+            meta_repo.expect_get_references().returning(|_| Ok(FileReferences::default()));
+            let mut snapshot_repo = MockSnapshotRepo::new();
+            snapshot_repo.expect_get_all_by_key_regex().returning(|_| Ok(vec![]));
+            Self {
+                meta_repo,
+                storage_repo: MockFileStorageRepo::new(),
+                snapshot_repo,
+                resources_service: MockStorageServerResourceService::new(),
+                broker_service: MockStorageServerBrokerService::new(),
+            }
+        }
+
+        fn build(self) -> FileGcServiceImpl {
+            FileGcServiceImpl::builder()
+                .meta_repo(Arc::new(self.meta_repo))
+                .storage_repo(Arc::new(self.storage_repo))
+                .snapshot_repo(Arc::new(self.snapshot_repo))
+                .resources_service(Arc::new(self.resources_service))
+                .storage_server_broker_service(Arc::new(self.broker_service))
+                .grace_secs(60)
+                .build()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_tombstoned() {
+        let meta = meta();
+        let meta_id = meta.id;
+        let server_id = Uuid::new_v4();
+        let mut mocks = Mocks::new(meta);
+        mocks
+            .meta_repo
+            .expect_tombstone()
+            .withf(move |id| *id == meta_id)
+            .times(1)
+            .returning(|_| Ok(true));
+        mocks.storage_repo.expect_get_all_by_meta_id().returning(move |id| {
+            Ok(vec![FileStorage {
+                storage_server_id: server_id,
+                meta_id: id,
+                server_url: id.to_string(),
+            }])
+        });
+        mocks.resources_service.expect_get_storage_server().returning(|id| {
+            Ok(StorageServer {
+                id,
+                name: "local".to_owned(),
+                capacity: 1024,
+                storage_type: StorageType::FileSystem {
+                    options: FileSystemOption {
+                        root: "/tmp".to_owned(),
+                        download_endpoint: None,
+                    },
+                },
+            })
+        });
+        mocks
+            .broker_service
+            .expect_delete()
+            .withf(move |server, id| server.id == server_id && *id == meta_id)
+            .times(1)
+            .returning(|_, _| Ok(()));
+        mocks.storage_repo.expect_delete_by_meta_id().times(1).returning(|_| Ok(()));
+        mocks.meta_repo.expect_delete_by_id().times(1).returning(|_| Ok(()));
+        mocks.meta_repo.expect_save_changed().times(1).returning(|| Ok(true));
+
+        assert_eq!(mocks.build().collect().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_collect_skip_referenced_again() {
+        let mut mocks = Mocks::new(meta());
+        // Referenced by a flash upload after the candidates are queried.
+        mocks.meta_repo.expect_tombstone().times(1).returning(|_| Ok(false));
+        mocks.storage_repo.expect_get_all_by_meta_id().never();
+        mocks.meta_repo.expect_delete_by_id().never();
+
+        assert_eq!(mocks.build().collect().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_collect_restore_snapshot_after_tombstone() {
+        let meta = meta();
+        let meta_id = meta.id;
+        let mut mocks = Mocks::new(meta);
+        mocks.snapshot_repo.checkpoint();
+        let mut calls = 0;
+        mocks.snapshot_repo.expect_get_all_by_key_regex().times(2).returning(move |_| {
+            calls += 1;
+            // Taken by hash before the tombstone.
+            Ok(if calls == 1 { vec![] } else { vec![snapshot()] })
+        });
+        mocks.meta_repo.expect_tombstone().times(1).returning(|_| Ok(true));
+        mocks
+            .meta_repo
+            .expect_restore()
+            .withf(move |id| *id == meta_id)
+            .times(1)
+            .returning(|_| Ok(()));
+        mocks.storage_repo.expect_get_all_by_meta_id().never();
+        mocks.meta_repo.expect_delete_by_id().never();
+
+        assert_eq!(mocks.build().collect().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_collect_skip_snapshot() {
+        let mut mocks = Mocks::new(meta());
+        mocks.snapshot_repo.checkpoint();
+        mocks
+            .snapshot_repo
+            .expect_get_all_by_key_regex()
+            .returning(|_| Ok(vec![snapshot()]));
+        mocks.meta_repo.expect_tombstone().never();
+
+        assert_eq!(mocks.build().collect().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_collect_grace_from_dereference() {
+        let mut mocks = Mocks::new(meta());
+        mocks.meta_repo.checkpoint();
+        let now = Utc::now();
+        mocks
+            .meta_repo
+            .expect_get_unreferenced()
+            .withf(move |dereferenced_before, limit| {
+                let grace = now - *dereferenced_before;
+                grace >= Duration::seconds(59) && grace <= Duration::seconds(61) && *limit == 100
+            })
+            .times(1)
+            .returning(|_, _| Ok(vec![]));
+
+        assert_eq!(mocks.build().collect().await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,65 @@
+use domain_storage::model::vo::HashAlgorithm;
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh3::Xxh3;
+
+/// Incremental hasher, so that a file can be hashed part by part.
+pub(crate) enum ContentHasher {
+    Blake3(Box<blake3::Hasher>),
+    Sha256(Sha256),
+    Xxh3(Box<Xxh3>),
+}
+
+impl ContentHasher {
+    pub fn new(hash_algorithm: &HashAlgorithm) -> Self {
+        match hash_algorithm {
+            HashAlgorithm::Blake3 => Self::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            HashAlgorithm::Xxh3 => Self::Xxh3(Box::new(Xxh3::new())),
+        }
+    }
+
+    pub fn update(&mut self, content: &[u8]) {
+        match self {
+            Self::Blake3(hasher) => {
+                hasher.update(content);
+            }
+            Self::Sha256(hasher) => hasher.update(content),
+            Self::Xxh3(hasher) => hasher.update(content),
+        }
+    }
+
+    /// Uppercase hex of the hash, xxh3 is the 64 bits variant.
+    pub fn finalize(self) -> String {
+        match self {
+            Self::Blake3(hasher) => hasher.finalize().to_string().to_uppercase(),
+            Self::Sha256(hasher) => format!("{:X}", hasher.finalize()),
+            Self::Xxh3(hasher) => format!("{:016X}", hasher.digest()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(hash_algorithm: &HashAlgorithm, parts: &[&[u8]]) -> String {
+        let mut hasher = ContentHasher::new(hash_algorithm);
+        for part in parts {
+            hasher.update(part);
+        }
+        hasher.finalize()
+    }
+
+    #[test]
+    fn test_content_hasher() {
+        assert_eq!(
+            hash(&HashAlgorithm::Sha256, &[b"a", b"bc"]),
+            "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD"
+        );
+        assert_eq!(hash(&HashAlgorithm::Xxh3, &[]), "2D06800538D394C2");
+        assert_eq!(
+            hash(&HashAlgorithm::Blake3, &[b"a", b"bc"]),
+            blake3::hash(b"abc").to_string().to_uppercase()
+        );
+    }
+}
@@ -1,5 +1,9 @@
+mod archive_extract;
 mod cache;
 mod content_extractor;
+mod direct_upload;
+mod file_gc;
+mod hasher;
 mod meta;
 mod mover;
 mod multipart;
@@ -10,11 +14,15 @@ mod server_upload_dispatcher;
 mod snapshot;
 mod storage_server_resource;
 mod text;
+mod upload_sweep;
 
 #[rustfmt::skip]
 pub use {
+    archive_extract::ArchiveExtractServiceImpl,
     cache::LocalCacheServiceImpl,
     content_extractor::ContentExtractorServiceImpl,
+    direct_upload::DirectUploadServiceImpl,
+    file_gc::FileGcServiceImpl,
     meta::MetaStorageServiceImpl,
     mover::FileMoveServiceImpl,
     multipart::MultipartServiceImpl,
@@ -25,4 +33,5 @@ pub use {
     snapshot::SnapshotServiceImpl,
     storage_server_resource::StorageServerResourceServiceImpl,
     text::TextStorageServiceImpl,
+    upload_sweep::UploadSweepServiceImpl,
 };
@@ -24,7 +24,7 @@ impl MetaStorageService for MetaStorageServiceImpl {
         &self,
         meta_id: Uuid,
         file_meta_info: RecordFileMeta,
-        file_storage_info: RecordFileStorage,
+        file_storage_infos: Vec<RecordFileStorage>,
     ) -> anyhow::Result<()> {
         let file_meta = FileMeta {
             id: meta_id,
@@ -33,14 +33,15 @@ impl MetaStorageService for MetaStorageServiceImpl {
             hash_algorithm: file_meta_info.hash_algorithm,
             size: file_meta_info.size,
         };
-        let file_storage = FileStorage {
-            storage_server_id: file_storage_info.storage_server_id,
-            meta_id,
-            server_url: file_storage_info.server_url,
-        };
-
         self.meta_repo.insert(&file_meta).await?;
-        self.storage_repo.insert(&file_storage).await?;
+        for file_storage_info in file_storage_infos {
+            let file_storage = FileStorage {
+                storage_server_id: file_storage_info.storage_server_id,
+                meta_id,
+                server_url: file_storage_info.server_url,
+            };
+            self.storage_repo.insert(&file_storage).await?;
+        }
 
         self.storage_repo.save_changed().await?;
         Ok(())
@@ -51,6 +52,9 @@ impl MetaStorageService for MetaStorageServiceImpl {
         hash: &str,
         hash_algorithm: &HashAlgorithm,
     ) -> anyhow::Result<Option<Uuid>> {
+        if !hash_algorithm.is_collision_resistant() {
+            return Ok(None);
+        }
         if let Some(meta) = self.meta_repo.get_by_hash_and_algorithm(hash, hash_algorithm).await? {
             return Ok(Some(meta.id));
         }
@@ -66,4 +70,14 @@ impl MetaStorageService for MetaStorageServiceImpl {
             .get_by_storage_server_id_and_meta_id(storage_server_id, meta_id)
             .await
     }
+
+    async fn get_storage_server_ids(&self, meta_id: Uuid) -> anyhow::Result<Vec<Uuid>> {
+        Ok(self
+            .storage_repo
+            .get_all_by_meta_id(meta_id)
+            .await?
+            .into_iter()
+            .map(|el| el.storage_server_id)
+            .collect())
+    }
 }
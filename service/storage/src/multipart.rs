@@ -3,12 +3,13 @@ use std::{sync::Arc, thread::sleep, time::Duration};
 use alice_architecture::message_queue::producer::MessageQueueProducerTemplate;
 use anyhow::Context;
 use async_trait::async_trait;
+use chrono::Utc;
 use domain_storage::{
     command::{CacheOperateCommand, CacheReadCommand},
     exception::{FileException, FileResult},
     model::{
         entity::Multipart,
-        vo::{HashAlgorithm, Part},
+        vo::{DirectUpload, HashAlgorithm, Part},
     },
     repository::{MoveRegistrationRepo, MultipartRepo},
     service::{CacheService, MultipartService},
@@ -18,6 +19,8 @@ use rand::Rng;
 use typed_builder::TypedBuilder;
 use uuid::Uuid;
 
+use crate::hasher::ContentHasher;
+
 #[derive(TypedBuilder)]
 pub struct MultipartServiceImpl {
     multipart_repo: Arc<dyn MultipartRepo>,
@@ -34,7 +37,7 @@ fn id_hash_key(id: Uuid, hash: &str) -> String {
     format!("multipart_{id}_{hash}")
 }
 
-fn id_key_regex(id: Uuid) -> String {
+pub(crate) fn id_key_regex(id: Uuid) -> String {
     format!("multipart_{id}_*")
 }
 
@@ -42,22 +45,25 @@ fn hash_key_regex(hash: &str) -> String {
     format!("multipart_*_{hash}")
 }
 
-fn move_meta_id_key_regex(meta_id: Uuid) -> String {
+pub(crate) fn move_meta_id_key_regex(meta_id: Uuid) -> String {
     format!("movereg_*_{meta_id}")
 }
 
-#[async_trait]
-impl MultipartService for MultipartServiceImpl {
-    async fn create(
+impl MultipartServiceImpl {
+    async fn insert(
         &self,
         meta_id: Uuid,
         hash: &str,
         hash_algorithm: HashAlgorithm,
         count: u64,
+        direct_upload: Option<DirectUpload>,
     ) -> FileResult<()> {
         let hash = hash.to_uppercase();
-        // Test is hash the same.
-        let same_hash = self.multipart_repo.get_one_by_key_regex(&hash_key_regex(&hash)).await?;
+        // Test is hash the same, uploads with hashes that collide easily are never shared.
+        let same_hash = match hash_algorithm.is_collision_resistant() {
+            true => self.multipart_repo.get_one_by_key_regex(&hash_key_regex(&hash)).await?,
+            false => None,
+        };
         if let Some(el) = same_hash {
             return Err(FileException::ConflictedHash {
                 meta_id: el.meta_id,
@@ -77,12 +83,39 @@ impl MultipartService for MultipartServiceImpl {
             hash_algorithm,
             shards: (0..count).collect(),
             part_count: count,
+            task_id: self.task_id,
+            updated_at: Utc::now(),
+            direct_upload,
         };
         self.multipart_repo
             .insert_with_lease(&id_hash_key(meta_id, &hash), &multipart, self.exp_msecs)
             .await?;
         Ok(())
     }
+}
+
+#[async_trait]
+impl MultipartService for MultipartServiceImpl {
+    async fn create(
+        &self,
+        meta_id: Uuid,
+        hash: &str,
+        hash_algorithm: HashAlgorithm,
+        count: u64,
+    ) -> FileResult<()> {
+        self.insert(meta_id, hash, hash_algorithm, count, None).await
+    }
+
+    async fn create_direct(
+        &self,
+        meta_id: Uuid,
+        hash: &str,
+        hash_algorithm: HashAlgorithm,
+        count: u64,
+        direct_upload: DirectUpload,
+    ) -> FileResult<()> {
+        self.insert(meta_id, hash, hash_algorithm, count, Some(direct_upload)).await
+    }
 
     async fn complete_part(&self, part: Part) -> FileResult<Vec<u64>> {
         let meta_id = part.meta_id;
@@ -114,18 +147,15 @@ impl MultipartService for MultipartServiceImpl {
         let parts_len = multipart.part_count;
         let hash_algorithm = multipart.hash_algorithm.to_owned();
         let hash = multipart.hash.to_owned().to_uppercase();
-        // If all parts are uploaded, merge them and delete multipart dir.
-        let mut completed_content = vec![];
+        // If all parts are uploaded, verify the declared hash part by part. Parts are kept until
+        // the file is moved, so that the whole file is never loaded into memory or written twice.
+        let mut hasher = ContentHasher::new(&hash_algorithm);
         for nth in 0..parts_len {
             let nth_content =
                 self.cache_service.read(CacheReadCommand::ReadPart { meta_id, nth }).await?;
-            completed_content.extend(nth_content);
+            hasher.update(&nth_content);
         }
-        let completed_content_hash = match hash_algorithm {
-            HashAlgorithm::Blake3 => {
-                blake3::hash(completed_content.as_slice()).to_string().to_uppercase()
-            }
-        };
+        let completed_content_hash = hasher.finalize();
         if completed_content_hash.ne(&hash) {
             let mut info = self
                 .move_registration_repo
@@ -134,8 +164,8 @@ impl MultipartService for MultipartServiceImpl {
                 .with_context(|| format!("no move_reg for meta_id: {}", multipart.meta_id))?;
             info.is_upload_failed = true;
             let failed_reason = format!(
-                "hash not match, provided: {}, completed: {}",
-                hash, completed_content_hash
+                "{hash_algorithm} hash of the assembled file doesn't match, provided: {hash}, \
+                 computed: {completed_content_hash}"
             );
             info.failed_reason = Some(failed_reason.to_owned());
             self.move_registration_repo
@@ -168,15 +198,6 @@ impl MultipartService for MultipartServiceImpl {
             });
         }
 
-        self.cache_service
-            .operate(CacheOperateCommand::WriteNormal {
-                meta_id,
-                content: completed_content,
-            })
-            .await?;
-        self.cache_service
-            .operate(CacheOperateCommand::RemoveMultipartDir { meta_id })
-            .await?;
         Ok(vec![])
     }
 
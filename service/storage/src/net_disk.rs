@@ -1,11 +1,15 @@
 use std::sync::Arc;
 
-use alice_architecture::repository::ReadOnlyRepository;
+use alice_architecture::repository::{DbField, ReadOnlyRepository};
 use async_trait::async_trait;
 use chrono::Utc;
 use domain_storage::{
     command::CreateNetDiskFileCommand,
-    model::entity::{FileType, NetDisk, NetDiskMeta, RecordNetDiskKind},
+    exception::FileException,
+    model::{
+        entity::{DbNetDisk, FileType, NetDisk, NetDiskMeta, RecordNetDiskKind},
+        vo::{ArchiveEntry, ArchiveListing, ArchiveTarget, NetDiskPage, NetDiskUsage},
+    },
     repository::NetDiskRepo,
     service::NetDiskService,
 };
@@ -43,9 +47,241 @@ impl NetDiskService for NetDiskServiceImpl {
         }
         Ok(())
     }
+
+    async fn list(
+        &self,
+        parent_id: Option<Uuid>,
+        page: u64,
+        page_size: u64,
+    ) -> anyhow::Result<NetDiskPage> {
+        let parent_id = match parent_id {
+            Some(el) => self.get_dir(el).await?.id,
+            None => self.get_or_create_user_root_id().await?,
+        };
+        self.net_disk_repo
+            .get_children_paged(parent_id, page.max(1), page_size.max(1))
+            .await
+    }
+
+    async fn create_dir(&self, parent_id: Option<Uuid>, name: &str) -> anyhow::Result<Uuid> {
+        check_file_name(name)?;
+        let parent_id = match parent_id {
+            Some(el) => self.get_dir(el).await?.id,
+            None => self.get_or_create_user_root_id().await?,
+        };
+        let name = self.fix_file_name(Some(parent_id), name).await?;
+        let id = self
+            .net_disk_repo
+            .insert(&NetDisk {
+                id: Uuid::new_v4(),
+                parent_id: Some(parent_id),
+                name,
+                is_dict: true,
+                kind: FileType::Folder,
+                file_metadata_id: None,
+                meta: None,
+            })
+            .await?;
+        self.net_disk_repo.save_changed().await?;
+        Ok(id)
+    }
+
+    async fn rename(&self, id: Uuid, name: &str) -> anyhow::Result<()> {
+        let file = self.get_modifiable(id).await?;
+        check_file_name(name)?;
+        if file.name.eq(name) {
+            return Ok(());
+        }
+        let name = self.fix_file_name(file.parent_id, name).await?;
+        self.net_disk_repo
+            .update(DbNetDisk {
+                id: DbField::Unchanged(id),
+                name: DbField::Set(name),
+                ..Default::default()
+            })
+            .await?;
+        self.net_disk_repo.save_changed().await?;
+        Ok(())
+    }
+
+    async fn move_to(&self, id: Uuid, parent_id: Option<Uuid>) -> anyhow::Result<()> {
+        let file = self.get_modifiable(id).await?;
+        let parent_id = self.get_destination(&file, parent_id).await?;
+        if file.parent_id.eq(&Some(parent_id)) {
+            return Ok(());
+        }
+        let name = self.fix_file_name(Some(parent_id), &file.name).await?;
+        self.net_disk_repo
+            .update(DbNetDisk {
+                id: DbField::Unchanged(id),
+                parent_id: DbField::Set(Some(parent_id)),
+                name: DbField::Set(name),
+                ..Default::default()
+            })
+            .await?;
+        self.net_disk_repo.save_changed().await?;
+        Ok(())
+    }
+
+    async fn copy(&self, id: Uuid, parent_id: Option<Uuid>) -> anyhow::Result<Uuid> {
+        let file = self.get_modifiable(id).await?;
+        let parent_id = self.get_destination(&file, parent_id).await?;
+        let name = self.fix_file_name(Some(parent_id), &file.name).await?;
+
+        // Copies are plain files, they don't belong to workflows.
+        let copy_id = Uuid::new_v4();
+        let mut pending = vec![(file.to_owned(), copy_id)];
+        self.net_disk_repo
+            .insert(&NetDisk {
+                id: copy_id,
+                parent_id: Some(parent_id),
+                name,
+                meta: None,
+                ..file
+            })
+            .await?;
+        while let Some((dir, dir_copy_id)) = pending.pop() {
+            if !dir.is_dict {
+                continue;
+            }
+            for child in self.net_disk_repo.get_children(dir.id).await? {
+                let child_copy_id = Uuid::new_v4();
+                self.net_disk_repo
+                    .insert(&NetDisk {
+                        id: child_copy_id,
+                        parent_id: Some(dir_copy_id),
+                        meta: None,
+                        ..child.to_owned()
+                    })
+                    .await?;
+                pending.push((child, child_copy_id));
+            }
+        }
+        self.net_disk_repo.save_changed().await?;
+        Ok(copy_id)
+    }
+
+    async fn delete(&self, id: Uuid) -> anyhow::Result<()> {
+        let file = self.get_modifiable(id).await?;
+        let mut ids = vec![];
+        let mut pending = vec![file];
+        while let Some(file) = pending.pop() {
+            if file.is_dict {
+                pending.extend(self.net_disk_repo.get_children(file.id).await?);
+            }
+            ids.push(file.id);
+        }
+        // Children go before their parents.
+        for id in ids.into_iter().rev() {
+            self.net_disk_repo.delete_by_id(id).await?;
+        }
+        self.net_disk_repo.save_changed().await?;
+        Ok(())
+    }
+
+    async fn get_usage(&self) -> anyhow::Result<NetDiskUsage> {
+        self.net_disk_repo.get_usage().await
+    }
+
+    async fn get_archive_listing(&self, target: ArchiveTarget) -> anyhow::Result<ArchiveListing> {
+        let dir_id = match target {
+            ArchiveTarget::NetDisk { id } => Some(id),
+            ArchiveTarget::FlowInstance { id } => {
+                self.net_disk_repo.get_flow_instance_dir_id(id).await?
+            }
+            ArchiveTarget::NodeInstance { id } => {
+                self.net_disk_repo.get_node_instance_dir_id(id).await?
+            }
+        };
+        let dir = match dir_id {
+            Some(el) => self.get_dir(el).await?,
+            None => anyhow::bail!("No net disk directory of {target:?}"),
+        };
+
+        let mut entries = vec![];
+        let mut pending = vec![(dir.id, dir.name.to_owned())];
+        while let Some((dir_id, dir_path)) = pending.pop() {
+            for child in self.net_disk_repo.get_children(dir_id).await? {
+                let path = format!("{dir_path}/{}", child.name);
+                if child.is_dict {
+                    pending.push((child.id, path.to_owned()));
+                    entries.push(ArchiveEntry {
+                        path,
+                        meta_id: None,
+                    });
+                } else if let Some(meta_id) = child.file_metadata_id {
+                    entries.push(ArchiveEntry {
+                        path,
+                        meta_id: Some(meta_id),
+                    });
+                }
+            }
+        }
+        Ok(ArchiveListing {
+            name: dir.name,
+            entries,
+        })
+    }
+}
+
+/// File names can't be empty or contain path separators.
+fn check_file_name(name: &str) -> anyhow::Result<()> {
+    if name.trim().is_empty() || name.contains(['/', '\\']) || name.eq(".") || name.eq("..") {
+        return Err(FileException::InvalidFileName {
+            name: name.to_owned(),
+        }
+        .into());
+    }
+    Ok(())
 }
 
 impl NetDiskServiceImpl {
+    async fn get_dir(&self, id: Uuid) -> anyhow::Result<NetDisk> {
+        let dir = self.net_disk_repo.get_by_id(id).await?;
+        if !dir.is_dict {
+            return Err(FileException::NetDiskFileNotFound { id }.into());
+        }
+        Ok(dir)
+    }
+
+    /// Get a file that can be renamed, moved, copied or deleted, which is any file except the
+    /// user's root directory.
+    async fn get_modifiable(&self, id: Uuid) -> anyhow::Result<NetDisk> {
+        let file = self.net_disk_repo.get_by_id(id).await?;
+        if file.parent_id.is_none() {
+            return Err(FileException::NetDiskRootNotModifiable.into());
+        }
+        Ok(file)
+    }
+
+    /// Get the directory to move or copy the file into, which can't be the file itself or inside
+    /// it.
+    async fn get_destination(
+        &self,
+        file: &NetDisk,
+        parent_id: Option<Uuid>,
+    ) -> anyhow::Result<Uuid> {
+        let parent_id = match parent_id {
+            Some(el) => el,
+            None => return self.get_or_create_user_root_id().await,
+        };
+        let mut ancestor = Some(self.get_dir(parent_id).await?);
+        while let Some(dir) = ancestor {
+            if dir.id.eq(&file.id) {
+                return Err(FileException::InvalidNetDiskDestination {
+                    id: file.id,
+                    parent_id,
+                }
+                .into());
+            }
+            ancestor = match dir.parent_id {
+                Some(el) => Some(self.net_disk_repo.get_by_id(el).await?),
+                None => None,
+            };
+        }
+        Ok(parent_id)
+    }
+
     async fn get_or_create_user_root_id(&self) -> anyhow::Result<Uuid> {
         let user_root = self.net_disk_repo.get_root_id().await?;
         Ok(match user_root {
@@ -196,3 +432,17 @@ impl NetDiskServiceImpl {
         Ok(node_instance_dir_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_file_name() {
+        assert!(check_file_name("result.txt").is_ok());
+        assert!(check_file_name("结果").is_ok());
+        for name in ["", "  ", ".", "..", "a/b", "a\\b"] {
+            assert!(check_file_name(name).is_err(), "{name}");
+        }
+    }
+}
@@ -1,35 +1,92 @@
+use std::future::Future;
 use std::ops::Range;
+use std::pin::Pin;
 use std::sync::Arc;
 
+use anyhow::anyhow;
 use async_trait::async_trait;
-use domain_storage::service::{
-    StorageServerBrokerService, StorageServerDownloadDispatcherService,
-    StorageServerResourceService,
+use domain_storage::{
+    model::entity::StorageServer,
+    service::{
+        MetaStorageService, StorageServerBrokerService, StorageServerDownloadDispatcherService,
+        StorageServerResourceService,
+    },
 };
 use typed_builder::TypedBuilder;
 use uuid::Uuid;
 
+type BrokerFuture<'a, T> = Pin<Box<dyn Future<Output = anyhow::Result<T>> + Send + 'a>>;
+
 #[derive(TypedBuilder)]
 pub struct StorageServerDownloadDispatcherServiceImpl {
     resources_service: Arc<dyn StorageServerResourceService>,
     storage_server_broker_service: Arc<dyn StorageServerBrokerService>,
+    meta_storage_service: Arc<dyn MetaStorageService>,
+}
+
+impl StorageServerDownloadDispatcherServiceImpl {
+    /// Storage servers holding the file, the default one if no replica is recorded.
+    async fn storage_server_ids(&self, meta_id: Uuid) -> anyhow::Result<Vec<Uuid>> {
+        let ids = self.meta_storage_service.get_storage_server_ids(meta_id).await?;
+        if ids.is_empty() {
+            return Ok(vec![
+                self.resources_service.default_file_storage_server().await?.id,
+            ]);
+        }
+        Ok(ids)
+    }
+
+    /// Run the operation on each replica in turn until one succeeds.
+    async fn with_replicas<'a, T, F>(&'a self, meta_id: Uuid, operation: F) -> anyhow::Result<T>
+    where
+        T: Send,
+        F: Fn(StorageServer) -> BrokerFuture<'a, T> + Send,
+    {
+        let mut errors = vec![];
+        for id in self.storage_server_ids(meta_id).await? {
+            let storage_server = match self.resources_service.get_storage_server(id).await {
+                Ok(el) => el,
+                Err(e) => {
+                    errors.push(format!("{id}: {e}"));
+                    continue;
+                }
+            };
+            match operation(storage_server).await {
+                Ok(el) => return Ok(el),
+                Err(e) => errors.push(format!("{id}: {e}")),
+            }
+        }
+        Err(anyhow!(
+            "All replicas of file meta id: {meta_id} failed. {}",
+            errors.join("; ")
+        ))
+    }
 }
 
 #[async_trait]
 impl StorageServerDownloadDispatcherService for StorageServerDownloadDispatcherServiceImpl {
     async fn download(&self, meta_id: Uuid) -> anyhow::Result<()> {
-        let storage_server = &self.resources_service.default_file_storage_server().await?;
-        self.storage_server_broker_service.download(storage_server, meta_id).await
+        let broker = &self.storage_server_broker_service;
+        self.with_replicas(meta_id, |storage_server| {
+            Box::pin(async move { broker.download(&storage_server, meta_id).await })
+        })
+        .await
     }
 
     async fn get_bytes(&self, meta_id: Uuid) -> anyhow::Result<Vec<u8>> {
-        let storage_server = &self.resources_service.default_file_storage_server().await?;
-        self.storage_server_broker_service.get_bytes(storage_server, meta_id).await
+        let broker = &self.storage_server_broker_service;
+        self.with_replicas(meta_id, |storage_server| {
+            Box::pin(async move { broker.get_bytes(&storage_server, meta_id).await })
+        })
+        .await
     }
 
     async fn get_text(&self, meta_id: Uuid) -> anyhow::Result<String> {
-        let storage_server = &self.resources_service.default_file_storage_server().await?;
-        self.storage_server_broker_service.get_text(storage_server, meta_id).await
+        let broker = &self.storage_server_broker_service;
+        self.with_replicas(meta_id, |storage_server| {
+            Box::pin(async move { broker.get_text(&storage_server, meta_id).await })
+        })
+        .await
     }
 
     async fn rangely_get_file(
@@ -37,21 +94,26 @@ impl StorageServerDownloadDispatcherService for StorageServerDownloadDispatcherS
         meta_id: Uuid,
         ranges: &[Range<u64>],
     ) -> anyhow::Result<Vec<Vec<u8>>> {
-        let storage_server = &self.resources_service.default_file_storage_server().await?;
-        self.storage_server_broker_service
-            .rangely_get_file(storage_server, meta_id, ranges)
-            .await
+        let broker = &self.storage_server_broker_service;
+        self.with_replicas(meta_id, |storage_server| {
+            Box::pin(async move { broker.rangely_get_file(&storage_server, meta_id, ranges).await })
+        })
+        .await
     }
 
     async fn get_file_size(&self, meta_id: Uuid) -> anyhow::Result<u64> {
-        let storage_server = &self.resources_service.default_file_storage_server().await?;
-        self.storage_server_broker_service.get_file_size(storage_server, meta_id).await
+        let broker = &self.storage_server_broker_service;
+        self.with_replicas(meta_id, |storage_server| {
+            Box::pin(async move { broker.get_file_size(&storage_server, meta_id).await })
+        })
+        .await
     }
 
     async fn get_download_url(&self, meta_id: Uuid) -> anyhow::Result<String> {
-        let storage_server = &self.resources_service.default_file_storage_server().await?;
-        self.storage_server_broker_service
-            .get_download_url(storage_server, meta_id)
-            .await
+        let broker = &self.storage_server_broker_service;
+        self.with_replicas(meta_id, |storage_server| {
+            Box::pin(async move { broker.get_download_url(&storage_server, meta_id).await })
+        })
+        .await
     }
 }
@@ -1,23 +1,71 @@
 use std::sync::Arc;
 
+use anyhow::bail;
 use async_trait::async_trait;
-use domain_storage::{model::vo::ServerUrl, service::*};
+use domain_storage::{
+    model::vo::{PlacementHint, ServerUrl},
+    service::*,
+};
 use typed_builder::TypedBuilder;
-use uuid::Uuid;
 
 #[derive(TypedBuilder, Clone)]
 pub struct StorageServerUploadDispatcherServiceImpl {
     resources_service: Arc<dyn StorageServerResourceService>,
     storage_server_broker_service: Arc<dyn StorageServerBrokerService>,
+    /// Number of copies stored on different servers.
+    #[builder(default = 1)]
+    replicas: usize,
 }
 
 #[async_trait]
 impl StorageServerUploadDispatcherService for StorageServerUploadDispatcherServiceImpl {
-    async fn upload(&self, meta_id: Uuid, content: &[u8]) -> anyhow::Result<ServerUrl> {
-        let storage_server = &self.resources_service.default_file_storage_server().await?;
+    async fn upload(
+        &self,
+        parts: &CachedParts,
+        size: u64,
+        hint: &PlacementHint,
+    ) -> anyhow::Result<Vec<ServerUrl>> {
+        let storage_servers = self.resources_service.place(size, hint).await?;
+        if storage_servers.len() < self.replicas {
+            bail!(
+                "Only {} storage servers can store the file, {} replicas are required.",
+                storage_servers.len(),
+                self.replicas
+            );
+        }
 
-        self.storage_server_broker_service
-            .upload(storage_server, meta_id, content)
-            .await
+        // Servers that fail are skipped, the next ones by preference take their place.
+        let mut stored = vec![];
+        let mut errors = vec![];
+        for storage_server in storage_servers.iter() {
+            if stored.len() >= self.replicas {
+                break;
+            }
+            match self.storage_server_broker_service.upload(storage_server, parts).await {
+                Ok(server_url) => stored.push((storage_server, server_url)),
+                Err(e) => errors.push(format!("{}: {e}", storage_server.name)),
+            }
+        }
+        if stored.len() < self.replicas {
+            // Fewer replicas than required are not recorded, so their objects are removed.
+            for (storage_server, _) in stored.iter() {
+                if let Err(e) =
+                    self.storage_server_broker_service.delete(storage_server, parts.meta_id).await
+                {
+                    tracing::error!(
+                        "Failed to delete replica of file {} on {}: {e}",
+                        parts.meta_id,
+                        storage_server.name
+                    );
+                }
+            }
+            bail!(
+                "Only {} of {} replicas of the file are stored. {}",
+                stored.len(),
+                self.replicas,
+                errors.join("; ")
+            );
+        }
+        Ok(stored.into_iter().map(|(_, server_url)| server_url).collect())
     }
 }
@@ -42,7 +42,7 @@ fn id_key_regex(id: Uuid) -> String {
     format!("snapshot_{id}_*_*_*_*_*")
 }
 
-fn hash_key_regex(hash: &str, hash_algorithm: &HashAlgorithm) -> String {
+pub(crate) fn hash_key_regex(hash: &str, hash_algorithm: &HashAlgorithm) -> String {
     format!("snapshot_*_*_*_*_{hash_algorithm}_{hash}")
 }
 
@@ -67,7 +67,7 @@ impl SnapshotService for SnapshotServiceImpl {
 
     async fn create(&self, snapshot: Snapshot) -> anyhow::Result<()> {
         self.cache_service
-            .operate(CacheOperateCommand::ChangeNormalToSnapshot {
+            .operate(CacheOperateCommand::ChangeMultipartToSnapshot {
                 meta_id: snapshot.meta_id,
             })
             .await?;
@@ -96,7 +96,7 @@ impl SnapshotService for SnapshotServiceImpl {
         if same_meta_id_snapshot.is_none() {
             // No more snapshot use the file, remove it. Otherwise  keep it.
             self.cache_service
-                .operate(CacheOperateCommand::RemoveMultipartDir {
+                .operate(CacheOperateCommand::RemoveSnapshot {
                     meta_id: deleted_record.meta_id,
                 })
                 .await?;
@@ -132,6 +132,9 @@ impl SnapshotService for SnapshotServiceImpl {
         hash: &str,
         hash_algorithm: &HashAlgorithm,
     ) -> anyhow::Result<Option<Uuid>> {
+        if !hash_algorithm.is_collision_resistant() {
+            return Ok(None);
+        }
         Ok(self
             .snapshot_repo
             .get_one_by_key_regex(&hash_key_regex(hash, hash_algorithm))
@@ -2,7 +2,14 @@ use std::sync::Arc;
 
 use alice_architecture::repository::ReadOnlyRepository;
 use async_trait::async_trait;
-use domain_storage::{model::entity::StorageServer, service::StorageServerResourceService};
+use domain_storage::{
+    model::{
+        entity::StorageServer,
+        vo::{PlacementConfig, PlacementHint, StorageServerUsage},
+    },
+    repository::FileStorageRepo,
+    service::StorageServerResourceService,
+};
 use typed_builder::TypedBuilder;
 use uuid::Uuid;
 
@@ -10,6 +17,9 @@ use uuid::Uuid;
 pub struct StorageServerResourceServiceImpl {
     default_storage_server_id: Uuid,
     storage_server_repo: Arc<dyn ReadOnlyRepository<StorageServer>>,
+    storage_repo: Arc<dyn FileStorageRepo>,
+    #[builder(default)]
+    placement: PlacementConfig,
 }
 
 #[async_trait]
@@ -17,4 +27,95 @@ impl StorageServerResourceService for StorageServerResourceServiceImpl {
     async fn default_file_storage_server(&self) -> anyhow::Result<StorageServer> {
         self.storage_server_repo.get_by_id(self.default_storage_server_id).await
     }
+
+    async fn get_storage_server(&self, id: Uuid) -> anyhow::Result<StorageServer> {
+        self.storage_server_repo.get_by_id(id).await
+    }
+
+    async fn place(&self, size: u64, hint: &PlacementHint) -> anyhow::Result<Vec<StorageServer>> {
+        let mut ids = vec![self.default_storage_server_id];
+        for id in self.placement.storage_server_ids.iter() {
+            if !ids.contains(id) {
+                ids.push(*id);
+            }
+        }
+        let mut candidates = vec![];
+        for id in ids {
+            match self.usage(id).await {
+                Ok(usage) => candidates.push(usage),
+                // One unavailable server doesn't fail the upload, it is placed on the others.
+                Err(e) => tracing::warn!("Skip storage server {id} in placement: {e}"),
+            }
+        }
+        Ok(self
+            .placement
+            .policy
+            .place(candidates, size, hint, self.default_storage_server_id))
+    }
+}
+
+impl StorageServerResourceServiceImpl {
+    async fn usage(&self, id: Uuid) -> anyhow::Result<StorageServerUsage> {
+        Ok(StorageServerUsage {
+            storage_server: self.storage_server_repo.get_by_id(id).await?,
+            used: self.storage_repo.get_used_size(id).await?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_storage::{
+        mock::{MockFileStorageRepo, MockStorageServerRepo},
+        model::entity::{ObjectServerOption, StorageType},
+    };
+
+    fn server(id: Uuid) -> StorageServer {
+        StorageServer {
+            id,
+            name: String::new(),
+            capacity: 100,
+            storage_type: StorageType::ObjectStorage {
+                options: ObjectServerOption::default(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_place_skips_failing_servers() {
+        let default_id = Uuid::new_v4();
+        let missing_id = Uuid::new_v4();
+        let failing_id = Uuid::new_v4();
+        let roomy_id = Uuid::new_v4();
+
+        let mut storage_server_repo = MockStorageServerRepo::new();
+        storage_server_repo
+            .expect_get_by_id()
+            .withf(move |id| *id == missing_id)
+            .returning(|id| anyhow::bail!("No storage server: {id}"));
+        storage_server_repo.expect_get_by_id().returning(|id| Ok(server(id)));
+        let mut storage_repo = MockFileStorageRepo::new();
+        storage_repo
+            .expect_get_used_size()
+            .withf(move |id| *id == failing_id)
+            .returning(|_| anyhow::bail!("Database is down"));
+        storage_repo.expect_get_used_size().returning(|_| Ok(0));
+
+        let service = StorageServerResourceServiceImpl::builder()
+            .default_storage_server_id(default_id)
+            .storage_server_repo(Arc::new(storage_server_repo))
+            .storage_repo(Arc::new(storage_repo))
+            .placement(PlacementConfig {
+                storage_server_ids: vec![missing_id, failing_id, roomy_id],
+                ..Default::default()
+            })
+            .build();
+
+        let placed = service.place(10, &PlacementHint::default()).await.unwrap();
+        assert_eq!(
+            placed.iter().map(|el| el.id).collect::<Vec<_>>(),
+            vec![default_id, roomy_id]
+        );
+    }
 }
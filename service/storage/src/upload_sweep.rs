@@ -0,0 +1,149 @@
+use std::{collections::HashSet, sync::Arc};
+
+use alice_architecture::message_queue::producer::MessageQueueProducerTemplate;
+use anyhow::bail;
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use domain_storage::{
+    command::CacheOperateCommand,
+    model::entity::Multipart,
+    repository::{MoveRegistrationRepo, MultipartRepo},
+    service::{CacheService, DirectUploadService, UploadSweepService},
+};
+use domain_workflow::model::vo::msg::{ChangeMsg, Info, TaskChangeInfo, TaskStatusChange};
+use typed_builder::TypedBuilder;
+
+use crate::multipart::{id_key_regex, move_meta_id_key_regex};
+
+#[derive(TypedBuilder)]
+pub struct UploadSweepServiceImpl {
+    multipart_repo: Arc<dyn MultipartRepo>,
+    move_registration_repo: Arc<dyn MoveRegistrationRepo>,
+    cache_service: Arc<dyn CacheService>,
+    direct_upload_service: Arc<dyn DirectUploadService>,
+    status_mq_producer: Arc<dyn MessageQueueProducerTemplate<ChangeMsg>>,
+    status_mq_topic: String,
+    /// Uploads without a completed part within these seconds are expired. It should be shorter
+    /// than the lease of multipart records, otherwise records expire before they are swept and
+    /// their tasks are never failed.
+    #[builder(default = 12 * 60 * 60)]
+    session_ttl_secs: i64,
+}
+
+impl UploadSweepServiceImpl {
+    /// Whether the upload isn't waiting for parts anymore. A completed one is being moved to
+    /// storage servers, a failed one keeps its failure for clients and has failed its task.
+    /// Both are left to the lease of their records.
+    async fn is_settled(&self, multipart: &Multipart) -> anyhow::Result<bool> {
+        if multipart.shards.is_empty() {
+            return Ok(true);
+        }
+        Ok(self
+            .move_registration_repo
+            .get_all_by_key_regex(&move_meta_id_key_regex(multipart.meta_id))
+            .await?
+            .iter()
+            .any(|el| el.is_upload_failed))
+    }
+
+    /// Remove everything of the upload, the record goes last so that a failed sweep is retried
+    /// by the next one.
+    async fn sweep_multipart(&self, multipart: &Multipart) -> anyhow::Result<()> {
+        let meta_id = multipart.meta_id;
+        if multipart.direct_upload.is_some() {
+            self.direct_upload_service.abort(meta_id).await?;
+        }
+        // The dir doesn't exist if no part is uploaded.
+        let _ = self
+            .cache_service
+            .operate(CacheOperateCommand::RemoveMultipartDir { meta_id })
+            .await;
+        let registrations = self
+            .move_registration_repo
+            .get_all_by_key_regex(&move_meta_id_key_regex(meta_id))
+            .await?;
+        if !registrations.is_empty() {
+            self.move_registration_repo
+                .remove_all_by_key_regex(&move_meta_id_key_regex(meta_id))
+                .await?;
+        }
+        if let Some(task_id) = multipart.task_id {
+            self.status_mq_producer
+                .send_object(
+                    &ChangeMsg {
+                        id: task_id,
+                        info: Info::Task(TaskChangeInfo {
+                            status: TaskStatusChange::Failed,
+                            message: Some(format!(
+                                "Upload of file {meta_id} expired, {} of {} parts are missing",
+                                multipart.shards.len(),
+                                multipart.part_count
+                            )),
+                            ..Default::default()
+                        }),
+                    },
+                    &self.status_mq_topic,
+                )
+                .await?;
+        }
+        self.multipart_repo.delete_by_key_regex(&id_key_regex(meta_id)).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UploadSweepService for UploadSweepServiceImpl {
+    async fn sweep(&self) -> anyhow::Result<usize> {
+        let expired_before = Utc::now() - Duration::seconds(self.session_ttl_secs);
+        let multiparts = self.multipart_repo.get_all_by_key_regex("multipart_*").await?;
+
+        let mut swept = 0;
+        let mut errors = vec![];
+        let mut alive = HashSet::new();
+        for multipart in multiparts {
+            if multipart.updated_at > expired_before {
+                alive.insert(multipart.meta_id);
+                continue;
+            }
+            match self.is_settled(&multipart).await {
+                Ok(true) => {
+                    alive.insert(multipart.meta_id);
+                    continue;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    alive.insert(multipart.meta_id);
+                    errors.push(format!("{}: {e}", multipart.meta_id));
+                    continue;
+                }
+            }
+            match self.sweep_multipart(&multipart).await {
+                Ok(()) => swept += 1,
+                Err(e) => errors.push(format!("{}: {e}", multipart.meta_id)),
+            }
+        }
+
+        // Dirs whose records have expired already, or are never created.
+        for dir in self.cache_service.get_multipart_dirs().await? {
+            if dir.modified_at > expired_before || alive.contains(&dir.meta_id) {
+                continue;
+            }
+            let meta_id = dir.meta_id;
+            match self
+                .cache_service
+                .operate(CacheOperateCommand::RemoveMultipartDir { meta_id })
+                .await
+            {
+                Ok(()) => swept += 1,
+                Err(e) => errors.push(format!("{meta_id}: {e}")),
+            }
+        }
+        if !errors.is_empty() {
+            bail!(
+                "Swept {swept} uploads, failed to sweep: {}",
+                errors.join("; ")
+            );
+        }
+        Ok(swept)
+    }
+}
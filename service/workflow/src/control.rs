@@ -2,28 +2,42 @@ use std::sync::Arc;
 
 use alice_architecture::{
     message_queue::producer::MessageQueueProducerTemplate,
-    repository::{MutableRepository, ReadOnlyRepository},
+    repository::{DbField, ReadOnlyRepository},
 };
 use async_trait::async_trait;
+use domain_content_repo::{model::vo::PinnedPackage, service::SoftwareComputingUsecaseInfoService};
 use domain_storage::model::entity::FileMeta;
 use domain_workflow::{
     exception::{WorkflowException, WorkflowResult},
     model::{
         entity::{
-            workflow_draft::WorkflowDraftSpec, NodeInstance, WorkflowDraft, WorkflowInstance,
+            node_instance::{DbNodeInstance, NodeInstanceStatus},
+            workflow_draft::WorkflowDraftSpec,
+            workflow_instance::WorkflowInstanceStatus,
+            WorkflowDraft, WorkflowInstance,
+        },
+        vo::{
+            msg::{ChangeMsg, FlowStatusChange, Info, NodeChangeInfo, NodeStatusChange},
+            NodeKind,
         },
-        vo::msg::{ChangeMsg, FlowStatusChange, Info},
     },
-    service::ControlService,
+    repository::{NodeInstanceRepo, TaskRepo, WorkflowInstanceRepo},
+    service::{ControlService, QuotaService},
 };
 use uuid::Uuid;
 
+use crate::schedule::batch::BatchService;
+
 #[derive(typed_builder::TypedBuilder)]
 pub struct ControlServiceImpl {
     draft_repo: Arc<dyn ReadOnlyRepository<WorkflowDraft>>,
-    instance_repo: Arc<dyn MutableRepository<WorkflowInstance>>,
-    node_repo: Arc<dyn MutableRepository<NodeInstance>>,
+    instance_repo: Arc<dyn WorkflowInstanceRepo>,
+    node_repo: Arc<dyn NodeInstanceRepo>,
+    task_repo: Arc<dyn TaskRepo>,
     file_meta_repo: Arc<dyn ReadOnlyRepository<FileMeta>>,
+    batch_service: Arc<BatchService>,
+    quota_service: Arc<dyn QuotaService>,
+    computing_usecase_repo: Arc<dyn SoftwareComputingUsecaseInfoService>,
     status_mq_producer: Arc<dyn MessageQueueProducerTemplate<ChangeMsg>>,
     status_mq_topic: String,
 }
@@ -34,7 +48,8 @@ impl ControlService for ControlServiceImpl {
         let draft = self.draft_repo.get_by_id(draft_id).await?;
         let spec = &draft.spec;
         self.validate_workflow_draft(spec).await?;
-        let instance = WorkflowInstance::from(draft);
+        let mut instance = WorkflowInstance::from(draft);
+        self.pin_packages(&mut instance).await?;
         self.instance_repo.insert(&instance).await?;
         let nodes = instance.parse_node_instances().await?;
         self.node_repo.insert_list(&nodes).await?;
@@ -42,6 +57,12 @@ impl ControlService for ControlServiceImpl {
         Ok(instance.id)
     }
     async fn start(&self, instance_id: Uuid) -> WorkflowResult<()> {
+        let flow = self.instance_repo.get_by_id(instance_id).await?;
+        if let Some(violation) = self.quota_service.check_flow(&flow).await? {
+            return Err(WorkflowException::QuotaExceeded {
+                reason: violation.reason,
+            });
+        }
         self.status_mq_producer
             .send_object(
                 &ChangeMsg {
@@ -93,6 +114,95 @@ impl ControlService for ControlServiceImpl {
         Ok(())
     }
 
+    async fn retry_from_failure(&self, instance_id: Uuid) -> WorkflowResult<()> {
+        let flow = self.instance_repo.get_by_id(instance_id).await?;
+        if !matches!(
+            flow.status,
+            WorkflowInstanceStatus::Failed | WorkflowInstanceStatus::Terminated
+        ) {
+            return Err(WorkflowException::WorkflowNotRetryable { id: instance_id });
+        }
+
+        let nodes = self.node_repo.get_all_workflow_instance_nodes(instance_id).await?;
+        let mut failed_nodes_ids = vec![];
+        for node in nodes.iter().filter(|n| {
+            matches!(
+                n.status,
+                NodeInstanceStatus::Failed | NodeInstanceStatus::Terminated
+            )
+        }) {
+            let id = node.batch_parent_id.unwrap_or(node.id);
+            if !failed_nodes_ids.contains(&id) {
+                failed_nodes_ids.push(id);
+            }
+        }
+        let nodes_to_retry_ids = flow.nodes_to_retry(&failed_nodes_ids);
+
+        // Completed nodes are untouched, so their prepared outputs in the spec are reused by the
+        // rerun nodes.
+        for node in nodes
+            .iter()
+            .filter(|n| nodes_to_retry_ids.contains(&n.batch_parent_id.unwrap_or(n.id)))
+        {
+            self.node_repo
+                .update(DbNodeInstance {
+                    id: DbField::Unchanged(node.id),
+                    status: DbField::Set(NodeInstanceStatus::Standby),
+                    log: DbField::Set(None),
+                    ..Default::default()
+                })
+                .await?;
+            self.task_repo.delete_by_node_id(node.id).await?;
+        }
+        self.node_repo.save_changed().await?;
+        self.task_repo.save_changed().await?;
+
+        self.status_mq_producer
+            .send_object(
+                &ChangeMsg {
+                    id: instance_id,
+                    info: Info::Flow(FlowStatusChange::Running { is_resumed: false }),
+                },
+                &self.status_mq_topic,
+            )
+            .await?;
+
+        // Rerun nodes whose dependencies are all satisfied.
+        let standby_nodes_ids = nodes
+            .iter()
+            .filter(|n| {
+                matches!(n.status, NodeInstanceStatus::Standby)
+                    || nodes_to_retry_ids.contains(&n.batch_parent_id.unwrap_or(n.id))
+            })
+            .map(|n| n.id)
+            .collect::<Vec<_>>();
+        let node_dependencies: Vec<(Uuid, Uuid)> = flow
+            .spec
+            .node_relations
+            .iter()
+            .filter(|el| standby_nodes_ids.contains(&el.from_id))
+            .map(|el| (el.from_id.to_owned(), el.to_id.to_owned()))
+            .collect();
+        let entry_nodes_ids =
+            BatchService::find_entry_nodes_ids(&standby_nodes_ids, &node_dependencies).await;
+        let task_node_specs = self.batch_service.get_task_node_specs(flow, entry_nodes_ids).await?;
+        for task_node_spec in task_node_specs.iter() {
+            self.status_mq_producer
+                .send_object(
+                    &ChangeMsg {
+                        id: task_node_spec.id,
+                        info: Info::Node(NodeChangeInfo {
+                            status: NodeStatusChange::Pending,
+                            ..Default::default()
+                        }),
+                    },
+                    &self.status_mq_topic,
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
     async fn validate(&self, draft_id: Uuid) -> WorkflowResult<()> {
         let draft = self.draft_repo.get_by_id(draft_id).await?;
         let spec = &draft.spec;
@@ -109,13 +219,52 @@ impl ControlServiceImpl {
     /// 5. MatchRegex 类型批量输入必须等于 1
     /// 6. 调度策略 Manual 和 Prefer 至少选一个队列
     /// 7. 所有输入文件必须在 FileMeta 表中存在
+    /// 8. 循环中提及的节点必须存在且在循环体中
     async fn validate_workflow_draft(&self, data: &WorkflowDraftSpec) -> WorkflowResult<()> {
         if data.node_drafts.is_empty() {
             return Err(WorkflowException::EmptyNodeDrafts);
         }
         let relied_input_slots = data.validate_related_nodes().await?;
+        data.validate_loops()?;
         data.validate_per_node(relied_input_slots, self.file_meta_repo.to_owned())
             .await?;
         Ok(())
     }
+
+    /// 记录软件用例节点提交时的软件包、用例包哈希以及解析得到的依赖版本与哈希，
+    /// 重新运行时使用相同内容的包；同时记录用例包规定的资源需求，调度时无需再获取包
+    async fn pin_packages(&self, instance: &mut WorkflowInstance) -> anyhow::Result<()> {
+        for node_spec in instance.spec.node_specs.iter_mut() {
+            let data = match &mut node_spec.kind {
+                NodeKind::SoftwareUsecaseComputing { data } => data,
+                _ => continue,
+            };
+            if data.software_package_hash.is_none() || data.usecase_package_hash.is_none() {
+                let computing_usecase = self
+                    .computing_usecase_repo
+                    .get_computing_usecase(data.software_version_id, data.usecase_version_id)
+                    .await?;
+                data.software_package_hash = Some(computing_usecase.software_hash);
+                data.usecase_package_hash = Some(computing_usecase.usecase_hash);
+                data.package_requirements =
+                    computing_usecase.usecase_spec.requirements.map(Into::into);
+            }
+            if data.pinned_dependencies.is_none() {
+                let dependencies = self
+                    .computing_usecase_repo
+                    .get_software_dependencies(data.software_version_id)
+                    .await?;
+                data.pinned_dependencies = Some(
+                    dependencies
+                        .into_iter()
+                        .map(|el| PinnedPackage {
+                            version_id: el.software_version_id,
+                            hash: el.hash,
+                        })
+                        .collect(),
+                );
+            }
+        }
+        Ok(())
+    }
 }
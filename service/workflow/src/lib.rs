@@ -1,12 +1,16 @@
 mod control;
 #[allow(clippy::module_inception)]
 mod queue_resource;
+mod quota;
 mod schedule;
+mod scheduler_backlog;
 mod task_status_receiver;
 mod use_cases;
 
 pub use control::ControlServiceImpl;
 pub use queue_resource::QueueResourceServiceImpl;
+pub use quota::QuotaServiceImpl;
 pub use schedule::*;
+pub use scheduler_backlog::SchedulerBacklogServiceImpl;
 pub use task_status_receiver::TaskStatusReceiveServiceImpl;
 pub use use_cases::*;
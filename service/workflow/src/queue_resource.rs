@@ -2,30 +2,46 @@ use std::sync::Arc;
 
 use alice_architecture::message_queue::producer::MessageQueueProducerTemplate;
 use alice_architecture::repository::DBRepository;
-use anyhow::{bail, Context};
+use anyhow::{anyhow, bail};
 use async_trait::async_trait;
 use domain_workflow::{
     model::{
         entity::{
-            queue::{QueueCacheInfo, QueueResourceUsed},
+            queue::{QueueCacheInfo, QueueRejection, QueueResourceUsed, QueueSelection},
             Queue,
         },
         vo::{
-            msg::{ChangeMsg, Info, TaskChangeInfo, TaskStatusChange},
-            SchedulingStrategy,
+            backlog::BacklogEntry,
+            msg::{ChangeMsg, Info, NodeChangeInfo, NodeStatusChange},
+            QueueScoring, Requirements, SchedulingStrategy,
         },
     },
-    service::QueueResourceService,
+    repository::{NodeInstanceRepo, QueueCacheRepo, SchedulerBacklogRepo, WorkflowInstanceRepo},
+    service::{QueueResourceService, QuotaService},
 };
 use rand::{seq::SliceRandom, thread_rng};
 use typed_builder::TypedBuilder;
 use uuid::Uuid;
 
+/// Times to select a queue again when another node takes the capacity of the selected one first.
+const RESERVE_ATTEMPTS: usize = 3;
+
 #[derive(TypedBuilder)]
 pub struct QueueResourceServiceImpl {
     queue_resource_repo: Arc<dyn DBRepository<Queue>>,
+    queue_cache_repo: Arc<dyn QueueCacheRepo>,
+    backlog_repo: Arc<dyn SchedulerBacklogRepo>,
+    node_repo: Arc<dyn NodeInstanceRepo>,
+    flow_repo: Arc<dyn WorkflowInstanceRepo>,
+    quota_service: Arc<dyn QuotaService>,
     status_mq_producer: Arc<dyn MessageQueueProducerTemplate<ChangeMsg>>,
     status_mq_topic: String,
+    /// Producer to notify the scheduler backlog to drain.
+    backlog_mq_producer: Arc<dyn MessageQueueProducerTemplate<Uuid>>,
+    backlog_mq_topic: String,
+    /// Queue scoring used when scheduling strategy doesn't specify one.
+    #[builder(default)]
+    default_scoring: QueueScoring,
 }
 
 impl QueueResourceServiceImpl {
@@ -35,81 +51,306 @@ impl QueueResourceServiceImpl {
         queues.shuffle(&mut rng);
         Ok(queues)
     }
+
+    /// Split queues into the available ones along with their cache info, and the rejected ones.
+    async fn available_queues(
+        &self,
+        queues: Vec<Queue>,
+        requirements: Option<&Requirements>,
+        rejections: &mut Vec<QueueRejection>,
+    ) -> anyhow::Result<Vec<(Queue, QueueCacheInfo)>> {
+        let mut result = vec![];
+        for queue in queues {
+            let cache_info = self.queue_cache_repo.get_cache_info(queue.id).await?;
+            let reason = if !queue.enabled {
+                Some(("queue is disabled".to_string(), false))
+            } else if let Err(e) = queue.fits(requirements) {
+                Some((e.to_string(), false))
+            } else if let Err(e) = cache_info.is_full(&queue) {
+                Some((e.to_string(), true))
+            } else {
+                None
+            };
+            match reason {
+                Some((reason, full)) => rejections.push(QueueRejection {
+                    queue_id: queue.id,
+                    queue_name: Some(queue.name),
+                    reason,
+                    full,
+                }),
+                None => result.push((queue, cache_info)),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Get queues by ids in order, missing ones are rejected.
+    async fn get_queues_by_ids(
+        &self,
+        ids: &[Uuid],
+        rejections: &mut Vec<QueueRejection>,
+    ) -> Vec<Queue> {
+        let mut result = vec![];
+        for id in ids {
+            match self.queue_resource_repo.get_by_id(*id).await {
+                Ok(queue) => result.push(queue),
+                Err(e) => rejections.push(QueueRejection {
+                    queue_id: *id,
+                    queue_name: None,
+                    reason: e.to_string(),
+                    full: false,
+                }),
+            }
+        }
+        result
+    }
+
+    /// Round of weighted round robin scoring, counted per queue set of the strategy and shared
+    /// by all instances. Other scorings don't count rounds.
+    async fn next_round(
+        &self,
+        scheduling_strategy: &SchedulingStrategy,
+        scoring: &QueueScoring,
+    ) -> anyhow::Result<usize> {
+        if !matches!(scoring, QueueScoring::WeightedRoundRobin { .. }) {
+            return Ok(0);
+        }
+        let sorted = |ids: &[Uuid]| {
+            let mut ids = ids.iter().map(Uuid::to_string).collect::<Vec<_>>();
+            ids.sort();
+            ids.join(",")
+        };
+        let queue_set = match scheduling_strategy {
+            SchedulingStrategy::Manual { queues, .. } => format!("manual_{}", sorted(queues)),
+            SchedulingStrategy::Auto { .. } => "auto".to_string(),
+            SchedulingStrategy::Prefer { queues, .. } => format!("prefer_{}", sorted(queues)),
+        };
+        self.queue_cache_repo.next_round(&queue_set).await
+    }
+
+    /// Report message to the node, and change the node's status if status is provided.
+    async fn send_node_status(
+        &self,
+        node_id: Uuid,
+        status: Option<NodeStatusChange>,
+        message: String,
+    ) -> anyhow::Result<()> {
+        self.status_mq_producer
+            .send_object(
+                &ChangeMsg {
+                    id: node_id,
+                    info: Info::Node(NodeChangeInfo {
+                        do_not_update_status: status.is_none(),
+                        status: status.unwrap_or_default(),
+                        message: Some(message),
+                        ..Default::default()
+                    }),
+                },
+                &self.status_mq_topic,
+            )
+            .await
+            .map_err(|e| anyhow!("send message failed: {}", e))
+    }
+
+    /// Notify the scheduler backlog to drain, id is the queue whose capacity is freed or the
+    /// node waiting.
+    async fn notify_backlog(&self, id: Uuid) {
+        if let Err(e) = self.backlog_mq_producer.send_object(&id, &self.backlog_mq_topic).await {
+            tracing::error!("Failed to notify scheduler backlog of {id}: {e}");
+        }
+    }
+
+    async fn reserve(
+        &self,
+        queue: &Queue,
+        node_id: Uuid,
+        requirements: Option<&Requirements>,
+    ) -> anyhow::Result<()> {
+        let used = QueueResourceUsed::of_requirements(requirements);
+        self.queue_cache_repo.reserve(queue, node_id, &used).await
+    }
+
+    /// Select a queue and reserve its resources for the node. The selection is made again when
+    /// the reservation fails, the selected queue is None when no reservation is made.
+    async fn select_and_reserve(
+        &self,
+        node_id: Uuid,
+        scheduling_strategy: &SchedulingStrategy,
+        requirements: Option<&Requirements>,
+    ) -> anyhow::Result<QueueSelection> {
+        let mut rejections = vec![];
+        for _ in 0..RESERVE_ATTEMPTS {
+            let mut selection = self.select_queue(scheduling_strategy, requirements).await?;
+            let queue = match selection.queue.take() {
+                Some(queue) => queue,
+                None => {
+                    selection.rejections.extend(rejections);
+                    return Ok(selection);
+                }
+            };
+            match self.reserve(&queue, node_id, requirements).await {
+                Ok(()) => {
+                    selection.queue = Some(queue);
+                    return Ok(selection);
+                }
+                // Taken by another node since the selection.
+                Err(e) => rejections.push(QueueRejection {
+                    queue_id: queue.id,
+                    queue_name: Some(queue.name),
+                    reason: e.to_string(),
+                    full: true,
+                }),
+            }
+        }
+        Ok(QueueSelection {
+            queue: None,
+            rejections,
+        })
+    }
+
+    /// Keep the node Pending in the scheduler backlog with its priority until the backlog
+    /// dispatches it again.
+    async fn wait_in_backlog(&self, node_id: Uuid, message: String) -> anyhow::Result<()> {
+        // Sub nodes of a batch take the priority of their parent in the spec.
+        let node = self.node_repo.get_by_id(node_id).await?;
+        let priority = self
+            .flow_repo
+            .get_by_id(node.flow_instance_id)
+            .await?
+            .spec
+            .node_priority(node.batch_parent_id.unwrap_or(node.id));
+        self.backlog_repo.push(&BacklogEntry::new(node_id, priority)).await?;
+        self.send_node_status(node_id, None, message).await?;
+        // Drain at once so that a node above the default priority can preempt lower ones.
+        if priority > 0 {
+            self.notify_backlog(node_id).await;
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
 impl QueueResourceService for QueueResourceServiceImpl {
     async fn get_queue(
         &self,
-        task_id: Uuid,
+        node_id: Uuid,
         scheduling_strategy: &SchedulingStrategy,
-    ) -> anyhow::Result<Queue> {
-        let mut result_queues = vec![];
-        match scheduling_strategy {
-            SchedulingStrategy::Manual { queues } => {
-                for queue in queues {
-                    let queue = self.queue_resource_repo.get_by_id(*queue).await?;
-                    result_queues.push(queue);
-                }
+        requirements: Option<&Requirements>,
+    ) -> anyhow::Result<Option<Queue>> {
+        match self.quota_service.start_node(node_id, requirements).await? {
+            Some(violation) if violation.can_wait => {
+                self.wait_in_backlog(
+                    node_id,
+                    format!("Waiting for resource quota. {}.", violation.reason),
+                )
+                .await?;
+                return Ok(None);
             }
-            SchedulingStrategy::Auto => {
-                let queues = self.get_all_quques().await?;
-                result_queues.extend(queues);
-            }
-            SchedulingStrategy::Prefer { queues } => {
-                for queue in queues {
-                    let queue = self.queue_resource_repo.get_by_id(*queue).await?;
-                    result_queues.push(queue);
-                }
-            }
-        };
-
-        let mut not_full_queues = vec![];
-        for queue in result_queues {
-            if queue.enabled && Queue::is_resource_full(&queue).await.is_ok() {
-                not_full_queues.push(queue);
+            Some(violation) => {
+                let message = format!("Resource quota exceeded: {}.", violation.reason);
+                self.send_node_status(node_id, Some(NodeStatusChange::Failed), message.to_owned())
+                    .await?;
+                bail!(message)
             }
+            None => {}
         }
-        if not_full_queues.is_empty() {
-            let change_msg = ChangeMsg {
-                id: task_id,
-                info: Info::Task(TaskChangeInfo {
-                    status: TaskStatusChange::Failed,
-                    message: Some("no queue available".to_string()),
-                    used_resources: None,
-                }),
+
+        // The node is counted in the quotas from now on, it is uncounted if no queue is reserved.
+        let selection =
+            match self.select_and_reserve(node_id, scheduling_strategy, requirements).await {
+                Ok(selection) => selection,
+                Err(e) => {
+                    self.quota_service.cancel_node(node_id).await?;
+                    return Err(e);
+                }
             };
-            let prefer_fallback_queues = self.get_all_quques().await?;
-            let mut not_full_queue = vec![];
-            for queue in prefer_fallback_queues {
-                if queue.enabled && Queue::is_resource_full(&queue).await.is_ok() {
-                    not_full_queue.push(queue);
+        if selection.queue.is_none() {
+            self.quota_service.cancel_node(node_id).await?;
+        }
+        let message = selection.message();
+        match selection.queue {
+            Some(queue) => {
+                if !selection.rejections.is_empty() {
+                    self.send_node_status(node_id, None, message).await?;
                 }
+                Ok(Some(queue))
             }
-            if not_full_queue.is_empty() {
-                self.status_mq_producer.send_object(&change_msg, &self.status_mq_topic).await?;
-                bail!("no queue available");
+            None if selection.can_wait() => {
+                self.wait_in_backlog(node_id, format!("Waiting for queue capacity. {message}"))
+                    .await?;
+                Ok(None)
+            }
+            None => {
+                self.send_node_status(node_id, Some(NodeStatusChange::Failed), message.to_owned())
+                    .await?;
+                bail!(message)
             }
-
-            self.status_mq_producer.send_object(&change_msg, &self.status_mq_topic).await?;
-            bail!("no queue available");
         }
-
-        Ok(not_full_queues
-            .first()
-            .context("ResourcesService impossible error.")?
-            .to_owned())
     }
-    async fn add_used_queue_resources(&self, queue: &Queue) -> anyhow::Result<()> {
-        Queue::cache_resource(queue).await
+
+    async fn select_queue(
+        &self,
+        scheduling_strategy: &SchedulingStrategy,
+        requirements: Option<&Requirements>,
+    ) -> anyhow::Result<QueueSelection> {
+        let scoring = scheduling_strategy.scoring().unwrap_or(&self.default_scoring);
+        let round = self.next_round(scheduling_strategy, scoring).await?;
+        let mut rejections = vec![];
+        let queue = match scheduling_strategy {
+            SchedulingStrategy::Manual { queues, .. } => {
+                let queues = self.get_queues_by_ids(queues, &mut rejections).await;
+                let queues = self.available_queues(queues, requirements, &mut rejections).await?;
+                scoring.select(queues, requirements, round)
+            }
+            SchedulingStrategy::Auto { .. } => {
+                let queues = self.get_all_quques().await?;
+                let queues = self.available_queues(queues, requirements, &mut rejections).await?;
+                scoring.select(queues, requirements, round)
+            }
+            SchedulingStrategy::Prefer { queues: ids, .. } => {
+                // Preferred queues are tried in order, then fall back to the global pool.
+                let queues = self.get_queues_by_ids(ids, &mut rejections).await;
+                let preferred =
+                    self.available_queues(queues, requirements, &mut rejections).await?;
+                match preferred.into_iter().next() {
+                    Some((queue, _)) => Some(queue),
+                    None => {
+                        let queues = self
+                            .get_all_quques()
+                            .await?
+                            .into_iter()
+                            .filter(|q| !ids.contains(&q.id))
+                            .collect();
+                        let queues =
+                            self.available_queues(queues, requirements, &mut rejections).await?;
+                        scoring.select(queues, requirements, round)
+                    }
+                }
+            }
+        };
+        Ok(QueueSelection { queue, rejections })
     }
 
-    async fn release_used_queue_resources(
+    async fn reserve_node(
         &self,
         queue_id: Uuid,
-        resource_used: &QueueResourceUsed,
-    ) {
-        Queue::release_resource(queue_id, resource_used).await;
+        node_id: Uuid,
+        requirements: Option<&Requirements>,
+    ) -> anyhow::Result<()> {
+        let queue = self.queue_resource_repo.get_by_id(queue_id).await?;
+        self.reserve(&queue, node_id, requirements).await
+    }
+
+    async fn release_node(&self, queue_id: Uuid, node_id: Uuid) {
+        match self.queue_cache_repo.release(queue_id, node_id).await {
+            Ok(true) => self.notify_backlog(queue_id).await,
+            Ok(false) => {}
+            Err(e) => {
+                tracing::error!(
+                    "Failed to release resources of queue {queue_id} for {node_id}: {e}"
+                )
+            }
+        }
     }
 
     async fn insert_queue(&self, queue: &Queue) -> anyhow::Result<()> {
@@ -118,21 +359,28 @@ impl QueueResourceService for QueueResourceServiceImpl {
         Ok(())
     }
 
-    async fn task_started(&self, queue_id: Uuid) -> anyhow::Result<()> {
-        let queue = self.queue_resource_repo.get_by_id(queue_id).await?;
-        Queue::task_started(&queue).await
+    async fn task_started(&self, queue_id: Uuid, node_id: Uuid) -> anyhow::Result<()> {
+        self.queue_cache_repo.start(queue_id, node_id).await
     }
 
     async fn update_queue_resource(&self, queue_id: Uuid, info: &QueueCacheInfo) {
-        Queue::update_resource(queue_id, info).await
+        let old = self.queue_cache_repo.get_cache_info(queue_id).await.unwrap_or_default();
+        if let Err(e) = self.queue_cache_repo.set_reported_usage(queue_id, info).await {
+            tracing::error!("Failed to update resources of queue {queue_id}: {e}");
+            return;
+        }
+        let new = self.queue_cache_repo.get_cache_info(queue_id).await.unwrap_or_default();
+        if new.is_freed_from(&old) {
+            self.notify_backlog(queue_id).await;
+        }
     }
 
     async fn test_queue_run_out_of_resource(&self, queue_id: Uuid) -> anyhow::Result<()> {
         let queue = self.queue_resource_repo.get_by_id(queue_id).await?;
-        Queue::is_resource_full(&queue).await
+        self.queue_cache_repo.get_cache_info(queue_id).await?.is_full(&queue)
     }
 
     async fn get_queue_cache_info(&self, queue_id: Uuid) -> anyhow::Result<QueueCacheInfo> {
-        Queue::get_cache_info(queue_id).await
+        self.queue_cache_repo.get_cache_info(queue_id).await
     }
 }
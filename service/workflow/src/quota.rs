@@ -0,0 +1,173 @@
+use std::sync::Arc;
+
+use alice_architecture::message_queue::producer::MessageQueueProducerTemplate;
+use async_trait::async_trait;
+use domain_workflow::{
+    model::{
+        entity::WorkflowInstance,
+        vo::{
+            quota::{QuotaConfig, QuotaLimit, QuotaSubject, QuotaUsage, QuotaViolation},
+            task_dto::result::TaskUsedResource,
+            Requirements,
+        },
+    },
+    repository::{QuotaRepo, WorkflowInstanceRepo},
+    service::QuotaService,
+};
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+#[derive(TypedBuilder)]
+pub struct QuotaServiceImpl {
+    quota_repo: Arc<dyn QuotaRepo>,
+    flow_repo: Arc<dyn WorkflowInstanceRepo>,
+    /// Producer to notify the scheduler backlog with the node id whose quota is released.
+    backlog_mq_producer: Arc<dyn MessageQueueProducerTemplate<Uuid>>,
+    backlog_mq_topic: String,
+    #[builder(default)]
+    config: QuotaConfig,
+}
+
+impl QuotaServiceImpl {
+    fn subjects(flow: &WorkflowInstance) -> Vec<QuotaSubject> {
+        let mut subjects = vec![QuotaSubject::User(flow.user_id)];
+        if let Some(project_id) = flow.project_id {
+            subjects.push(QuotaSubject::Project(project_id));
+        }
+        subjects
+    }
+
+    /// Cores occupied by a node, one core when not specified.
+    fn cores(requirements: Option<&Requirements>) -> u64 {
+        requirements.and_then(|el| el.cpu_cores).unwrap_or(1) as u64
+    }
+
+    async fn check(
+        &self,
+        flow: &WorkflowInstance,
+        cores: Option<u64>,
+    ) -> anyhow::Result<Option<QuotaViolation>> {
+        for subject in Self::subjects(flow) {
+            let limit = match self.config.limit(&subject) {
+                Some(limit) => limit,
+                None => continue,
+            };
+            let usage = self.quota_repo.get_usage(&subject).await?;
+            if let Some(violation) = limit.check(&subject, &usage, cores) {
+                return Ok(Some(violation));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Check the quota of the subject and count the node as running atomically.
+    async fn start_for(
+        &self,
+        subject: &QuotaSubject,
+        node_id: Uuid,
+        cores: u64,
+    ) -> anyhow::Result<Option<QuotaViolation>> {
+        let limit = match self.config.limit(subject) {
+            Some(limit) => limit,
+            None => {
+                self.quota_repo.add_running_node(subject, node_id, cores).await?;
+                return Ok(None);
+            }
+        };
+        // Core hours are only spent by finished tasks, so they are checked beforehand.
+        let core_hours = self.quota_repo.get_usage(subject).await?.core_hours;
+        let spent = QuotaUsage {
+            core_hours,
+            ..Default::default()
+        };
+        if let Some(violation) = limit.check(subject, &spent, Some(0)) {
+            return Ok(Some(violation));
+        }
+        let usage =
+            match self.quota_repo.try_add_running_node(subject, node_id, cores, limit).await? {
+                Some(usage) => usage,
+                None => return Ok(None),
+            };
+        Ok(Some(Self::running_violation(subject, limit, &usage, cores)))
+    }
+
+    /// Why running the node exceeds the limit with the usage.
+    fn running_violation(
+        subject: &QuotaSubject,
+        limit: &QuotaLimit,
+        usage: &QuotaUsage,
+        cores: u64,
+    ) -> QuotaViolation {
+        limit.check(subject, usage, Some(cores)).unwrap_or_else(|| QuotaViolation {
+            reason: format!("{subject} quota is exceeded"),
+            can_wait: true,
+        })
+    }
+}
+
+#[async_trait]
+impl QuotaService for QuotaServiceImpl {
+    async fn check_flow(&self, flow: &WorkflowInstance) -> anyhow::Result<Option<QuotaViolation>> {
+        self.check(flow, None).await
+    }
+
+    async fn check_node(
+        &self,
+        node_id: Uuid,
+        requirements: Option<&Requirements>,
+    ) -> anyhow::Result<Option<QuotaViolation>> {
+        let flow = self.flow_repo.get_by_node_id(node_id).await?;
+        self.check(&flow, Some(Self::cores(requirements))).await
+    }
+
+    async fn start_node(
+        &self,
+        node_id: Uuid,
+        requirements: Option<&Requirements>,
+    ) -> anyhow::Result<Option<QuotaViolation>> {
+        let flow = self.flow_repo.get_by_node_id(node_id).await?;
+        let cores = Self::cores(requirements);
+        let mut started = vec![];
+        for subject in Self::subjects(&flow) {
+            if let Some(violation) = self.start_for(&subject, node_id, cores).await? {
+                // Subjects are counted one by one, undo the ones counted.
+                for subject in started.iter() {
+                    self.quota_repo.remove_running_node(subject, node_id).await?;
+                }
+                return Ok(Some(violation));
+            }
+            started.push(subject);
+        }
+        Ok(None)
+    }
+
+    async fn cancel_node(&self, node_id: Uuid) -> anyhow::Result<()> {
+        let flow = self.flow_repo.get_by_node_id(node_id).await?;
+        for subject in Self::subjects(&flow) {
+            self.quota_repo.remove_running_node(&subject, node_id).await?;
+        }
+        Ok(())
+    }
+
+    async fn node_finished(&self, node_id: Uuid) -> anyhow::Result<()> {
+        let flow = self.flow_repo.get_by_node_id(node_id).await?;
+        for subject in Self::subjects(&flow) {
+            self.quota_repo.remove_running_node(&subject, node_id).await?;
+        }
+        if let Err(e) = self.backlog_mq_producer.send_object(&node_id, &self.backlog_mq_topic).await
+        {
+            tracing::error!("Failed to notify scheduler backlog of node {node_id}: {e}");
+        }
+        Ok(())
+    }
+
+    async fn record_spend(&self, node_id: Uuid, used: &TaskUsedResource) -> anyhow::Result<()> {
+        // cpu_time is in core seconds.
+        let core_hours = used.cpu_time as f64 / 3600.0;
+        let flow = self.flow_repo.get_by_node_id(node_id).await?;
+        for subject in Self::subjects(&flow) {
+            self.quota_repo.add_core_hours(&subject, core_hours).await?;
+        }
+        Ok(())
+    }
+}
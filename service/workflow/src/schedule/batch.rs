@@ -1,6 +1,6 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, thread::sleep, time::Duration};
 
-use alice_architecture::repository::DBRepository;
+use alice_architecture::repository::{DBRepository, DbField};
 use chrono::Utc;
 use domain_storage::{
     model::{
@@ -12,7 +12,8 @@ use domain_storage::{
 use domain_workflow::{
     model::{
         entity::{
-            workflow_instance::{NodeSpec, NodeSpecOutputSlotKind},
+            node_instance::NodeInstanceStatus,
+            workflow_instance::{DbWorkflowInstance, NodeSpec, NodeSpecOutputSlotKind},
             WorkflowInstance,
         },
         vo::{
@@ -22,6 +23,7 @@ use domain_workflow::{
     },
     repository::{NodeInstanceRepo, WorkflowInstanceRepo},
 };
+use rand::Rng;
 use uuid::Uuid;
 
 #[derive(typed_builder::TypedBuilder)]
@@ -51,7 +53,7 @@ impl BatchService {
 
         // Iterate the entry node list
         for entry_node in entry_nodes.iter_mut() {
-            if let SchedulingStrategy::Auto = entry_node.scheduling_strategy {
+            if let SchedulingStrategy::Auto { scoring: None } = entry_node.scheduling_strategy {
                 entry_node.scheduling_strategy = flow_schedule_strategy.to_owned();
             }
             // Get this entry node's relations.
@@ -150,6 +152,70 @@ impl BatchService {
         }
         Ok(task_node_specs)
     }
+
+    /// 如果节点是某个循环的终止条件节点，且循环未结束，则生成循环的下一次迭代
+    /// 新迭代的节点实例处于待命状态，由之后的调度作为入口节点启动
+    /// 返回是否生成了下一次迭代
+    ///
+    /// # 参数
+    ///
+    /// * `node_id` - 已完成的节点 id
+    pub async fn iterate_loop(&self, node_id: Uuid) -> anyhow::Result<bool> {
+        let new_node_instances = loop {
+            let mut flow = self.workflow_instance_repository.get_by_node_id(node_id).await?;
+            let loop_index =
+                match flow.spec.loops.iter().position(|el| el.until.node_id.eq(&node_id)) {
+                    Some(x) => x,
+                    None => return Ok(false),
+                };
+
+            let mut texts = HashMap::new();
+            for output_slot in flow.spec.node(node_id).output_slots.iter() {
+                if let NodeSpecOutputSlotKind::Text {
+                    all_tasks_prepared_text_keys,
+                } = &output_slot.kind
+                {
+                    if let Some(key) = all_tasks_prepared_text_keys.first() {
+                        let value = self.text_storage_repository.get_by_id(*key).await?.value;
+                        texts.insert(output_slot.descriptor.to_owned(), value);
+                    }
+                }
+            }
+            if flow.spec.loops[loop_index].is_done(&texts)? {
+                return Ok(false);
+            }
+
+            let new_node_specs = flow.next_loop_iteration(loop_index)?;
+            let new_node_instances = new_node_specs
+                .iter()
+                .flat_map(|el| flow.node_instances(el))
+                .map(|mut el| {
+                    el.status = NodeInstanceStatus::Standby;
+                    el
+                })
+                .collect::<Vec<_>>();
+
+            if self
+                .workflow_instance_repository
+                .update_immediately_with_lock(DbWorkflowInstance {
+                    id: DbField::Unchanged(flow.id),
+                    spec: DbField::Set(flow.spec),
+                    last_modified_time: DbField::Unchanged(flow.last_modified_time),
+                    ..Default::default()
+                })
+                .await
+                .is_ok()
+            {
+                break new_node_instances;
+            }
+            sleep(Duration::from_millis(rand::thread_rng().gen_range(10..100)));
+        };
+
+        self.node_instance_repository.insert_list(&new_node_instances).await?;
+        self.node_instance_repository.save_changed().await?;
+        Ok(true)
+    }
+
     /// 传入节点 id 集合、节点依赖关系 id 集合，获得一批入口节点 id
     pub async fn find_entry_nodes_ids(
         node_ids: &[Uuid],
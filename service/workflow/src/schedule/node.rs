@@ -16,7 +16,7 @@ use domain_workflow::{
         },
     },
     repository::{NodeInstanceRepo, TaskRepo, WorkflowInstanceRepo},
-    service::{ScheduleService, UsecaseSelectService},
+    service::{QueueResourceService, QuotaService, ScheduleService, UsecaseSelectService},
 };
 use rand::Rng;
 use uuid::Uuid;
@@ -34,6 +34,8 @@ pub struct NodeScheduleServiceImpl {
     bill_mq_topic: String,
     usecase_select_service: Arc<dyn UsecaseSelectService>,
     batch_service: Arc<BatchService>,
+    quota_service: Arc<dyn QuotaService>,
+    queue_resource_service: Arc<dyn QueueResourceService>,
 }
 
 #[async_trait]
@@ -116,6 +118,8 @@ impl ScheduleService for NodeScheduleServiceImpl {
             NodeStatusChange::Completed => {
                 // Send bill message.
                 self.bill_mq_producer.send_object(&id, &self.bill_mq_topic).await?;
+                self.quota_service.node_finished(id).await?;
+                self.release_queue(id).await?;
 
                 // Firstly, judge if all nodes meet the condition to continue.
 
@@ -155,6 +159,10 @@ impl ScheduleService for NodeScheduleServiceImpl {
                     return Ok(());
                 }
 
+                // If the node decides a loop, expand the next iteration before scheduling, so its
+                // downstream nodes wait for the new iteration.
+                self.batch_service.iterate_loop(id).await?;
+
                 let nodes =
                     self.node_repo.get_all_workflow_instance_nodes(node.flow_instance_id).await?;
 
@@ -170,11 +178,13 @@ impl ScheduleService for NodeScheduleServiceImpl {
                     .map(|n| n.id)
                     .collect::<Vec<_>>();
 
+                // Only relations from unscheduled nodes still block, relations from completed or
+                // skipped nodes are satisfied.
                 let node_dependencies: Vec<(Uuid, Uuid)> = flow
                     .spec
                     .node_relations
                     .iter()
-                    .filter(|el| el.from_id.ne(&id) && el.to_id.ne(&id))
+                    .filter(|el| unscheduled_nodes_ids.contains(&el.from_id))
                     .map(|el| (el.from_id.to_owned(), el.to_id.to_owned()))
                     .collect();
 
@@ -212,6 +222,8 @@ impl ScheduleService for NodeScheduleServiceImpl {
                 }
             }
             NodeStatusChange::Failed => {
+                self.quota_service.node_finished(id).await?;
+                self.release_queue(id).await?;
                 let flow_id = self.node_repo.get_by_id(id).await?.flow_instance_id;
                 self.status_mq_producer
                     .send_object(
@@ -247,6 +259,8 @@ impl ScheduleService for NodeScheduleServiceImpl {
                 }
             }
             NodeStatusChange::Terminated => {
+                self.quota_service.node_finished(id).await?;
+                self.release_queue(id).await?;
                 let node = self.node_repo.get_by_id(id).await?;
                 let can_make_super_as_terminated = |s: &NodeInstanceStatus| {
                     matches!(
@@ -254,6 +268,7 @@ impl ScheduleService for NodeScheduleServiceImpl {
                         NodeInstanceStatus::Standby
                             | NodeInstanceStatus::Terminated
                             | NodeInstanceStatus::Completed
+                            | NodeInstanceStatus::Skipped
                     )
                 };
                 if let Some(parent_id) = node.batch_parent_id {
@@ -292,6 +307,30 @@ impl ScheduleService for NodeScheduleServiceImpl {
                 // Toggled by flow instance start.
                 // In this branch, do nothing.
             }
+            NodeStatusChange::Skipped => {
+                // Toggled by condition node whose branch isn't activated, before it reports
+                // Completed. Skip the batch sub nodes as well.
+
+                let node = self.node_repo.get_by_id(id).await?;
+                if !node.is_parent {
+                    return Ok(());
+                }
+                let sub_nodes = self.node_repo.get_node_sub_node_instances(id).await?;
+                for n in sub_nodes.iter() {
+                    self.status_mq_producer
+                        .send_object(
+                            &ChangeMsg {
+                                id: n.id,
+                                info: Info::Node(NodeChangeInfo {
+                                    status: NodeStatusChange::Skipped,
+                                    ..Default::default()
+                                }),
+                            },
+                            &self.status_mq_topic,
+                        )
+                        .await?;
+                }
+            }
             NodeStatusChange::Pausing => {
                 // Similar as other 'ing' command.
                 let tasks = self.task_repo.get_tasks_by_node_id(id).await?;
@@ -325,6 +364,7 @@ impl ScheduleService for NodeScheduleServiceImpl {
                         NodeInstanceStatus::Completed
                             | NodeInstanceStatus::Standby
                             | NodeInstanceStatus::Paused
+                            | NodeInstanceStatus::Skipped
                     )
                 };
                 if let Some(parent_id) = node.batch_parent_id {
@@ -435,3 +475,14 @@ impl ScheduleService for NodeScheduleServiceImpl {
         self.handle_changed(id, info).await
     }
 }
+
+impl NodeScheduleServiceImpl {
+    /// Release the resources reserved for the node on its queue once it is finished, retried
+    /// tasks keep the reservation of their node.
+    async fn release_queue(&self, id: Uuid) -> anyhow::Result<()> {
+        if let Some(queue_id) = self.node_repo.get_by_id(id).await?.queue_id {
+            self.queue_resource_service.release_node(queue_id, id).await;
+        }
+        Ok(())
+    }
+}
@@ -6,7 +6,10 @@ use alice_architecture::{
 use async_trait::async_trait;
 use domain_workflow::{
     model::{
-        entity::task::{DbTask, TaskStatus},
+        entity::{
+            node_instance::DbNodeInstance,
+            task::{DbTask, Task, TaskAttempt, TaskStatus},
+        },
         vo::{
             msg::{
                 ChangeMsg, Info, NodeChangeInfo, NodeStatusChange, TaskChangeInfo, TaskStatusChange,
@@ -14,14 +17,18 @@ use domain_workflow::{
             task_dto::{self, result::TaskUsedResource, TaskCommand},
         },
     },
-    repository::TaskRepo,
-    service::ScheduleService,
+    repository::{NodeInstanceRepo, TaskRepo, WorkflowInstanceRepo},
+    service::{QueueResourceService, QuotaService, ScheduleService},
 };
 use uuid::Uuid;
 
 #[derive(typed_builder::TypedBuilder)]
 pub struct TaskScheduleServiceImpl {
     task_repo: Arc<dyn TaskRepo>,
+    node_repo: Arc<dyn NodeInstanceRepo>,
+    flow_repo: Arc<dyn WorkflowInstanceRepo>,
+    queue_resource_service: Arc<dyn QueueResourceService>,
+    quota_service: Arc<dyn QuotaService>,
     mq_producer_task: Arc<dyn MessageQueueProducerTemplate<task_dto::Task>>,
     status_mq_producer: Arc<dyn MessageQueueProducerTemplate<ChangeMsg>>,
     status_mq_topic: String,
@@ -135,6 +142,9 @@ impl ScheduleService for TaskScheduleServiceImpl {
                 // empty, report node as Completed.
 
                 let node_instance_id = self.task_repo.get_by_id(id).await?.node_instance_id;
+                if let Some(used) = &info.used_resources {
+                    self.quota_service.record_spend(node_instance_id, used).await?;
+                }
                 if info.used_resources.is_some() {
                     self.status_mq_producer
                         .send_object(
@@ -207,9 +217,17 @@ impl ScheduleService for TaskScheduleServiceImpl {
             }
 
             TaskStatusChange::Failed => {
-                // Report node as Failed.
+                // Firstly, try to retry the task with the node's retry policy.
+                // If not retried, report node as Failed.
 
-                let node_id = self.task_repo.get_by_id(id).await?.node_instance_id;
+                let task = self.task_repo.get_by_id(id).await?;
+                let node_id = task.node_instance_id;
+                if let Some(used) = &info.used_resources {
+                    self.quota_service.record_spend(node_id, used).await?;
+                }
+                if self.retry(task, info.message.to_owned()).await? {
+                    return Ok(());
+                }
                 self.status_mq_producer
                     .send_object(
                         &ChangeMsg {
@@ -364,3 +382,146 @@ impl ScheduleService for TaskScheduleServiceImpl {
         self.handle_changed(id, info).await
     }
 }
+
+impl TaskScheduleServiceImpl {
+    /// Start tasks whose retry backoff has ended, returns the count of them.
+    pub async fn run_due_retries(&self) -> anyhow::Result<usize> {
+        let ids = self.task_repo.take_due_retries(chrono::Utc::now()).await?;
+        for id in ids.iter() {
+            self.status_mq_producer
+                .send_object(
+                    &ChangeMsg {
+                        id: *id,
+                        info: Info::Task(TaskChangeInfo {
+                            status: TaskStatusChange::Running { is_resumed: false },
+                            ..Default::default()
+                        }),
+                    },
+                    &self.status_mq_topic,
+                )
+                .await?;
+        }
+        Ok(ids.len())
+    }
+
+    /// Retry a failed task if its node's retry policy allows, returns whether it is retried.
+    ///
+    /// The task is kept Failed during backoff, so that the other tasks of the node won't go on.
+    /// The retry is started by [`Self::run_due_retries`] when the backoff ends.
+    async fn retry(&self, mut task: Task, message: Option<String>) -> anyhow::Result<bool> {
+        let node = self.node_repo.get_by_id(task.node_instance_id).await?;
+        let flow = self.flow_repo.get_by_id(node.flow_instance_id).await?;
+        let node_spec = flow.spec.node(node.batch_parent_id.unwrap_or(node.id));
+        let policy = match &node_spec.retry_policy {
+            Some(policy) => policy,
+            None => return Ok(false),
+        };
+        let attempts = task.attempts.len() + 1;
+        if !policy.should_retry(attempts, &task.r#type, message.as_deref())? {
+            return Ok(false);
+        }
+
+        task.attempts.push(TaskAttempt {
+            queue_topic: task.queue_topic.to_owned(),
+            message,
+            failed_time: chrono::Utc::now(),
+        });
+        self.task_repo
+            .update(DbTask {
+                id: DbField::Unchanged(task.id),
+                attempts: DbField::Set(task.attempts.to_owned()),
+                ..Default::default()
+            })
+            .await?;
+        self.task_repo.save_changed().await?;
+
+        let tasks = self.task_repo.get_tasks_by_node_id(node.id).await?;
+        let mut retry_tasks_ids = vec![task.id];
+        // Only reselect queue when no other task of the node is on the agent, and as deployed
+        // software and downloaded files are left on the old queue, all tasks must be rerun.
+        if policy.reselect_queue
+            && !tasks.iter().any(|t| {
+                matches!(
+                    t.status,
+                    TaskStatus::Queuing
+                        | TaskStatus::Running
+                        | TaskStatus::Pausing
+                        | TaskStatus::Resuming
+                        | TaskStatus::Cancelling
+                )
+            })
+        {
+            // Keep the current queue if no other queue is available, the node is still retried.
+            let selection = self
+                .queue_resource_service
+                .select_queue(
+                    &node_spec.scheduling_strategy,
+                    node_spec.effective_requirements(),
+                )
+                .await?;
+            let reserved = match selection.queue {
+                Some(queue) if node.queue_id != Some(queue.id) => match self
+                    .queue_resource_service
+                    .reserve_node(queue.id, node.id, node_spec.effective_requirements())
+                    .await
+                {
+                    Ok(()) => Some(queue),
+                    Err(e) => {
+                        tracing::warn!("Failed to reselect queue for task {}: {e}", task.id);
+                        None
+                    }
+                },
+                Some(_) => None,
+                None => {
+                    tracing::warn!(
+                        "Failed to reselect queue for task {}: {}",
+                        task.id,
+                        selection.message()
+                    );
+                    None
+                }
+            };
+            if let Some(queue) = reserved {
+                if let Some(old_queue_id) = node.queue_id {
+                    self.queue_resource_service.release_node(old_queue_id, node.id).await;
+                }
+                self.node_repo
+                    .update(DbNodeInstance {
+                        id: DbField::Unchanged(node.id),
+                        queue_id: DbField::Set(Some(queue.id)),
+                        ..Default::default()
+                    })
+                    .await?;
+                self.node_repo.save_changed().await?;
+                for t in tasks.iter() {
+                    self.task_repo
+                        .update(DbTask {
+                            id: DbField::Unchanged(t.id),
+                            status: DbField::Set(TaskStatus::Standby),
+                            ..Default::default()
+                        })
+                        .await?;
+                }
+                self.task_repo.save_changed().await?;
+                let first_task = tasks.first().unwrap_or(&task);
+                retry_tasks_ids =
+                    tasks.iter().filter(|t| t.r#type == first_task.r#type).map(|t| t.id).collect();
+            }
+        }
+
+        // The scheduler polls for due retries, so that a pending retry survives restarts.
+        let next_attempt_at =
+            chrono::Utc::now() + chrono::Duration::from_std(policy.backoff.delay(attempts))?;
+        for id in retry_tasks_ids {
+            self.task_repo
+                .update(DbTask {
+                    id: DbField::Unchanged(id),
+                    next_attempt_at: DbField::Set(Some(next_attempt_at)),
+                    ..Default::default()
+                })
+                .await?;
+        }
+        self.task_repo.save_changed().await?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,259 @@
+use std::{collections::HashSet, sync::Arc};
+
+use alice_architecture::message_queue::producer::MessageQueueProducerTemplate;
+use async_trait::async_trait;
+use domain_workflow::{
+    model::{
+        entity::{node_instance::NodeInstanceStatus, queue::QueueSelection, task::TaskStatus},
+        vo::{
+            backlog::{BacklogEntry, Preemption},
+            msg::{ChangeMsg, Info, NodeChangeInfo, NodeStatusChange},
+        },
+    },
+    repository::{NodeInstanceRepo, SchedulerBacklogRepo, TaskRepo, WorkflowInstanceRepo},
+    service::{QueueResourceService, QuotaService, SchedulerBacklogService},
+};
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+#[derive(TypedBuilder)]
+pub struct SchedulerBacklogServiceImpl {
+    backlog_repo: Arc<dyn SchedulerBacklogRepo>,
+    node_repo: Arc<dyn NodeInstanceRepo>,
+    flow_repo: Arc<dyn WorkflowInstanceRepo>,
+    task_repo: Arc<dyn TaskRepo>,
+    queue_resource_service: Arc<dyn QueueResourceService>,
+    quota_service: Arc<dyn QuotaService>,
+    status_mq_producer: Arc<dyn MessageQueueProducerTemplate<ChangeMsg>>,
+    status_mq_topic: String,
+}
+
+#[async_trait]
+impl SchedulerBacklogService for SchedulerBacklogServiceImpl {
+    async fn drain(&self) -> anyhow::Result<()> {
+        self.resume_preempted().await?;
+
+        let mut entries = self.backlog_repo.get_all().await?;
+        BacklogEntry::sort(&mut entries);
+
+        // Queue capacity is only reserved when a dispatched node is handled, so at most one node
+        // is dispatched to each queue in a drain, the others wait for the next one.
+        let mut dispatched_queues = HashSet::new();
+        for entry in entries {
+            let node = match self.node_repo.get_by_id(entry.node_id).await {
+                Ok(node) => node,
+                Err(e) => {
+                    tracing::warn!("Drop backlog node {}: {e}", entry.node_id);
+                    self.backlog_repo.remove(entry.node_id).await?;
+                    continue;
+                }
+            };
+            if !matches!(node.status, NodeInstanceStatus::Pending) {
+                // Terminated or paused while waiting.
+                self.backlog_repo.remove(entry.node_id).await?;
+                continue;
+            }
+
+            let node_spec = self.node_repo.get_node_spec(entry.node_id).await?;
+            match self
+                .quota_service
+                .check_node(entry.node_id, node_spec.effective_requirements())
+                .await?
+            {
+                Some(violation) if violation.can_wait => continue,
+                // Quota is used up, dispatch it so that it is reported as Failed.
+                Some(_) => {}
+                None => {
+                    let selection = self
+                        .queue_resource_service
+                        .select_queue(
+                            &node_spec.scheduling_strategy,
+                            node_spec.effective_requirements(),
+                        )
+                        .await?;
+                    match &selection.queue {
+                        Some(queue) => {
+                            if !dispatched_queues.insert(queue.id) {
+                                continue;
+                            }
+                        }
+                        None if selection.can_wait() => {
+                            self.preempt(&entry, &selection).await?;
+                            continue;
+                        }
+                        // No queue will ever fit, dispatch it so that it is reported as Failed.
+                        None => {}
+                    }
+                }
+            }
+
+            // Another instance may be draining at the same time, only the one removed it
+            // dispatches the node.
+            if self.backlog_repo.remove(entry.node_id).await?.is_none() {
+                continue;
+            }
+            self.send_node_status(entry.node_id, NodeStatusChange::Pending, None).await?;
+        }
+        Ok(())
+    }
+}
+
+impl SchedulerBacklogServiceImpl {
+    /// Pause the lowest priority running node on the full queues for the waiting node, if its
+    /// priority is lower and all its active tasks can be paused. At most one node is preempted
+    /// for a waiting node.
+    async fn preempt(
+        &self,
+        entry: &BacklogEntry,
+        selection: &QueueSelection,
+    ) -> anyhow::Result<()> {
+        let preemptions = self.backlog_repo.get_preemptions().await?;
+        if preemptions.iter().any(|el| el.for_node_id == entry.node_id) {
+            return Ok(());
+        }
+        let queue_ids = selection
+            .rejections
+            .iter()
+            .filter(|el| el.full)
+            .map(|el| el.queue_id)
+            .collect::<Vec<_>>();
+
+        let mut candidate: Option<(i32, Uuid, Option<Uuid>)> = None;
+        for node in self.node_repo.get_running_nodes_by_queue_ids(&queue_ids).await? {
+            if preemptions.iter().any(|el| el.node_id == node.id) {
+                continue;
+            }
+            let flow = self.flow_repo.get_by_id(node.flow_instance_id).await?;
+            let priority = flow.spec.node_priority(node.batch_parent_id.unwrap_or(node.id));
+            if priority >= entry.priority || matches!(candidate, Some((p, ..)) if p <= priority) {
+                continue;
+            }
+            let tasks = self.task_repo.get_tasks_by_node_id(node.id).await?;
+            let active_tasks = tasks
+                .iter()
+                .filter(|t| matches!(t.status, TaskStatus::Running | TaskStatus::Queuing))
+                .collect::<Vec<_>>();
+            if active_tasks.is_empty() || !active_tasks.iter().all(|t| t.r#type.is_pausable()) {
+                continue;
+            }
+            candidate = Some((priority, node.id, node.queue_id));
+        }
+
+        let (node_id, queue_id) = match candidate {
+            Some((_, node_id, queue_id)) => (node_id, queue_id),
+            None => return Ok(()),
+        };
+        self.backlog_repo
+            .push_preemption(&Preemption {
+                node_id,
+                for_node_id: entry.node_id,
+                preempted_time: chrono::Utc::now(),
+            })
+            .await?;
+        self.send_node_status(
+            node_id,
+            NodeStatusChange::Pausing,
+            Some(format!(
+                "Preempted by node {} with higher priority.",
+                entry.node_id
+            )),
+        )
+        .await?;
+        // The paused node gives up its quota and queue capacity to the waiting node, agents keep
+        // reporting the usage until the tasks are paused.
+        if let Some(queue_id) = queue_id {
+            self.queue_resource_service.release_node(queue_id, node_id).await;
+        }
+        self.quota_service.node_finished(node_id).await
+    }
+
+    /// Take the quota and queue capacity of a preempted node back before resuming it, Ok(false)
+    /// when they are taken by others for now.
+    async fn reacquire(&self, node_id: Uuid) -> anyhow::Result<bool> {
+        let node = self.node_repo.get_by_id(node_id).await?;
+        let node_spec = self.node_repo.get_node_spec(node_id).await?;
+        let requirements = node_spec.effective_requirements();
+        if let Some(violation) = self.quota_service.start_node(node_id, requirements).await? {
+            tracing::info!(
+                "Preempted node {node_id} waits to resume: {}",
+                violation.reason
+            );
+            return Ok(false);
+        }
+        let queue_id = match node.queue_id {
+            Some(queue_id) => queue_id,
+            None => return Ok(true),
+        };
+        if let Err(e) =
+            self.queue_resource_service.reserve_node(queue_id, node_id, requirements).await
+        {
+            tracing::info!("Preempted node {node_id} waits to resume: {e}");
+            self.quota_service.cancel_node(node_id).await?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Resume the preempted nodes whose preempting node is finished.
+    async fn resume_preempted(&self) -> anyhow::Result<()> {
+        for preemption in self.backlog_repo.get_preemptions().await? {
+            let is_finished = match self.node_repo.get_by_id(preemption.for_node_id).await {
+                Ok(node) => !matches!(
+                    node.status,
+                    NodeInstanceStatus::Pending
+                        | NodeInstanceStatus::Running
+                        | NodeInstanceStatus::Pausing
+                        | NodeInstanceStatus::Paused
+                        | NodeInstanceStatus::Resuming
+                        | NodeInstanceStatus::Terminating
+                ),
+                Err(_) => true,
+            };
+            if !is_finished {
+                continue;
+            }
+            if self.backlog_repo.remove_preemption(preemption.node_id).await?.is_none() {
+                continue;
+            }
+            match self.node_repo.get_by_id(preemption.node_id).await?.status {
+                NodeInstanceStatus::Paused => {
+                    if !self.reacquire(preemption.node_id).await? {
+                        self.backlog_repo.push_preemption(&preemption).await?;
+                        continue;
+                    }
+                    self.send_node_status(preemption.node_id, NodeStatusChange::Resuming, None)
+                        .await?
+                }
+                // Not paused yet, resume it in the next drain.
+                NodeInstanceStatus::Pausing => {
+                    self.backlog_repo.push_preemption(&preemption).await?
+                }
+                // Terminated or resumed by user.
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_node_status(
+        &self,
+        node_id: Uuid,
+        status: NodeStatusChange,
+        message: Option<String>,
+    ) -> anyhow::Result<()> {
+        self.status_mq_producer
+            .send_object(
+                &ChangeMsg {
+                    id: node_id,
+                    info: Info::Node(NodeChangeInfo {
+                        status,
+                        message,
+                        ..Default::default()
+                    }),
+                },
+                &self.status_mq_topic,
+            )
+            .await?;
+        Ok(())
+    }
+}
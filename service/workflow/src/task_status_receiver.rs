@@ -8,6 +8,7 @@ use domain_workflow::{
         msg::{ChangeMsg, Info, TaskChangeInfo},
         task_dto::result::TaskResult,
     },
+    repository::TaskRepo,
     service::{QueueResourceService, TaskStatusReceiveService},
 };
 use typed_builder::TypedBuilder;
@@ -18,6 +19,7 @@ pub struct TaskStatusReceiveServiceImpl {
     status_mq_producer: Arc<dyn MessageQueueProducerTemplate<ChangeMsg>>,
     status_mq_topic: String,
     queue_resource_service: Arc<dyn QueueResourceService>,
+    task_repo: Arc<dyn TaskRepo>,
     queue_id: Option<Uuid>,
 }
 
@@ -42,12 +44,10 @@ impl TaskStatusReceiveService for TaskStatusReceiveServiceImpl {
                     .await?;
             }
             Err(_) => {
-                self.queue_resource_service
-                    .task_started(
-                        self.queue_id
-                            .context("No queue id when TaskStatusReceiveService use it.")?,
-                    )
-                    .await?
+                let queue_id =
+                    self.queue_id.context("No queue id when TaskStatusReceiveService use it.")?;
+                let node_id = self.task_repo.get_by_id(result.id).await?.node_instance_id;
+                self.queue_resource_service.task_started(queue_id, node_id).await?
             }
         }
         Ok(())
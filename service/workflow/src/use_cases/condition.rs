@@ -0,0 +1,129 @@
+use std::{collections::HashMap, sync::Arc};
+
+use alice_architecture::{
+    message_queue::producer::MessageQueueProducerTemplate, repository::DBRepository,
+};
+use async_trait::async_trait;
+use domain_storage::model::entity::TextStorage;
+use domain_workflow::{
+    model::{
+        entity::{
+            node_instance::{NodeInstanceKind, NodeInstanceStatus},
+            workflow_instance::NodeSpec,
+        },
+        vo::{
+            msg::{ChangeMsg, Info, NodeChangeInfo, NodeStatusChange},
+            NodeInputSlotKind, NodeKind,
+        },
+    },
+    repository::{NodeInstanceRepo, WorkflowInstanceRepo},
+    service::UsecaseParseService,
+};
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+/// 条件分支用例解析微服务
+#[derive(TypedBuilder)]
+pub struct ConditionUsecaseServiceImpl {
+    node_repo: Arc<dyn NodeInstanceRepo>,
+    flow_repo: Arc<dyn WorkflowInstanceRepo>,
+    text_storage_repository: Arc<dyn DBRepository<TextStorage>>,
+    status_mq_producer: Arc<dyn MessageQueueProducerTemplate<ChangeMsg>>,
+    status_mq_topic: String,
+}
+
+#[async_trait]
+impl UsecaseParseService for ConditionUsecaseServiceImpl {
+    /// 处理用例
+    /// 根据输入文本选择分支，跳过未激活分支上的节点，然后完成该节点
+    async fn handle_usecase(&self, node_spec: NodeSpec) -> anyhow::Result<()> {
+        let data = match &node_spec.kind {
+            NodeKind::Condition { data } => data,
+            _ => anyhow::bail!("Unreachable node kind!"),
+        };
+
+        let flow = self.flow_repo.get_by_node_id(node_spec.id).await?;
+        let completed_node_spec =
+            flow.produce_node_spec_by_complete_node_inputs(&node_spec, None)?;
+        let mut texts = HashMap::new();
+        for input_slot in completed_node_spec.input_slots.iter() {
+            if let NodeInputSlotKind::Text {
+                contents: Some(contents),
+                ..
+            } = &input_slot.kind
+            {
+                if let Some(key) = contents.first() {
+                    let value = self.text_storage_repository.get_by_id(*key).await?.value;
+                    texts.insert(input_slot.descriptor.to_owned(), value);
+                }
+            }
+        }
+
+        let selected_to_ids = match data.select(&texts) {
+            Ok(x) => x,
+            Err(e) => {
+                self.send_status(
+                    node_spec.id,
+                    NodeStatusChange::Failed,
+                    Some(format!("Failed to evaluate condition: {e}")),
+                )
+                .await?;
+                return Ok(());
+            }
+        };
+
+        let skipped_nodes_ids = self
+            .node_repo
+            .get_all_workflow_instance_nodes(flow.id)
+            .await?
+            .into_iter()
+            .filter(|n| matches!(n.status, NodeInstanceStatus::Skipped))
+            .map(|n| n.id)
+            .collect::<Vec<_>>();
+        let inactive_to_ids = data.inactive_to_ids(&selected_to_ids);
+
+        // Skipped nodes must be reported before the condition node completes, so that they won't
+        // be scheduled as next entry nodes.
+        for id in flow.nodes_to_skip(node_spec.id, &inactive_to_ids, &skipped_nodes_ids) {
+            self.send_status(id, NodeStatusChange::Skipped, None).await?;
+        }
+        self.send_status(
+            node_spec.id,
+            NodeStatusChange::Completed,
+            Some(format!("Activated nodes: {selected_to_ids:?}")),
+        )
+        .await
+    }
+
+    fn get_service_type(&self) -> NodeInstanceKind {
+        NodeInstanceKind::Condition
+    }
+
+    async fn get_cmd(&self, _node_id: Uuid) -> anyhow::Result<Option<String>> {
+        unimplemented!()
+    }
+}
+
+impl ConditionUsecaseServiceImpl {
+    async fn send_status(
+        &self,
+        id: Uuid,
+        status: NodeStatusChange,
+        message: Option<String>,
+    ) -> anyhow::Result<()> {
+        self.status_mq_producer
+            .send_object(
+                &ChangeMsg {
+                    id,
+                    info: Info::Node(NodeChangeInfo {
+                        status,
+                        message,
+                        ..Default::default()
+                    }),
+                },
+                &self.status_mq_topic,
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("send message failed: {}", e))
+    }
+}
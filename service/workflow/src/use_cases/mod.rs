@@ -1,3 +1,4 @@
+mod condition;
 mod milestone;
 mod no_action;
 // mod script;
@@ -5,6 +6,7 @@ mod software_computing;
 
 #[rustfmt::skip]
 pub use {
+    condition::ConditionUsecaseServiceImpl,
     milestone::MilestoneUsecaseServiceImpl,
     no_action::NoActionUsecaseServiceImpl,
     // script::ScriptUsecaseServiceImpl,
@@ -49,11 +49,38 @@ impl UsecaseParseService for ScriptUsecaseServiceImpl {
             anyhow::bail!("Unreachable node kind.");
         };
 
-        let queue_id = self
+        let queue_id = match self
             .queue_resource_service
-            .get_queue(node_spec.id, &node_spec.scheduling_strategy)
+            .get_queue(
+                node_spec.id,
+                &node_spec.scheduling_strategy,
+                node_spec.effective_requirements(),
+            )
             .await?
-            .id;
+        {
+            Some(queue) => queue.id,
+            // Waiting in the scheduler backlog.
+            None => return Ok(()),
+        };
+        let result = self.dispatch(&task, queue_id).await;
+        if result.is_err() {
+            self.queue_resource_service.release_node(queue_id, node_spec.id).await;
+        }
+        result
+    }
+
+    fn get_service_type(&self) -> NodeInstanceKind {
+        NodeInstanceKind::Script
+    }
+
+    async fn get_cmd(&self, _node_id: String) -> anyhow::Result<Option<String>> {
+        unimplemented!()
+    }
+}
+
+impl ScriptUsecaseServiceImpl {
+    /// 记录节点的队列并发送任务，失败时由调用方释放节点在队列上预留的资源
+    async fn dispatch(&self, task: &Task, queue_id: Uuid) -> anyhow::Result<()> {
         let mut node_instance = self.node_instance_repository.get_by_id(task.id).await?;
         node_instance.queue_id = Some(queue_id);
         self.node_instance_repository
@@ -64,14 +91,6 @@ impl UsecaseParseService for ScriptUsecaseServiceImpl {
             })
             .await?;
         self.node_instance_repository.save_changed().await?;
-        self.task_distribution_service.send_task(&task, queue_id).await
-    }
-
-    fn get_service_type(&self) -> NodeInstanceKind {
-        NodeInstanceKind::Script
-    }
-
-    async fn get_cmd(&self, _node_id: String) -> anyhow::Result<Option<String>> {
-        unimplemented!()
+        self.task_distribution_service.send_task(task, queue_id).await
     }
 }
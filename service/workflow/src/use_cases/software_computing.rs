@@ -5,21 +5,24 @@ use alice_architecture::{
 use anyhow::Context;
 use async_trait::async_trait;
 use domain_content_repo::{
-    model::vo::abilities::{
-        common::FileKind,
-        software_computing::{
-            software::{
-                materials::inputs::{Argument, Environment},
-                SoftwareSpec as RepoSoftwareSpec,
-            },
-            usecase::{
-                collected_out::{
-                    CollectFrom as RepoCollectFrom, CollectRule as RepoCollectRule,
-                    CollectTo as RepoCollectTo,
+    model::vo::{
+        abilities::{
+            common::FileKind,
+            software_computing::{
+                software::{
+                    materials::inputs::{Argument, Environment},
+                    SoftwareSpec as RepoSoftwareSpec,
+                },
+                usecase::{
+                    collected_out::{
+                        CollectFrom as RepoCollectFrom, CollectRule as RepoCollectRule,
+                        CollectTo as RepoCollectTo,
+                    },
+                    spec::*,
                 },
-                spec::*,
             },
         },
+        SoftwareComputingUsecase, SoftwareDependency,
     },
     service::SoftwareComputingUsecaseInfoService,
 };
@@ -38,7 +41,7 @@ use domain_workflow::{
                 ExecuteUsecase, FacilityKind, FileTransmitKind, StartTaskBody, StdInKind,
                 UploadFile,
             },
-            NodeInputSlotKind, NodeKind,
+            NodeInputSlotKind, NodeKind, SoftwareUsecaseComputing,
         },
     },
     repository::*,
@@ -103,11 +106,82 @@ struct FormatFill {
 #[async_trait]
 impl UsecaseParseService for SoftwareComputingUsecaseServiceImpl {
     async fn handle_usecase(&self, node_spec: NodeSpec) -> anyhow::Result<()> {
-        let queue = self
+        // 节点没有覆盖资源需求时采取用例包规定的，提交前的实例没有记录用例包的资源需求
+        let requirements = match node_spec.effective_requirements() {
+            Some(requirements) => Some(requirements.to_owned()),
+            None => {
+                let data = match &node_spec.kind {
+                    NodeKind::SoftwareUsecaseComputing { data } => data,
+                    _ => anyhow::bail!("Unreachable node kind!"),
+                };
+                self.get_computing_usecase(data)
+                    .await?
+                    .usecase_spec
+                    .requirements
+                    .map(Into::into)
+            }
+        };
+        let queue = match self
             .queue_resource_service
-            .get_queue(node_spec.id, &node_spec.scheduling_strategy)
-            .await?;
+            .get_queue(
+                node_spec.id,
+                &node_spec.scheduling_strategy,
+                requirements.as_ref(),
+            )
+            .await?
+        {
+            Some(queue) => queue,
+            // Waiting in the scheduler backlog.
+            None => return Ok(()),
+        };
+        let result = self.dispatch(node_spec.to_owned(), &queue).await;
+        if result.is_err() {
+            self.queue_resource_service.release_node(queue.id, node_spec.id).await;
+        }
+        result
+    }
 
+    fn get_service_type(&self) -> NodeInstanceKind {
+        NodeInstanceKind::SoftwareUsecaseComputing
+    }
+
+    async fn get_cmd(&self, node_id: Uuid) -> anyhow::Result<Option<String>> {
+        let flow_id = self.node_repo.get_by_id(node_id).await?.flow_instance_id;
+        let flow = self.flow_repo.get_by_id(flow_id).await?;
+        let node_spec = flow.spec.node(node_id).to_owned();
+        let tasks = self.parse_start_bodys(node_spec).await?;
+        let name_and_arguments = tasks.iter().find_map(|task| {
+            if let StartTaskBody::ExecuteUsecase(ExecuteUsecase {
+                name, arguments, ..
+            }) = &task
+            {
+                Some((name, arguments))
+            } else {
+                None
+            }
+        });
+        Ok(match name_and_arguments {
+            Some((name, arguments)) => {
+                let arg_str = arguments.join(" ");
+                Some(if arg_str.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{name} {arg_str}")
+                })
+            }
+            None => None,
+        })
+    }
+}
+
+impl SoftwareComputingUsecaseServiceImpl {
+    /// 将节点解析为任务并发送到队列，失败时由调用方释放节点在队列上预留的资源
+    ///
+    /// # 参数
+    ///
+    /// * `node_spec` - 节点
+    /// * `queue` - 选中的队列
+    async fn dispatch(&self, node_spec: NodeSpec, queue: &entity::Queue) -> anyhow::Result<()> {
         let start_bodys = self.parse_start_bodys(node_spec.to_owned()).await?;
         let mut tasks = vec![];
         for start_body in start_bodys {
@@ -151,40 +225,55 @@ impl UsecaseParseService for SoftwareComputingUsecaseServiceImpl {
         Ok(())
     }
 
-    fn get_service_type(&self) -> NodeInstanceKind {
-        NodeInstanceKind::SoftwareUsecaseComputing
+    /// 根据用例包 id、软件包 id，获取用例分析数据，提交时固定了包哈希的使用相同内容的包
+    ///
+    /// # 参数
+    ///
+    /// * `data` - 软件用例节点数据
+    async fn get_computing_usecase(
+        &self,
+        data: &SoftwareUsecaseComputing,
+    ) -> anyhow::Result<SoftwareComputingUsecase> {
+        match (&data.software_package_hash, &data.usecase_package_hash) {
+            (Some(software_hash), Some(usecase_hash)) => {
+                self.computing_usecase_repo
+                    .get_pinned_computing_usecase(
+                        data.software_version_id,
+                        software_hash,
+                        data.usecase_version_id,
+                        usecase_hash,
+                    )
+                    .await
+            }
+            _ => {
+                self.computing_usecase_repo
+                    .get_computing_usecase(data.software_version_id, data.usecase_version_id)
+                    .await
+            }
+        }
     }
 
-    async fn get_cmd(&self, node_id: Uuid) -> anyhow::Result<Option<String>> {
-        let flow_id = self.node_repo.get_by_id(node_id).await?.flow_instance_id;
-        let flow = self.flow_repo.get_by_id(flow_id).await?;
-        let node_spec = flow.spec.node(node_id).to_owned();
-        let tasks = self.parse_start_bodys(node_spec).await?;
-        let name_and_arguments = tasks.iter().find_map(|task| {
-            if let StartTaskBody::ExecuteUsecase(ExecuteUsecase {
-                name, arguments, ..
-            }) = &task
-            {
-                Some((name, arguments))
-            } else {
-                None
+    /// 获取软件包依赖的闭包，提交时固定了依赖的使用相同版本与内容的包，不再重新解析
+    ///
+    /// # 参数
+    ///
+    /// * `data` - 软件用例节点数据
+    async fn get_software_dependencies(
+        &self,
+        data: &SoftwareUsecaseComputing,
+    ) -> anyhow::Result<Vec<SoftwareDependency>> {
+        match &data.pinned_dependencies {
+            Some(pinned) => {
+                self.computing_usecase_repo.get_pinned_software_dependencies(pinned).await
             }
-        });
-        Ok(match name_and_arguments {
-            Some((name, arguments)) => {
-                let arg_str = arguments.join(" ");
-                Some(if arg_str.is_empty() {
-                    name.to_string()
-                } else {
-                    format!("{name} {arg_str}")
-                })
+            None => {
+                self.computing_usecase_repo
+                    .get_software_dependencies(data.software_version_id)
+                    .await
             }
-            None => None,
-        })
+        }
     }
-}
 
-impl SoftwareComputingUsecaseServiceImpl {
     /// 解析节点数据，返回任务
     ///
     /// # 参数
@@ -196,16 +285,9 @@ impl SoftwareComputingUsecaseServiceImpl {
             _ => anyhow::bail!("Unreachable node kind!"),
         };
 
-        let (usecase_version_id, software_version_id) = (
-            data.usecase_version_id.to_owned(),
-            data.software_version_id.to_owned(),
-        );
-
-        // 根据用例包 id、软件包 id，获取用例分析数据
-        let computing_usecase = self
-            .computing_usecase_repo
-            .get_computing_usecase(software_version_id, usecase_version_id)
-            .await?;
+        let computing_usecase = self.get_computing_usecase(data).await?;
+        // 软件包依赖的闭包，依赖在依赖它的软件包之前
+        let dependencies = self.get_software_dependencies(data).await?;
 
         let usecase_spec = computing_usecase.usecase_spec;
         let argument_materials = computing_usecase.arguments;
@@ -888,31 +970,11 @@ impl SoftwareComputingUsecaseServiceImpl {
             })
             .collect();
 
-        let (software_name, version, require_install_arguments) = match software_spec.to_owned() {
-            RepoSoftwareSpec::Spack {
-                name,
-                argument_list,
-            } => (
-                name,
-                argument_list.first().cloned().unwrap_or_default().replace('@', ""),
-                argument_list,
-            ),
-            RepoSoftwareSpec::Singularity { image, tag } => (image, tag.to_owned(), vec![tag]),
-        };
-
-        if !self
-            .software_block_list_repository
-            .is_software_version_blocked(&software_name, &version)
-            .await?
-            && !self
-                .installed_software_repository
-                .is_software_satisfied(&software_name, &require_install_arguments)
-                .await?
-        {
-            tasks.push(StartTaskBody::DeploySoftware(DeploySoftware {
-                facility_kind: FacilityKind::from(software_spec.to_owned()),
-            }));
+        // 先部署依赖，再部署软件本身
+        for dependency in dependencies.iter() {
+            tasks.extend(self.deploy_software_task(&dependency.software_spec).await?);
         }
+        tasks.extend(self.deploy_software_task(&software_spec).await?);
 
         for download_file in download_files {
             tasks.push(StartTaskBody::DownloadFile(download_file));
@@ -922,6 +984,10 @@ impl SoftwareComputingUsecaseServiceImpl {
             arguments,
             environments,
             facility_kind: FacilityKind::from(software_spec.to_owned()),
+            dependency_facility_kinds: dependencies
+                .into_iter()
+                .map(|el| FacilityKind::from(el.software_spec))
+                .collect(),
             std_in,
             requirements: override_requirements
                 .map(|r| r.into())
@@ -938,6 +1004,76 @@ impl SoftwareComputingUsecaseServiceImpl {
         Ok(tasks)
     }
 
+    /// 软件未被禁用且未安装满足要求的版本时，返回部署软件任务
+    ///
+    /// # 参数
+    ///
+    /// * `software_spec` - 软件规格
+    async fn deploy_software_task(
+        &self,
+        software_spec: &RepoSoftwareSpec,
+    ) -> anyhow::Result<Option<StartTaskBody>> {
+        let (software_name, version, require_install_arguments) = match software_spec.to_owned() {
+            RepoSoftwareSpec::Spack {
+                name,
+                argument_list,
+            } => (
+                name,
+                argument_list.first().cloned().unwrap_or_default().replace('@', ""),
+                argument_list,
+            ),
+            RepoSoftwareSpec::Singularity { image, tag }
+            | RepoSoftwareSpec::Apptainer { image, tag } => (image, tag.to_owned(), vec![tag]),
+            RepoSoftwareSpec::Oci { image, digest } => (image, digest.to_owned(), vec![digest]),
+            // conda 环境没有单一版本，以空版本查找环境名
+            RepoSoftwareSpec::Conda { name, packages, .. } => (name, String::new(), packages),
+            RepoSoftwareSpec::Module { name, version, .. } => {
+                (name, version.to_owned(), vec![version])
+            }
+        };
+
+        // conda 环境中任一包被禁用时，整个环境也被禁用
+        let mut blocked_lookups = vec![(software_name.to_owned(), version)];
+        if let RepoSoftwareSpec::Conda { packages, .. } = software_spec {
+            blocked_lookups.extend(packages.iter().map(|package| Self::conda_package(package)));
+        }
+        for (name, version) in blocked_lookups.iter() {
+            if self
+                .software_block_list_repository
+                .is_software_version_blocked(name, version)
+                .await?
+            {
+                return Ok(None);
+            }
+        }
+
+        if !self
+            .installed_software_repository
+            .is_software_satisfied(&software_name, &require_install_arguments)
+            .await?
+        {
+            return Ok(Some(StartTaskBody::DeploySoftware(DeploySoftware {
+                facility_kind: FacilityKind::from(software_spec.to_owned()),
+            })));
+        }
+        Ok(None)
+    }
+
+    /// 将 conda 包规格（如 `numpy=1.26`、`numpy==1.26`）拆分为包名和版本，未指定版本时版本为空
+    ///
+    /// # 参数
+    ///
+    /// * `package` - conda 包规格
+    fn conda_package(package: &str) -> (String, String) {
+        match package.split_once('=') {
+            Some((name, version)) => (
+                name.trim().to_owned(),
+                version.trim_start_matches('=').trim().to_owned(),
+            ),
+            None => (package.trim().to_owned(), String::new()),
+        }
+    }
+
     /// 返回模板填充完毕后的内容
     ///
     /// # 参数